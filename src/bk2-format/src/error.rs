@@ -1,10 +1,24 @@
 use thiserror::Error;
 
+use crate::header::Bk2HeaderError;
+
 #[derive(Error, Debug)]
 pub enum Bk2Error {
     #[error("IO error: {0}")]
-    IoError(std::io::Error),
+    IoError(#[from] std::io::Error),
 
     #[error("Zip error: {0}")]
-    ZipError(zip::result::ZipError),
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error("Missing file in archive: {0}")]
+    MissingFile(&'static str),
+
+    #[error("Invalid header: {0}")]
+    Header(#[from] Bk2HeaderError),
+
+    #[error("Input log has no LogKey")]
+    MissingLogKey,
+
+    #[error("Invalid input log line: {0:?}")]
+    InvalidInputLine(String),
 }
@@ -1,9 +1,20 @@
+use std::collections::BTreeMap;
+
 use thiserror::Error;
 
 /// Errors that can occur when reading a BK2 header file.
 #[derive(Error, Debug)]
-pub enum Bk2HeaderError {}
+pub enum Bk2HeaderError {
+    #[error("Missing header key: {0}")]
+    MissingKey(&'static str),
+
+    #[error("Invalid rerecord count header: {0}")]
+    InvalidRerecordCount(std::num::ParseIntError),
+}
 
+/// The content of `Header.txt`. Keys that are not used directly (board name,
+/// firmware hashes, etc.) are kept in [`Bk2Header::get`].
+#[derive(Debug, Clone)]
 pub struct Bk2Header {
     movie_version: String,
     version: String,
@@ -13,14 +24,153 @@ pub struct Bk2Header {
     game_name: String,
     sha1: String,
     core: String,
+    others: BTreeMap<String, String>,
 }
 
 impl TryFrom<String> for Bk2Header {
     type Error = Bk2HeaderError;
 
     fn try_from(header: String) -> Result<Self, Self::Error> {
-        todo!()
+        let mut movie_version = None;
+        let mut platform = None;
+        let mut result = Self {
+            movie_version: String::new(),
+            version: String::new(),
+            rerecord_count: 0,
+            author: String::new(),
+            platform: String::new(),
+            game_name: String::new(),
+            sha1: String::new(),
+            core: String::new(),
+            others: BTreeMap::new(),
+        };
+
+        for line in header.trim_start_matches('\u{feff}').lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim().to_string();
+            match key.to_ascii_lowercase().as_str() {
+                "movieversion" => movie_version = Some(value),
+                "emuversion" => result.version = value,
+                "rerecordcount" => {
+                    result.rerecord_count = value
+                        .parse()
+                        .map_err(Bk2HeaderError::InvalidRerecordCount)?
+                }
+                "author" => result.author = value,
+                "platform" => platform = Some(value),
+                "gamename" => result.game_name = value,
+                "sha1" => result.sha1 = value,
+                "core" => result.core = value,
+                _ => {
+                    result.others.insert(key.to_string(), value);
+                }
+            }
+        }
+
+        result.movie_version = movie_version.ok_or(Bk2HeaderError::MissingKey("MovieVersion"))?;
+        result.platform = platform.ok_or(Bk2HeaderError::MissingKey("Platform"))?;
+        Ok(result)
     }
 }
 
-impl Bk2Header {}
+impl Bk2Header {
+    /// The version of the movie format, e.g. `BizHawk v2.0.0`.
+    pub fn movie_version(&self) -> &str {
+        &self.movie_version
+    }
+
+    /// The version of the emulator that recorded the movie.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn rerecord_count(&self) -> usize {
+        self.rerecord_count
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// The system ID, e.g. `NES`, `SNES` or `GEN`.
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    pub fn game_name(&self) -> &str {
+        &self.game_name
+    }
+
+    /// The SHA1 of the ROM, as an uppercase hexadecimal string.
+    pub fn sha1(&self) -> &str {
+        &self.sha1
+    }
+
+    /// The BizHawk core used for recording, e.g. `NesHawk` or `Snes9x`.
+    pub fn core(&self) -> &str {
+        &self.core
+    }
+
+    /// Whether the movie starts from the savestate stored in the archive.
+    pub fn starts_from_savestate(&self) -> bool {
+        self.get_bool("StartsFromSavestate")
+    }
+
+    /// Whether the movie starts from the SRAM stored in the archive.
+    pub fn starts_from_save_ram(&self) -> bool {
+        self.get_bool("StartsFromSaveRam")
+    }
+
+    /// Get any other header value.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.others.get(key).map(String::as_str)
+    }
+
+    fn get_bool(&self, key: &str) -> bool {
+        self.get(key)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true") || v == "1")
+    }
+}
+
+#[test]
+fn parse_header() {
+    let header = Bk2Header::try_from(
+        "MovieVersion BizHawk v2.0.0\r\n\
+         Author Some Author\r\n\
+         emuVersion Version 2.9.1\r\n\
+         Platform NES\r\n\
+         GameName Super Mario Bros.\r\n\
+         SHA1 EA343F4E445A9050D4B4FBAC2C77D0693B1D0922\r\n\
+         Core NesHawk\r\n\
+         rerecordCount 1234\r\n\
+         BoardName NROM\r\n\
+         StartsFromSavestate True\r\n"
+            .to_string(),
+    )
+    .unwrap();
+
+    assert_eq!(header.movie_version(), "BizHawk v2.0.0");
+    assert_eq!(header.author(), "Some Author");
+    assert_eq!(header.version(), "Version 2.9.1");
+    assert_eq!(header.platform(), "NES");
+    assert_eq!(header.game_name(), "Super Mario Bros.");
+    assert_eq!(header.core(), "NesHawk");
+    assert_eq!(header.rerecord_count(), 1234);
+    assert_eq!(header.get("BoardName"), Some("NROM"));
+    assert!(header.starts_from_savestate());
+    assert!(!header.starts_from_save_ram());
+}
+
+#[test]
+fn parse_header_missing_platform() {
+    let header = Bk2Header::try_from("MovieVersion BizHawk v2.0.0\n".to_string());
+    assert!(matches!(
+        header,
+        Err(Bk2HeaderError::MissingKey("Platform"))
+    ));
+}
@@ -0,0 +1,316 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::Bk2Error;
+
+/// An input, as named in the LogKey. `P1 Up` is the `Up` input of player 1,
+/// while console inputs (`Reset`, `Power`) have no player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bk2InputKey {
+    pub player: Option<u8>,
+    pub name: String,
+}
+
+impl FromStr for Bk2InputKey {
+    type Err = Bk2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let player = s
+            .strip_prefix('P')
+            .and_then(|rest| rest.split_once(' '))
+            .and_then(|(player, name)| Some((player.parse::<u8>().ok()?, name)));
+
+        Ok(match player {
+            Some((player, name)) => Self {
+                player: Some(player),
+                name: name.to_string(),
+            },
+            None => Self {
+                player: None,
+                name: s.to_string(),
+            },
+        })
+    }
+}
+
+impl Display for Bk2InputKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.player {
+            Some(player) => write!(f, "P{} {}", player, self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+/// The mapping of columns in the input log, e.g.
+/// `#Reset|Power|#P1 Up|P1 Down|P1 Left|P1 Right|P1 Start|P1 Select|P1 B|P1 A|`.
+/// Each `#` starts a new group, which is a `|` separated section of a frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bk2LogKey {
+    groups: Vec<Vec<Bk2InputKey>>,
+}
+
+impl FromStr for Bk2LogKey {
+    type Err = Bk2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let groups = s
+            .split('#')
+            .map(|group| {
+                group
+                    .split('|')
+                    .filter(|name| !name.is_empty())
+                    .map(Bk2InputKey::from_str)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .filter(|group| !matches!(group, Ok(g) if g.is_empty()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { groups })
+    }
+}
+
+impl Bk2LogKey {
+    /// All the inputs, in the order of the columns.
+    pub fn keys(&self) -> impl Iterator<Item = &Bk2InputKey> {
+        self.groups.iter().flatten()
+    }
+
+    /// The number of inputs.
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of players (controllers) in the movie.
+    pub fn players(&self) -> u8 {
+        self.keys().filter_map(|k| k.player).max().unwrap_or(0)
+    }
+
+    /// Find the column of an input.
+    pub fn position(&self, player: Option<u8>, name: &str) -> Option<usize> {
+        self.keys()
+            .position(|k| k.player == player && k.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Parse a frame of the input log, e.g. `|..|U..R...A|`.
+    pub fn parse_frame(&self, line: &str) -> Result<Bk2Frame, Bk2Error> {
+        let invalid = || Bk2Error::InvalidInputLine(line.to_string());
+
+        let inner = line.trim_end().strip_prefix('|').ok_or_else(invalid)?;
+        let inner = inner.strip_suffix('|').unwrap_or(inner);
+        let sections = inner.split('|').collect::<Vec<_>>();
+        if sections.len() != self.groups.len() {
+            return Err(invalid());
+        }
+
+        let mut values = Vec::with_capacity(self.len());
+        for (group, section) in self.groups.iter().zip(sections) {
+            let mut rest = section;
+            for _ in group {
+                let (value, next) = parse_value(rest).ok_or_else(invalid)?;
+                values.push(value);
+                rest = next;
+            }
+            if !rest.is_empty() {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Bk2Frame { values })
+    }
+}
+
+/// Parse a single value at the start of a section, returning the rest.
+/// Analog values are numbers followed by a comma (e.g. `  -12,`), while
+/// buttons are a single mnemonic character, or `.` when not pressed.
+fn parse_value(section: &str) -> Option<(Bk2InputValue, &str)> {
+    let first = section.chars().next()?;
+    if first == ' ' || first == '-' || first.is_ascii_digit() {
+        if let Some((number, rest)) = section.split_once(',') {
+            if let Ok(value) = number.trim().parse::<i32>() {
+                return Some((Bk2InputValue::Axis(value), rest));
+            }
+        }
+    }
+
+    let rest = &section[first.len_utf8()..];
+    Some((Bk2InputValue::Button(first != '.' && first != ' '), rest))
+}
+
+/// The value of a single input on a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bk2InputValue {
+    Button(bool),
+    Axis(i32),
+}
+
+impl Bk2InputValue {
+    /// Whether this is a pressed button.
+    pub fn is_pressed(&self) -> bool {
+        matches!(self, Bk2InputValue::Button(true))
+    }
+
+    pub fn as_axis(&self) -> Option<i32> {
+        match self {
+            Bk2InputValue::Axis(value) => Some(*value),
+            Bk2InputValue::Button(_) => None,
+        }
+    }
+}
+
+/// The values of all inputs on a frame, in the order of the LogKey.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bk2Frame {
+    values: Vec<Bk2InputValue>,
+}
+
+impl Bk2Frame {
+    pub fn values(&self) -> &[Bk2InputValue] {
+        &self.values
+    }
+}
+
+/// The content of `Input Log.txt`.
+#[derive(Debug, Clone, Default)]
+pub struct Bk2InputLog {
+    key: Bk2LogKey,
+    frames: Vec<Bk2Frame>,
+}
+
+impl FromStr for Bk2InputLog {
+    type Err = Bk2Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut key = None;
+        let mut frames = Vec::with_capacity(1024);
+
+        for line in s.trim_start_matches('\u{feff}').lines() {
+            if let Some(log_key) = line.strip_prefix("LogKey:") {
+                key = Some(log_key.parse::<Bk2LogKey>()?);
+            } else if line.starts_with('|') {
+                let key = key.as_ref().ok_or(Bk2Error::MissingLogKey)?;
+                frames.push(key.parse_frame(line)?);
+            }
+        }
+
+        Ok(Self {
+            key: key.ok_or(Bk2Error::MissingLogKey)?,
+            frames,
+        })
+    }
+}
+
+impl Bk2InputLog {
+    pub fn key(&self) -> &Bk2LogKey {
+        &self.key
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Iterate over all frames, with their inputs.
+    pub fn frames(&self) -> impl Iterator<Item = Bk2FrameInputs<'_>> {
+        self.frames.iter().map(|frame| Bk2FrameInputs {
+            key: &self.key,
+            frame,
+        })
+    }
+}
+
+/// A frame of inputs, with their names.
+#[derive(Debug, Clone, Copy)]
+pub struct Bk2FrameInputs<'a> {
+    key: &'a Bk2LogKey,
+    frame: &'a Bk2Frame,
+}
+
+impl<'a> Bk2FrameInputs<'a> {
+    /// Iterate over all inputs and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a Bk2InputKey, Bk2InputValue)> {
+        self.key.keys().zip(self.frame.values.iter().copied())
+    }
+
+    pub fn value(&self, player: Option<u8>, name: &str) -> Option<Bk2InputValue> {
+        self.key
+            .position(player, name)
+            .and_then(|i| self.frame.values.get(i).copied())
+    }
+
+    /// Whether a button is pressed. Use `None` as player for console buttons.
+    pub fn is_pressed(&self, player: Option<u8>, name: &str) -> bool {
+        self.value(player, name)
+            .is_some_and(|value| value.is_pressed())
+    }
+
+    /// The value of an analog input.
+    pub fn axis(&self, player: Option<u8>, name: &str) -> Option<i32> {
+        self.value(player, name).and_then(|value| value.as_axis())
+    }
+
+    /// The names of all the buttons pressed by a player.
+    pub fn pressed(&self, player: Option<u8>) -> impl Iterator<Item = &'a str> {
+        self.iter()
+            .filter(move |(key, value)| key.player == player && value.is_pressed())
+            .map(|(key, _)| key.name.as_str())
+    }
+}
+
+#[test]
+fn parse_log_key() {
+    let key: Bk2LogKey = "#Reset|Power|#P1 Up|P1 Down|P1 X Axis|#P2 A|"
+        .parse()
+        .unwrap();
+
+    assert_eq!(key.len(), 6);
+    assert_eq!(key.players(), 2);
+    assert_eq!(key.position(None, "Power"), Some(1));
+    assert_eq!(key.position(Some(1), "X Axis"), Some(4));
+    assert_eq!(key.position(Some(2), "A"), Some(5));
+    assert_eq!(
+        key.keys().map(ToString::to_string).collect::<Vec<_>>(),
+        ["Reset", "Power", "P1 Up", "P1 Down", "P1 X Axis", "P2 A"]
+    );
+}
+
+#[test]
+fn parse_input_log() {
+    let log: Bk2InputLog = "[Input]\n\
+        LogKey:#Reset|Power|#P1 Up|P1 Down|P1 X Axis|P1 A|#P2 A|\n\
+        |..|..    0,.|.|\n\
+        |r.|U.  -12,A|A|\n\
+        [/Input]\n"
+        .parse()
+        .unwrap();
+
+    assert_eq!(log.len(), 2);
+    let frames = log.frames().collect::<Vec<_>>();
+    assert!(!frames[0].is_pressed(None, "Reset"));
+    assert_eq!(frames[0].axis(Some(1), "X Axis"), Some(0));
+    assert_eq!(frames[0].pressed(Some(1)).count(), 0);
+
+    assert!(frames[1].is_pressed(None, "Reset"));
+    assert_eq!(frames[1].axis(Some(1), "X Axis"), Some(-12));
+    assert_eq!(frames[1].pressed(Some(1)).collect::<Vec<_>>(), ["Up", "A"]);
+    assert!(frames[1].is_pressed(Some(2), "A"));
+}
+
+#[test]
+fn parse_invalid_frame() {
+    let key: Bk2LogKey = "#Reset|#P1 A|P1 B|".parse().unwrap();
+    assert!(key.parse_frame("|.|AB|").is_ok());
+    assert!(key.parse_frame("|.|A|").is_err());
+    assert!(key.parse_frame("|.AB|").is_err());
+
+    // Extra characters after the inputs of a section.
+    assert!(key.parse_frame("|.|ABX|").is_err());
+    assert!(key.parse_frame("|.R|AB|").is_err());
+}
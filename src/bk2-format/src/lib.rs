@@ -1,16 +1,159 @@
+//! Reader for BizHawk movie files (`.bk2`). These are zip archives containing
+//! a header, an input log and, optionally, a savestate or SRAM to start from.
 use std::io::{Read, Seek};
 
 pub use error::Bk2Error;
+pub use header::{Bk2Header, Bk2HeaderError};
+pub use input::{Bk2Frame, Bk2FrameInputs, Bk2InputKey, Bk2InputLog, Bk2InputValue, Bk2LogKey};
 
 mod error;
 mod header;
+mod input;
+
+const HEADER_FILE: &str = "Header.txt";
+const INPUT_LOG_FILE: &str = "Input Log.txt";
+const SYNC_SETTINGS_FILE: &str = "SyncSettings.json";
+const SAVESTATE_FILE: &str = "Core.bin";
+const SAVESTATE_TEXT_FILE: &str = "Core.txt";
+const SAVE_RAM_FILE: &str = "SaveRam.bin";
+const COMMENTS_FILE: &str = "Comments.txt";
 
 pub struct Bk2File<R: Read + Seek> {
     file: zip::ZipArchive<R>,
+    header: Bk2Header,
+    inputs: Bk2InputLog,
 }
 
 impl<R: Read + Seek> Bk2File<R> {
+    /// Load a BK2 archive. The header and input log are parsed right away,
+    /// other entries are read on demand.
     pub fn load(file: R) -> Result<Self, Bk2Error> {
-        todo!()
+        let mut file = zip::ZipArchive::new(file)?;
+
+        let header =
+            read_string(&mut file, HEADER_FILE)?.ok_or(Bk2Error::MissingFile(HEADER_FILE))?;
+        let header = Bk2Header::try_from(header)?;
+
+        let inputs = read_string(&mut file, INPUT_LOG_FILE)?
+            .ok_or(Bk2Error::MissingFile(INPUT_LOG_FILE))?
+            .parse()?;
+
+        Ok(Self {
+            file,
+            header,
+            inputs,
+        })
+    }
+
+    pub fn header(&self) -> &Bk2Header {
+        &self.header
+    }
+
+    pub fn inputs(&self) -> &Bk2InputLog {
+        &self.inputs
+    }
+
+    /// Iterate over all frames of the movie.
+    pub fn frames(&self) -> impl Iterator<Item = Bk2FrameInputs<'_>> {
+        self.inputs.frames()
+    }
+
+    /// The core sync settings, as a JSON string.
+    pub fn sync_settings(&mut self) -> Result<Option<String>, Bk2Error> {
+        read_string(&mut self.file, SYNC_SETTINGS_FILE)
+    }
+
+    /// The savestate the movie starts from, if any. Text savestates are
+    /// returned as their bytes.
+    pub fn savestate(&mut self) -> Result<Option<Vec<u8>>, Bk2Error> {
+        match read_bytes(&mut self.file, SAVESTATE_FILE)? {
+            Some(bytes) => Ok(Some(bytes)),
+            None => read_bytes(&mut self.file, SAVESTATE_TEXT_FILE),
+        }
+    }
+
+    /// The SRAM the movie starts from, if any.
+    pub fn save_ram(&mut self) -> Result<Option<Vec<u8>>, Bk2Error> {
+        read_bytes(&mut self.file, SAVE_RAM_FILE)
     }
+
+    pub fn comments(&mut self) -> Result<Option<String>, Bk2Error> {
+        read_string(&mut self.file, COMMENTS_FILE)
+    }
+}
+
+fn read_bytes<R: Read + Seek>(
+    file: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, Bk2Error> {
+    let mut entry = match file.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+fn read_string<R: Read + Seek>(
+    file: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Option<String>, Bk2Error> {
+    read_bytes(file, name)?
+        .map(|bytes| {
+            String::from_utf8(bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+        })
+        .transpose()
+}
+
+#[test]
+fn load_archive() {
+    use std::io::{Cursor, Write};
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    writer.start_file(HEADER_FILE, options).unwrap();
+    writer
+        .write_all(b"MovieVersion BizHawk v2.0.0\nPlatform NES\nrerecordCount 3\n")
+        .unwrap();
+    writer.start_file(INPUT_LOG_FILE, options).unwrap();
+    writer
+        .write_all(
+            b"[Input]\nLogKey:#Reset|Power|#P1 Up|P1 Down|P1 A|\n|..|...|\n|..|..A|\n[/Input]\n",
+        )
+        .unwrap();
+    writer.start_file(SAVE_RAM_FILE, options).unwrap();
+    writer.write_all(&[1, 2, 3]).unwrap();
+    let archive = writer.finish().unwrap();
+
+    let mut bk2 = Bk2File::load(archive).unwrap();
+    assert_eq!(bk2.header().platform(), "NES");
+    assert_eq!(bk2.header().rerecord_count(), 3);
+    assert_eq!(bk2.inputs().len(), 2);
+    assert!(bk2.frames().nth(1).unwrap().is_pressed(Some(1), "A"));
+    assert_eq!(bk2.save_ram().unwrap(), Some(vec![1, 2, 3]));
+    assert_eq!(bk2.savestate().unwrap(), None);
+    assert_eq!(bk2.comments().unwrap(), None);
+}
+
+#[test]
+fn load_archive_missing_input_log() {
+    use std::io::{Cursor, Write};
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file(HEADER_FILE, zip::write::FileOptions::default())
+        .unwrap();
+    writer
+        .write_all(b"MovieVersion BizHawk v2.0.0\nPlatform NES\n")
+        .unwrap();
+    let archive = writer.finish().unwrap();
+
+    assert!(matches!(
+        Bk2File::load(archive),
+        Err(Bk2Error::MissingFile(INPUT_LOG_FILE))
+    ));
 }
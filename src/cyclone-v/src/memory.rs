@@ -47,6 +47,13 @@ pub trait MemoryMapper {
     /// Returns a mutable pointer to the mapped memory region.
     fn as_mut_ptr<T>(&mut self) -> *mut T;
 
+    /// Synchronize the mapped memory with whatever is on the other side of it.
+    /// Hardware sees writes directly so this does nothing by default, but
+    /// simulated memory can use it to react to writes. This should be called
+    /// after writing to a register a device reacts to, and while polling one.
+    #[inline]
+    fn sync(&mut self) {}
+
     /// Creates an inner range of bytes. The offsets are relative to the base
     /// of the mapped memory, e.g. `as_range(0..4)` will return the first 4
     /// bytes of the mapped memory (a memory mapping to address 0x12340000 will
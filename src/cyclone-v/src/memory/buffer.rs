@@ -1,34 +1,68 @@
 #![cfg(feature = "std")]
 
 use crate::memory::MemoryMapper;
+use std::fmt;
 use std::pin::Pin;
 
+/// A simulated device that reacts to changes in a [`BufferMemoryMapper`]. It
+/// receives the whole buffer every time the mapper is synchronized.
+pub type BufferDevice = Box<dyn FnMut(&mut [u8]) + Send>;
+
 /// Maps a region of memory over a vector.
 /// Useful for testing.
 pub struct BufferMemoryMapper {
     region: Pin<Box<[u8]>>,
+    address: usize,
+    device: Option<BufferDevice>,
+}
+
+impl fmt::Debug for BufferMemoryMapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferMemoryMapper")
+            .field(
+                "physical",
+                &format_args!("{:#X} ({} bytes)", self.address, self.region.len()),
+            )
+            .field("device", &self.device.is_some())
+            .finish()
+    }
 }
 
 impl BufferMemoryMapper {
     pub fn new(size: usize) -> Self {
-        Self {
-            region: vec![0; size].into_boxed_slice().into(),
-        }
+        Self::from_vec(vec![0; size])
     }
 
     pub fn from_vec(vec: Vec<u8>) -> Self {
         Self {
             region: vec.into_boxed_slice().into(),
+            address: 0,
+            device: None,
         }
     }
+
+    /// The physical address this buffer is simulating.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Attach a simulated device to this buffer. The device will be called
+    /// with the whole buffer on every [`MemoryMapper::sync`].
+    pub fn with_device(mut self, device: impl FnMut(&mut [u8]) + Send + 'static) -> Self {
+        self.device = Some(Box::new(device));
+        self
+    }
 }
 
 impl MemoryMapper for BufferMemoryMapper {
-    fn create(_address: usize, _size: usize) -> Result<Self, &'static str>
+    fn create(address: usize, size: usize) -> Result<Self, &'static str>
     where
         Self: Sized,
     {
-        unimplemented!()
+        Ok(Self {
+            address,
+            ..Self::new(size)
+        })
     }
 
     fn len(&self) -> usize {
@@ -42,4 +76,33 @@ impl MemoryMapper for BufferMemoryMapper {
     fn as_mut_ptr<T>(&mut self) -> *mut T {
         self.region.as_mut_ptr() as *mut T
     }
+
+    fn sync(&mut self) {
+        if let Some(device) = self.device.as_mut() {
+            device(&mut self.region);
+        }
+    }
+}
+
+#[test]
+fn create_works() {
+    let mut mapper = BufferMemoryMapper::create(0x1234_0000, 16).unwrap();
+    assert_eq!(mapper.address(), 0x1234_0000);
+    assert_eq!(mapper.len(), 16);
+
+    mapper.as_mut_range(0..4).copy_from_slice(&[1, 2, 3, 4]);
+    assert_eq!(mapper.as_range(..6), &[1, 2, 3, 4, 0, 0]);
+}
+
+#[test]
+fn device_sees_writes() {
+    let mut mapper = BufferMemoryMapper::new(4).with_device(|memory| {
+        // Echo the first byte into the second one.
+        memory[1] = memory[0].wrapping_add(1);
+    });
+
+    mapper.as_mut_range(0..1).copy_from_slice(&[41]);
+    assert_eq!(mapper.as_range(..), &[41, 0, 0, 0]);
+    mapper.sync();
+    assert_eq!(mapper.as_range(..), &[41, 42, 0, 0]);
 }
@@ -1,11 +1,15 @@
 use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::io::BufRead;
+use std::io::{BufRead, Write};
 use std::str::FromStr;
 
 use base64::prelude::*;
 use thiserror::Error;
 
+pub use recorder::FceRecorder;
+
+mod recorder;
+
 #[derive(Debug, Error)]
 pub enum FceError {
     #[error("IO Error: {0}")]
@@ -64,6 +68,9 @@ pub enum FceError {
 
     #[error("Invalid input line for Gamepad: {0}")]
     InvalidGamepadInputLine(String),
+
+    #[error("Invalid input line for Zapper: {0}")]
+    InvalidZapperInputLine(String),
 }
 
 #[derive(Debug, Clone)]
@@ -116,6 +123,74 @@ impl FceHeader {
     }
 }
 
+impl Default for FceHeader {
+    /// A header for a new movie, using the latest version of the format.
+    fn default() -> Self {
+        Self {
+            version: 3,
+            ..Self::new()
+        }
+    }
+}
+
+impl FceHeader {
+    fn write(&self, output: &mut impl Write) -> Result<(), FceError> {
+        writeln!(output, "version {}", self.version)?;
+        // Empty values cannot be read back, so they are skipped.
+        if !self.emu_version.is_empty() {
+            writeln!(output, "emuVersion {}", self.emu_version)?;
+        }
+        if let Some(rerecord_count) = self.rerecord_count {
+            writeln!(output, "rerecordCount {}", rerecord_count)?;
+        }
+        writeln!(output, "palFlag {}", self.pal as u8)?;
+        if !self.rom_filename.is_empty() {
+            writeln!(output, "romFilename {}", self.rom_filename)?;
+        }
+        writeln!(
+            output,
+            "romChecksum base64:{}",
+            BASE64_STANDARD.encode(self.rom_checksum)
+        )?;
+
+        let guid = hex::encode_upper(self.guid);
+        writeln!(
+            output,
+            "guid {}-{}-{}-{}-{}",
+            &guid[0..8],
+            &guid[8..12],
+            &guid[12..16],
+            &guid[16..20],
+            &guid[20..32]
+        )?;
+        writeln!(output, "fourscore {}", self.fourscore as u8)?;
+        writeln!(output, "microphone {}", self.microphone as u8)?;
+        writeln!(output, "port0 {}", self.port0.as_u8())?;
+        writeln!(output, "port1 {}", self.port1.as_u8())?;
+        writeln!(output, "port2 {}", self.port2.as_u8())?;
+        writeln!(output, "FDS {}", self.fds as u8)?;
+        writeln!(output, "NewPPU {}", self.new_ppu as u8)?;
+
+        if let Some(savestate) = &self.savestate {
+            writeln!(output, "savestate 0x{}", hex::encode(savestate))?;
+        }
+        for (subject, comments) in &self.comments {
+            for comment in comments {
+                if subject.is_empty() {
+                    writeln!(output, "comment {}", comment)?;
+                } else {
+                    writeln!(output, "comment {} {}", subject, comment)?;
+                }
+            }
+        }
+        for (frame, subtitle) in &self.subtitles {
+            writeln!(output, "subtitle {} {}", frame, subtitle)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// NES controller input buttons.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -183,6 +258,20 @@ impl FromStr for FceInputGamepad {
     }
 }
 
+impl std::fmt::Display for FceInputGamepad {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // RLDUTSBA
+        for (i, c) in "RLDUTSBA".chars().enumerate() {
+            if self.0 & (0x80 >> i) != 0 {
+                write!(f, "{}", c)?;
+            } else {
+                f.write_str(".")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl FceInputGamepad {
     pub fn new() -> Self {
         Self(0)
@@ -217,6 +306,34 @@ pub struct FceInputZapper {
     z: u32,
 }
 
+impl FromStr for FceInputZapper {
+    type Err = FceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FceError::InvalidZapperInputLine(s.to_string());
+        let mut parts = s.split_whitespace();
+        let mut next = || parts.next().ok_or_else(invalid);
+
+        Ok(Self {
+            x: next()?.parse().map_err(|_| invalid())?,
+            y: next()?.parse().map_err(|_| invalid())?,
+            mouse: next()?.parse::<u8>().map_err(|_| invalid())? != 0,
+            internal: next()?.parse().map_err(|_| invalid())?,
+            z: next()?.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl std::fmt::Display for FceInputZapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.x, self.y, self.mouse as u8, self.internal, self.z
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FceInputPort {
     None,
@@ -251,6 +368,16 @@ impl FceInputPort {
     }
 }
 
+impl std::fmt::Display for FceInputPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FceInputPort::None => Ok(()),
+            FceInputPort::Gamepad(gamepad) => write!(f, "{}", gamepad),
+            FceInputPort::Zapper(zapper) => write!(f, "{}", zapper),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FceInputPortType {
     None,
@@ -271,6 +398,16 @@ impl FromStr for FceInputPortType {
     }
 }
 
+impl FceInputPortType {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            FceInputPortType::None => 0,
+            FceInputPortType::Gamepad => 1,
+            FceInputPortType::Zapper => 2,
+        }
+    }
+}
+
 impl From<FceInputPort> for FceInputPortType {
     fn from(port: FceInputPort) -> Self {
         match port {
@@ -335,6 +472,10 @@ impl FceFrameCommandSet {
     pub fn has(&self, command: FceFrameCommand) -> bool {
         self.0 & (command as u8) != 0
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -360,6 +501,20 @@ impl FceFrame {
     }
 }
 
+impl std::fmt::Display for FceFrame {
+    /// Format the frame as an input line, e.g. `|0|R......A|........||`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "|{}|", self.commands.0)?;
+        for port in [&self.port0, &self.port1, &self.port2] {
+            if let Some(port) = port {
+                write!(f, "{}", port)?;
+            }
+            f.write_str("|")?;
+        }
+        Ok(())
+    }
+}
+
 fn parse_header_(header: &mut FceHeader, line: String) -> Result<(), FceError> {
     if line == "|" {
         return Ok(());
@@ -483,7 +638,7 @@ fn parse_port(ty: FceInputPortType, inner: &str) -> Result<Option<FceInputPort>,
     match ty {
         FceInputPortType::None => Ok(None),
         FceInputPortType::Gamepad => Ok(Some(FceInputPort::Gamepad(inner.parse()?))),
-        FceInputPortType::Zapper => todo!("Zapper input not supported yet"),
+        FceInputPortType::Zapper => Ok(Some(FceInputPort::Zapper(inner.parse()?))),
     }
 }
 
@@ -512,6 +667,20 @@ impl FceFrameInputs {
     pub fn iter(&self) -> impl Iterator<Item = &FceFrame> {
         self.0.iter()
     }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<FceFrame>> for FceFrameInputs {
+    fn from(value: Vec<FceFrame>) -> Self {
+        Self(value)
+    }
 }
 
 pub struct FceFile {
@@ -547,7 +716,59 @@ impl FceFile {
         })
     }
 
+    /// Write the movie as a text FM2 file. Binary input logs are not
+    /// supported, so the `binary` flag of the header is ignored.
+    pub fn write_stream(&self, mut output: impl Write) -> Result<(), FceError> {
+        self.header.write(&mut output)?;
+        for frame in self.inputs.iter() {
+            writeln!(output, "{}", frame)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+
     pub fn frames(&self) -> impl Iterator<Item = &FceFrame> {
         self.inputs.iter()
     }
 }
+
+#[test]
+fn write_and_load_roundtrip() {
+    let mut recorder = FceRecorder::default();
+    recorder.header_mut().rom_filename = "Super Mario Bros".to_string();
+    recorder.header_mut().guid = [0xAB; 16];
+    recorder
+        .header_mut()
+        .comments
+        .insert("author".to_string(), vec!["Someone".to_string()]);
+    recorder
+        .header_mut()
+        .subtitles
+        .insert(1, "Hello World".to_string());
+
+    let mut gamepad = FceInputGamepad::new();
+    gamepad.set(FceInputButton::Right);
+    gamepad.set(FceInputButton::A);
+    recorder.command(FceFrameCommand::SoftReset);
+    recorder.record(&[FceInputGamepad::new()]);
+    recorder.record(&[gamepad, gamepad]);
+    let movie = recorder.finish();
+
+    let mut output = Vec::new();
+    movie.write_stream(&mut output).unwrap();
+    let text = String::from_utf8(output.clone()).unwrap();
+    assert!(text.ends_with("|1|........|........||\n|0|R......A|R......A||\n"));
+
+    let loaded = FceFile::load_stream(output.as_slice()).unwrap();
+    assert_eq!(loaded.header.version, 3);
+    assert_eq!(loaded.header.rom_filename, "Super Mario Bros");
+    assert_eq!(loaded.header.guid, [0xAB; 16]);
+    assert_eq!(loaded.header.comments["author"], ["Someone"]);
+    assert_eq!(loaded.header.subtitles[&1], "Hello World");
+    assert_eq!(loaded.inputs.len(), 2);
+
+    let frames = loaded.frames().collect::<Vec<_>>();
+    assert!(frames[0].commands.has(FceFrameCommand::SoftReset));
+    assert_eq!(frames[1].port0.unwrap().as_gamepad(), Some(&gamepad));
+    assert_eq!(frames[1].port1.unwrap().as_gamepad(), Some(&gamepad));
+}
@@ -0,0 +1,151 @@
+use crate::{
+    FceFile, FceFrame, FceFrameCommand, FceFrameCommandSet, FceHeader, FceInputGamepad,
+    FceInputPort, FceInputPortType,
+};
+
+/// Records frames of input into a new movie.
+///
+/// Gamepads are assigned to the ports of the header, in order. Ports that
+/// aren't gamepads (e.g. zappers) are recorded as empty.
+#[derive(Debug, Clone)]
+pub struct FceRecorder {
+    header: FceHeader,
+    frames: Vec<FceFrame>,
+    commands: FceFrameCommandSet,
+}
+
+impl Default for FceRecorder {
+    /// A recorder for two gamepads.
+    fn default() -> Self {
+        Self::new(FceHeader {
+            port0: FceInputPortType::Gamepad,
+            port1: FceInputPortType::Gamepad,
+            ..FceHeader::default()
+        })
+    }
+}
+
+impl FceRecorder {
+    pub fn new(header: FceHeader) -> Self {
+        Self {
+            header,
+            frames: Vec::with_capacity(1024),
+            commands: FceFrameCommandSet::new(),
+        }
+    }
+
+    pub fn header(&self) -> &FceHeader {
+        &self.header
+    }
+
+    pub fn header_mut(&mut self) -> &mut FceHeader {
+        &mut self.header
+    }
+
+    /// The number of frames recorded so far.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Add a command (e.g. a reset) to the next recorded frame.
+    pub fn command(&mut self, command: FceFrameCommand) {
+        self.commands.set(command);
+    }
+
+    /// Record a frame with the state of each gamepad. Missing gamepads are
+    /// recorded as not pressed, extra ones are ignored.
+    pub fn record(&mut self, gamepads: &[FceInputGamepad]) {
+        let mut frame = FceFrame::empty(&self.header);
+        frame.commands = std::mem::take(&mut self.commands);
+
+        let mut gamepads = gamepads.iter();
+        for port in [&mut frame.port0, &mut frame.port1, &mut frame.port2] {
+            if let Some(FceInputPort::Gamepad(gamepad)) = port {
+                if let Some(g) = gamepads.next() {
+                    *gamepad = *g;
+                }
+            }
+        }
+
+        self.frames.push(frame);
+    }
+
+    /// Finish recording and return the movie.
+    pub fn finish(self) -> FceFile {
+        FceFile {
+            header: self.header,
+            inputs: self.frames.into(),
+        }
+    }
+}
+
+#[cfg(feature = "one-fpga")]
+mod one_fpga_inputs {
+    use one_fpga::inputs::gamepad::{Button, ButtonSet};
+
+    use super::FceRecorder;
+    use crate::{FceInputButton, FceInputGamepad};
+
+    impl From<ButtonSet> for FceInputGamepad {
+        fn from(buttons: ButtonSet) -> Self {
+            let mut gamepad = FceInputGamepad::new();
+            for (button, fce) in [
+                (Button::A, FceInputButton::A),
+                (Button::B, FceInputButton::B),
+                (Button::Back, FceInputButton::Select),
+                (Button::Start, FceInputButton::Start),
+                (Button::DPadUp, FceInputButton::Up),
+                (Button::DPadDown, FceInputButton::Down),
+                (Button::DPadLeft, FceInputButton::Left),
+                (Button::DPadRight, FceInputButton::Right),
+            ] {
+                if buttons.contains(button) {
+                    gamepad.set(fce);
+                }
+            }
+            gamepad
+        }
+    }
+
+    impl FceRecorder {
+        /// Record a frame from the buttons pressed on each gamepad.
+        pub fn record_buttons(&mut self, gamepads: &[ButtonSet]) {
+            let gamepads = gamepads
+                .iter()
+                .map(|b| FceInputGamepad::from(*b))
+                .collect::<Vec<_>>();
+            self.record(&gamepads);
+        }
+    }
+}
+
+#[cfg(feature = "one-fpga")]
+#[test]
+fn record_buttons() {
+    use crate::FceInputButton;
+    use one_fpga::inputs::gamepad::{Button, ButtonSet};
+
+    let mut buttons = ButtonSet::new();
+    buttons.insert(Button::DPadLeft);
+    buttons.insert(Button::Back);
+    buttons.insert(Button::X);
+
+    let mut recorder = FceRecorder::default();
+    recorder.record_buttons(&[buttons]);
+    let movie = recorder.finish();
+
+    let frame = movie.frames().next().unwrap();
+    let gamepad = frame.port0.unwrap();
+    assert_eq!(
+        gamepad.as_gamepad().unwrap().buttons(),
+        [FceInputButton::Select, FceInputButton::Left]
+    );
+    assert_eq!(
+        frame.port1.unwrap().as_gamepad(),
+        Some(&FceInputGamepad::new())
+    );
+}
@@ -1,11 +1,13 @@
+use std::io::Cursor;
 use std::path::PathBuf;
 
 use boa_engine::class::Class;
+use boa_engine::object::builtins::JsArrayBuffer;
 use boa_engine::value::TryFromJs;
-use boa_engine::{js_string, Context, JsError, JsResult, JsString, JsValue, Module};
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
 use boa_interop::{ContextData, IntoJsFunctionCopied, IntoJsModule};
 use boa_macros::{Finalize, JsData, Trace};
-use one_fpga::core::Rom;
+use one_fpga::core::{Bios, Rom};
 use one_fpga::runner::CoreLaunchInfo;
 use serde::Deserialize;
 
@@ -17,13 +19,30 @@ use crate::HostData;
 #[serde(tag = "type")]
 pub enum CoreType {
     Path { path: String },
+    Mra { path: String },
 }
 
 /// The game type for JavaScript.
 #[derive(Debug, Trace, Finalize, JsData, Deserialize)]
 #[serde(tag = "type")]
 pub enum GameType {
-    RomPath { path: String },
+    RomPath {
+        path: String,
+    },
+    /// A ROM in an `ArrayBuffer`. The name is used to find its file type.
+    RomBuffer {
+        name: Option<String>,
+        #[serde(skip)]
+        data: Vec<u8>,
+    },
+}
+
+/// A BIOS file for JavaScript, either a path or a path with an explicit file index.
+#[derive(Debug, Trace, Finalize, JsData, Deserialize)]
+#[serde(untagged)]
+pub enum BiosType {
+    Path(String),
+    Indexed { path: String, index: u8 },
 }
 
 #[derive(Debug, Trace, Finalize, JsData, Deserialize)]
@@ -31,6 +50,7 @@ pub enum GameType {
 struct RunOptions {
     core: CoreType,
     game: Option<GameType>,
+    bios: Option<Vec<BiosType>>,
     files: Option<Vec<Option<String>>>,
     savestate: Option<String>,
     show_menu: Option<bool>,
@@ -38,28 +58,64 @@ struct RunOptions {
 
 impl TryFromJs for RunOptions {
     fn try_from_js(value: &JsValue, context: &mut Context) -> JsResult<Self> {
-        serde_json::from_value(value.to_json(context)?)
-            .map_err(|e| JsError::from_opaque(JsString::from(e.to_string()).into()))
+        let mut options: Self = serde_json::from_value(value.to_json(context)?)
+            .map_err(|e| JsError::from_opaque(JsString::from(e.to_string()).into()))?;
+
+        // Buffers cannot go through JSON, read them directly.
+        if let Some(GameType::RomBuffer { data, .. }) = &mut options.game {
+            let buffer = value
+                .as_object()
+                .map(|o| o.get(js_string!("game"), context))
+                .transpose()?
+                .and_then(|game| game.as_object().cloned())
+                .map(|game| game.get(js_string!("data"), context))
+                .transpose()?
+                .and_then(|data| data.as_object().cloned())
+                .ok_or_else(|| js_error!(TypeError: "RomBuffer game needs a data ArrayBuffer"))?;
+            let buffer = JsArrayBuffer::from_object(buffer)?;
+            *data = buffer
+                .data()
+                .ok_or_else(|| js_error!("Invalid rom ArrayBuffer"))?
+                .to_vec();
+        }
+
+        Ok(options)
     }
 }
 
 fn load_(
-    options: RunOptions,
+    mut options: RunOptions,
     host_data: ContextData<HostData>,
     context: &mut Context,
 ) -> JsResult<JsValue> {
     let app = host_data.0.app_mut();
     let mut core_options = match &options.core {
         CoreType::Path { path } => CoreLaunchInfo::rbf(PathBuf::from(path)),
+        CoreType::Mra { path } => CoreLaunchInfo::mra(PathBuf::from(path)),
     };
 
-    match &options.game {
+    match options.game.take() {
         Some(GameType::RomPath { path }) => {
-            core_options = core_options.with_rom(Rom::File(PathBuf::from(path)));
+            core_options = core_options.with_rom(Rom::from_path(path));
+        }
+        Some(GameType::RomBuffer { name, data }) => {
+            core_options =
+                core_options.with_rom(Rom::Memory(name.map(PathBuf::from), Cursor::new(data)));
         }
         None => {}
     };
 
+    for bios in options.bios.iter().flatten() {
+        let b = match bios {
+            BiosType::Path(path) => Bios::from_path(path),
+            BiosType::Indexed { path, index } => {
+                Bios::from_path(path).map(|b| b.with_index(*index))
+            }
+        }
+        .map_err(JsError::from_rust)?;
+        core_options = core_options.with_bios(b);
+    }
+
     if let Some(files) = &options.files {
         for (i, file) in files
             .iter()
@@ -3,6 +3,7 @@ use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsVa
 use boa_interop::{IntoJsFunctionCopied, IntoJsModule};
 use boa_macros::TryFromJs;
 use either::Either;
+use one_fpga::archive::ArchivePath;
 use sha2::Digest;
 use std::path::PathBuf;
 
@@ -216,34 +217,44 @@ fn find_all_files(
     )
 }
 
+/// Read a file, or a member of an archive if the path goes through one
+/// (e.g. `roms.zip/game.nes`).
+fn read_file_or_member(path: &str) -> std::io::Result<Vec<u8>> {
+    match ArchivePath::parse(path) {
+        Some(archive) => archive.read(),
+        None => std::fs::read(path),
+    }
+}
+
+/// The size of a file, or of a member of an archive if the path goes through
+/// one.
+fn file_or_member_size(path: &str) -> std::io::Result<u64> {
+    match ArchivePath::parse(path) {
+        Some(archive) => archive.size(),
+        None => Ok(std::fs::metadata(path)?.len()),
+    }
+}
+
 fn sha256(paths: Either<String, Vec<String>>, context: &mut Context) -> JsPromise {
+    fn hash(path: &str) -> JsResult<JsValue> {
+        let data = read_file_or_member(path).map_err(JsError::from_rust)?;
+        let hash = sha2::Sha256::digest(&data);
+        let hash = hash
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        Ok(JsString::from(hash).into())
+    }
+
     JsPromise::new(
         |fns, context| {
             let value: JsValue = match paths {
-                Either::Left(path) => {
-                    let path = PathBuf::from(path);
-                    let data = std::fs::read(&path).unwrap();
-                    let hash = sha2::Sha256::digest(&data);
-                    let hash = hash
-                        .iter()
-                        .map(|b| format!("{:02x}", b))
-                        .collect::<String>();
-                    JsString::from(hash).into()
-                }
+                Either::Left(path) => hash(&path)?,
                 Either::Right(paths) => {
                     let hashes = paths
-                        .into_iter()
-                        .map(|path| {
-                            let path = PathBuf::from(path);
-                            let data = std::fs::read(&path).unwrap();
-                            let hash = sha2::Sha256::digest(&data);
-                            let hash = hash
-                                .iter()
-                                .map(|b| format!("{:02x}", b))
-                                .collect::<String>();
-                            JsString::from(hash).into()
-                        })
-                        .collect::<Vec<JsValue>>();
+                        .iter()
+                        .map(|path| hash(path))
+                        .collect::<JsResult<Vec<JsValue>>>()?;
                     JsArray::from_iter(hashes, context).into()
                 }
             };
@@ -256,24 +267,21 @@ fn sha256(paths: Either<String, Vec<String>>, context: &mut Context) -> JsPromis
 }
 
 fn file_size(paths: Either<String, Vec<String>>, context: &mut Context) -> JsPromise {
+    fn size(path: &str) -> JsResult<JsValue> {
+        let size = file_or_member_size(path).map_err(JsError::from_rust)?;
+        Ok(JsValue::from(size))
+    }
+
     JsPromise::new(
         |fns, context| {
             let value: JsValue = match paths {
-                Either::Left(path) => {
-                    let path = PathBuf::from(path);
-                    let size = std::fs::metadata(&path).unwrap().len();
-                    JsValue::from(size).into()
-                }
+                Either::Left(path) => size(&path)?,
                 Either::Right(paths) => {
-                    let hashes = paths
-                        .into_iter()
-                        .map(|path| {
-                            let path = PathBuf::from(path);
-                            let size = std::fs::metadata(&path).unwrap().len();
-                            JsValue::from(size).into()
-                        })
-                        .collect::<Vec<JsValue>>();
-                    JsArray::from_iter(hashes, context).into()
+                    let sizes = paths
+                        .iter()
+                        .map(|path| size(path))
+                        .collect::<JsResult<Vec<JsValue>>>()?;
+                    JsArray::from_iter(sizes, context).into()
                 }
             };
 
@@ -1,3 +1,4 @@
+mod capture;
 mod core;
 mod db;
 mod image;
@@ -0,0 +1,182 @@
+//! Capture of the frames of a core, to a clip on disk or a socket.
+use boa_engine::value::TryFromJs;
+use boa_engine::{js_error, JsError, JsResult};
+use boa_macros::{Finalize, Trace};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Trace, Finalize, TryFromJs)]
+pub struct CaptureOptions {
+    /// The number of frames to capture.
+    pub frames: u32,
+
+    /// Only capture one frame out of `interval` frames.
+    pub interval: Option<u32>,
+
+    /// The maximum width of the captured frames.
+    pub width: Option<u32>,
+
+    /// The maximum height of the captured frames.
+    pub height: Option<u32>,
+
+    /// A `.gif` file to write an animation to, or a directory to write a
+    /// sequence of PNG files to.
+    pub path: Option<String>,
+
+    /// An address (e.g. `127.0.0.1:9000`) to stream frames to.
+    pub stream: Option<String>,
+}
+
+/// Where captured frames are written.
+pub enum FrameSink {
+    Gif(GifEncoder<BufWriter<File>>, Duration),
+    Png(PathBuf, usize),
+    Stream(BufWriter<TcpStream>),
+}
+
+impl FrameSink {
+    /// Create the sink for a capture. `frame_time` is the duration of a single
+    /// frame of the core.
+    pub fn new(options: &CaptureOptions, frame_time: Duration) -> JsResult<Self> {
+        match (&options.path, &options.stream) {
+            (Some(_), Some(_)) => Err(js_error!("Cannot capture to a path and a stream")),
+            (Some(path), None) => {
+                let path = Path::new(path);
+                if path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+                {
+                    let file = File::create(path).map_err(JsError::from_rust)?;
+                    let mut encoder = GifEncoder::new(BufWriter::new(file));
+                    encoder
+                        .set_repeat(Repeat::Infinite)
+                        .map_err(JsError::from_rust)?;
+                    Ok(Self::Gif(encoder, frame_time))
+                } else {
+                    std::fs::create_dir_all(path).map_err(JsError::from_rust)?;
+                    Ok(Self::Png(path.to_path_buf(), 0))
+                }
+            }
+            (None, Some(address)) => {
+                let stream = TcpStream::connect(address).map_err(JsError::from_rust)?;
+                Ok(Self::Stream(BufWriter::new(stream)))
+            }
+            (None, None) => Err(js_error!("A path or a stream is required to capture")),
+        }
+    }
+
+    /// Write a frame, shown for `frames` frames of the core. Streamed frames
+    /// are sent as their width and height (big endian `u32`), followed by the
+    /// RGB888 pixels.
+    pub fn write(&mut self, image: DynamicImage, frames: usize) -> JsResult<()> {
+        match self {
+            Self::Gif(encoder, frame_time) => {
+                let delay = Delay::from_saturating_duration(*frame_time * frames as u32);
+                encoder
+                    .encode_frame(Frame::from_parts(image.into_rgba8(), 0, 0, delay))
+                    .map_err(JsError::from_rust)
+            }
+            Self::Png(dir, index) => {
+                *index += 1;
+                image
+                    .save(dir.join(format!("frame-{index:05}.png")))
+                    .map_err(JsError::from_rust)
+            }
+            Self::Stream(stream) => {
+                let image = image.into_rgb8();
+                stream
+                    .write_all(&image.width().to_be_bytes())
+                    .and_then(|_| stream.write_all(&image.height().to_be_bytes()))
+                    .and_then(|_| stream.write_all(image.as_raw()))
+                    .and_then(|_| stream.flush())
+                    .map_err(JsError::from_rust)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_options(path: Option<String>, stream: Option<String>) -> CaptureOptions {
+    CaptureOptions {
+        frames: 2,
+        interval: None,
+        width: None,
+        height: None,
+        path,
+        stream,
+    }
+}
+
+#[cfg(test)]
+fn test_frame(value: u8) -> DynamicImage {
+    DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 1, image::Rgb([value; 3])))
+}
+
+#[test]
+fn sink_requires_one_output() {
+    let frame_time = Duration::from_millis(20);
+    assert!(FrameSink::new(&test_options(None, None), frame_time).is_err());
+    let options = test_options(Some("a.gif".to_string()), Some("localhost:0".to_string()));
+    assert!(FrameSink::new(&options, frame_time).is_err());
+}
+
+#[test]
+fn sink_gif() {
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    let dir = tempdir::TempDir::new("capture").unwrap();
+    let path = dir.path().join("clip.gif");
+    let options = test_options(Some(path.to_string_lossy().to_string()), None);
+    let mut sink = FrameSink::new(&options, Duration::from_millis(20)).unwrap();
+    sink.write(test_frame(0), 2).unwrap();
+    sink.write(test_frame(0xFF), 1).unwrap();
+    drop(sink);
+
+    let file = std::io::BufReader::new(File::open(path).unwrap());
+    let frames = GifDecoder::new(file)
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap();
+    let delays = frames
+        .iter()
+        .map(|f| f.delay().numer_denom_ms())
+        .collect::<Vec<_>>();
+    assert_eq!(delays, [(40, 1), (20, 1)]);
+    assert_eq!(frames[1].buffer().get_pixel(0, 0).0, [0xFF; 4]);
+}
+
+#[test]
+fn sink_png() {
+    let dir = tempdir::TempDir::new("capture").unwrap();
+    let path = dir.path().join("frames");
+    let options = test_options(Some(path.to_string_lossy().to_string()), None);
+    let mut sink = FrameSink::new(&options, Duration::from_millis(20)).unwrap();
+    sink.write(test_frame(0), 1).unwrap();
+    sink.write(test_frame(0xFF), 1).unwrap();
+
+    let image = image::open(path.join("frame-00002.png")).unwrap();
+    assert_eq!(image.into_rgb8(), test_frame(0xFF).into_rgb8());
+    assert!(path.join("frame-00001.png").exists());
+}
+
+#[test]
+fn sink_stream() {
+    use std::io::Read;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let mut sink = FrameSink::new(&test_options(None, Some(address)), Duration::ZERO).unwrap();
+    sink.write(test_frame(7), 1).unwrap();
+    drop(sink);
+
+    let mut data = Vec::new();
+    listener.accept().unwrap().0.read_to_end(&mut data).unwrap();
+    assert_eq!(data, [0, 0, 0, 2, 0, 0, 0, 1, 7, 7, 7, 7, 7, 7]);
+}
@@ -1,4 +1,5 @@
 use crate::commands::maybe_call_command;
+use crate::modules::one_fpga::globals::capture::{CaptureOptions, FrameSink};
 use crate::modules::one_fpga::globals::classes::JsImage;
 use crate::HostData;
 use boa_engine::object::builtins::{JsFunction, JsUint8Array};
@@ -9,15 +10,28 @@ use boa_macros::{js_str, Finalize, JsData, Trace};
 use enum_map::{Enum, EnumMap};
 use firmware_ui::application::panels::core_loop::run_core_loop;
 use firmware_ui::application::OneFpgaApp;
+use firmware_ui::input::recording::{InputMovie, InputPlayback, InputRecording};
 use mister_fpga::core::{AsMisterCore, MisterFpgaCore};
 use one_fpga::core::SettingId;
 use one_fpga::{Core, OneFpgaCore};
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Duration;
 use tracing::{error, info};
 
 #[derive(Debug, Clone, Trace, Finalize, TryFromJs)]
-struct LoopOptions {}
+struct LoopOptions {
+    /// Record the inputs sent to the core. The recording is returned by the
+    /// loop.
+    record: Option<bool>,
+
+    /// A path to also write the recording to, as an FM2 movie (FCEUX). Only
+    /// the buttons of the first two gamepads are kept.
+    fm2: Option<String>,
+
+    /// A recording of inputs to play back.
+    playback: Option<JsValue>,
+}
 
 #[derive(Debug, Clone, Enum)]
 enum Events {
@@ -61,18 +75,36 @@ impl JsCore {
         host_defined: HostData,
         options: Option<LoopOptions>,
         context: &mut Context,
-    ) -> JsResult<()> {
+    ) -> JsResult<JsValue> {
         let app = host_defined.app_mut();
         let command_map = host_defined.command_map_mut();
         let mut core = self.core.clone();
 
         let events = self.events.clone();
         info!("Running loop: {:?}", options);
+        let fm2_path = options.as_ref().and_then(|o| o.fm2.clone());
+
+        let mut movie = match options {
+            Some(LoopOptions {
+                playback: Some(playback),
+                ..
+            }) => {
+                let json = playback.to_json(context)?;
+                let recording: InputRecording =
+                    serde_json::from_value(json).map_err(JsError::from_rust)?;
+                InputMovie::Playback(InputPlayback::new(recording))
+            }
+            Some(LoopOptions {
+                record: Some(true), ..
+            }) => InputMovie::Record(InputRecording::default()),
+            _ => InputMovie::Off,
+        };
 
         run_core_loop(
             app,
             &mut core,
-            &mut (command_map, context),
+            &mut (command_map, &mut *context),
+            &mut movie,
             |app, _core, id, (command_map, context)| -> JsResult<()> {
                 maybe_call_command(app, id, command_map, context)
             },
@@ -98,7 +130,22 @@ impl JsCore {
 
                 Ok(())
             },
-        )
+        )?;
+
+        match movie {
+            InputMovie::Record(recording) => {
+                if let Some(path) = fm2_path {
+                    let file = std::fs::File::create(path).map_err(JsError::from_rust)?;
+                    recording
+                        .to_fm2()
+                        .write_stream(std::io::BufWriter::new(file))
+                        .map_err(JsError::from_rust)?;
+                }
+                let json = serde_json::to_value(&recording).map_err(JsError::from_rust)?;
+                JsValue::from_json(&json, context)
+            }
+            _ => Ok(JsValue::undefined()),
+        }
     }
 
     fn show_osd(
@@ -141,6 +188,38 @@ impl JsCore {
         Ok(())
     }
 
+    /// Capture frames of the core, to a file or a stream.
+    fn capture(&mut self, options: CaptureOptions) -> JsResult<()> {
+        let core = self
+            .core
+            .as_any_mut()
+            .downcast_mut::<MisterFpgaCore>()
+            .ok_or_else(|| js_error!("Frame capture is only supported by MiSTer cores"))?;
+
+        let interval = options.interval.unwrap_or(1).max(1);
+        let vtime = core
+            .video_info()
+            .map(|info| info.vtime())
+            .ok()
+            .filter(|t| !t.is_zero())
+            .unwrap_or(Duration::from_micros(16_667));
+        let mut sink = FrameSink::new(&options, vtime)?;
+
+        let mut capture = core.capture_frames().with_interval(interval as usize);
+        if options.width.is_some() || options.height.is_some() {
+            capture = capture.with_max_size(
+                options.width.unwrap_or(u32::MAX),
+                options.height.unwrap_or(u32::MAX),
+            );
+        }
+
+        for frame in capture.take(options.frames as usize) {
+            let (image, frames) = frame.map_err(|e| js_error!("Could not capture frame: {}", e))?;
+            sink.write(image, frames)?;
+        }
+        Ok(())
+    }
+
     fn settings(&self, context: &mut Context) -> JsResult<JsValue> {
         let settings = self.core.settings().map_err(JsError::from_rust)?;
         let json = serde_json::to_value(&settings).map_err(JsError::from_rust)?;
@@ -230,7 +309,7 @@ js_class! {
             data: ContextData<HostData>,
             options: Option<LoopOptions>,
             context: &mut Context,
-        ) -> JsResult<()> {
+        ) -> JsResult<JsValue> {
             this.clone_inner().r#loop(data.0, options, context)
         }
 
@@ -274,6 +353,13 @@ js_class! {
             this.clone_inner().core.int_option(SettingId::from(id), value).map_err(JsError::from_rust)
         }
 
+        fn capture(
+            this: JsClass<JsCore>,
+            options: CaptureOptions,
+        ) -> JsResult<()> {
+            this.clone_inner().capture(options)
+        }
+
         fn quit(this: JsClass<JsCore>) -> () {
             this.clone_inner().quit()
         }
@@ -153,7 +153,7 @@ fn create_row_object_array<'a>(mut statement: Statement, ctx: &mut Context) -> J
 }
 
 pub fn db_root() -> PathBuf {
-    if cfg!(feature = "platform_de10") {
+    if cfg!(feature = "platform_de10") && !cfg!(feature = "platform_desktop") {
         PathBuf::from("/media/fat/1fpga")
     } else {
         let d = directories::BaseDirs::new()
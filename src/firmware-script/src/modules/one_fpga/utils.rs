@@ -1,47 +1,169 @@
 use boa_engine::object::builtins::{JsArrayBuffer, JsPromise};
-use boa_engine::{js_error, js_string, Context, JsResult, JsString, JsValue, Module};
+use boa_engine::value::TryFromJs;
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
 use boa_interop::{IntoJsFunctionCopied, IntoJsModule};
-use boa_macros::TryFromJs;
-use std::ops::Deref;
+use either::Either;
+use serde::Deserialize;
+use sha2::Digest;
 
-#[derive(TryFromJs)]
-struct IpsPatchOption {}
+mod patch;
+
+/// A ROM or patch, either in an `ArrayBuffer` or in a file on disk.
+type PatchInput = Either<JsArrayBuffer, JsString>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatchOptions {
+    /// Skip the CRC32 verification of BPS and UPS patches.
+    #[serde(default)]
+    skip_checksum: bool,
+
+    /// The expected SHA-256 of the patched ROM, in hexadecimal.
+    sha256: Option<String>,
+
+    /// Write the patched ROM to this path.
+    output: Option<String>,
+}
+
+impl TryFromJs for PatchOptions {
+    fn try_from_js(value: &JsValue, context: &mut Context) -> JsResult<Self> {
+        serde_json::from_value(value.to_json(context)?).map_err(JsError::from_rust)
+    }
+}
+
+fn read_input(input: &PatchInput, name: &str) -> JsResult<Vec<u8>> {
+    match input {
+        Either::Left(buffer) => Ok(buffer
+            .data()
+            .ok_or_else(|| js_error!("Invalid {} ArrayBuffer", name))?
+            .to_vec()),
+        Either::Right(path) => {
+            std::fs::read(path.to_std_string_escaped()).map_err(JsError::from_rust)
+        }
+    }
+}
+
+fn verify_sha256(data: &[u8], options: &PatchOptions) -> JsResult<()> {
+    let Some(expected) = &options.sha256 else {
+        return Ok(());
+    };
+
+    let hash = sha2::Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    if !hash.eq_ignore_ascii_case(expected) {
+        return Err(js_error!(
+            "Patched ROM SHA-256 does not match (expected {}, got {})",
+            expected,
+            hash
+        ));
+    }
+    Ok(())
+}
+
+/// Verify and output a patched ROM. It is written to the `output` path if there
+/// is one, otherwise it is returned as an `ArrayBuffer`.
+fn output(target: Vec<u8>, options: &PatchOptions, context: &mut Context) -> JsResult<JsValue> {
+    verify_sha256(&target, options)?;
+
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, target).map_err(JsError::from_rust)?;
+            Ok(JsValue::undefined())
+        }
+        None => Ok(JsArrayBuffer::from_byte_block(target, context)?.into()),
+    }
+}
 
 fn ips_patch_(
-    rom: JsArrayBuffer,
-    patch: JsArrayBuffer,
-    options: Option<IpsPatchOption>,
+    rom: PatchInput,
+    patch: PatchInput,
+    options: Option<PatchOptions>,
     context: &mut Context,
 ) -> JsResult<JsPromise> {
-    let rom_bytes = rom
-        .data_mut()
-        .ok_or_else(|| js_error!("Invalid rom ArrayBuffer"))?;
-    let ips_bytes = patch
-        .data()
-        .ok_or_else(|| js_error!("Invalid patch ArrayBuffer"))?;
-
-    let patch = ips::Patch::parse(ips_bytes.deref()).map_err(|e| js_error!(e.to_string()))?;
-
-    for hunk in patch.hunks() {
-        let offset = hunk.offset();
-        let payload = hunk.payload();
-        rom_bytes[offset..offset + payload.len()].copy_from_slice(payload);
-    }
+    let options = options.unwrap_or_default();
+    let target = patch::apply_ips(read_input(&rom, "rom")?, &read_input(&patch, "patch")?)
+        .map_err(|e| js_error!(e))?;
+    verify_sha256(&target, &options)?;
 
-    if let Some(truncation) = patch.truncation() {
-        rom_bytes.truncate(truncation);
+    // IPS patches are applied in place on buffers, unless an output is given.
+    match (&rom, &options.output) {
+        (_, Some(path)) => std::fs::write(path, target).map_err(JsError::from_rust)?,
+        (Either::Left(buffer), None) => {
+            let mut rom_bytes = buffer
+                .data_mut()
+                .ok_or_else(|| js_error!("Invalid rom ArrayBuffer"))?;
+            if target.len() > rom_bytes.len() {
+                return Err(js_error!(
+                    "IPS patch extends the ROM, use the output option instead"
+                ));
+            }
+            rom_bytes[..target.len()].copy_from_slice(&target);
+            rom_bytes.truncate(target.len());
+        }
+        // Never overwrite a ROM file, it might be the only copy.
+        (Either::Right(_), None) => {
+            return Err(js_error!("Patching a ROM file requires an output path"));
+        }
     }
 
     Ok(JsPromise::resolve(JsValue::undefined(), context))
 }
 
+fn bps_patch_(
+    rom: PatchInput,
+    patch: PatchInput,
+    options: Option<PatchOptions>,
+    context: &mut Context,
+) -> JsResult<JsPromise> {
+    let options = options.unwrap_or_default();
+    let target = patch::apply_bps(
+        &read_input(&rom, "rom")?,
+        &read_input(&patch, "patch")?,
+        !options.skip_checksum,
+    )
+    .map_err(|e| js_error!(e))?;
+
+    let value = output(target, &options, context)?;
+    Ok(JsPromise::resolve(value, context))
+}
+
+fn ups_patch_(
+    rom: PatchInput,
+    patch: PatchInput,
+    options: Option<PatchOptions>,
+    context: &mut Context,
+) -> JsResult<JsPromise> {
+    let options = options.unwrap_or_default();
+    let target = patch::apply_ups(
+        &read_input(&rom, "rom")?,
+        &read_input(&patch, "patch")?,
+        !options.skip_checksum,
+    )
+    .map_err(|e| js_error!(e))?;
+
+    let value = output(target, &options, context)?;
+    Ok(JsPromise::resolve(value, context))
+}
+
 pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
     Ok((
         js_string!("utils"),
-        [(
-            js_string!("ipsPatch"),
-            ips_patch_.into_js_function_copied(context),
-        )]
+        [
+            (
+                js_string!("ipsPatch"),
+                ips_patch_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("bpsPatch"),
+                bps_patch_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("upsPatch"),
+                ups_patch_.into_js_function_copied(context),
+            ),
+        ]
         .into_js_module(context),
     ))
 }
@@ -0,0 +1,377 @@
+//! ROM patch formats. IPS is parsed by the `ips` crate, BPS and UPS are
+//! decoded here. BPS and UPS contain the CRC32 of the source, target and
+//! patch, which are verified unless asked otherwise.
+
+/// The largest target a BPS or UPS patch can produce, the size of the largest
+/// file that can be sent to the core's SDRAM.
+const MAX_TARGET_SIZE: usize = 0x2000_0000;
+
+/// Apply an IPS patch. The ROM grows if a hunk writes past its end.
+pub fn apply_ips(mut rom: Vec<u8>, patch: &[u8]) -> Result<Vec<u8>, String> {
+    let patch = ips::Patch::parse(patch).map_err(|e| e.to_string())?;
+
+    for hunk in patch.hunks() {
+        let offset = hunk.offset();
+        let payload = hunk.payload();
+        if rom.len() < offset + payload.len() {
+            rom.resize(offset + payload.len(), 0);
+        }
+        rom[offset..offset + payload.len()].copy_from_slice(payload);
+    }
+
+    if let Some(truncation) = patch.truncation() {
+        rom.truncate(truncation);
+    }
+
+    Ok(rom)
+}
+
+/// A reader of the variable length integers used by BPS and UPS.
+struct PatchReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    /// Validate the magic and the checksum footer of a patch, and return a
+    /// reader of its body and the source and target CRC32.
+    fn new(patch: &'a [u8], magic: &[u8], check: bool) -> Result<(Self, u32, u32), String> {
+        if patch.len() < magic.len() + 12 || !patch.starts_with(magic) {
+            return Err("Invalid patch header".to_string());
+        }
+
+        let footer = patch.len() - 12;
+        let crc = |offset: usize| u32::from_le_bytes(patch[offset..offset + 4].try_into().unwrap());
+        let (source_crc, target_crc, patch_crc) = (crc(footer), crc(footer + 4), crc(footer + 8));
+
+        if check && crc32fast::hash(&patch[..footer + 8]) != patch_crc {
+            return Err("Patch checksum does not match".to_string());
+        }
+
+        let reader = Self {
+            data: &patch[..footer],
+            offset: magic.len(),
+        };
+        Ok((reader, source_crc, target_crc))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .data
+            .get(self.offset)
+            .ok_or("Unexpected end of patch")?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or("Unexpected end of patch")?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|v| v.checked_add(value))
+                .ok_or("Invalid number in patch")?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or("Invalid number in patch")?;
+            value = value.checked_add(shift).ok_or("Invalid number in patch")?;
+        }
+    }
+
+    /// A signed relative offset, as used by BPS copy commands.
+    fn offset(&mut self) -> Result<isize, String> {
+        let value = self.number()?;
+        let offset = (value >> 1) as isize;
+        Ok(if value & 1 != 0 { -offset } else { offset })
+    }
+}
+
+fn check_target_size(target_size: usize) -> Result<(), String> {
+    if target_size > MAX_TARGET_SIZE {
+        return Err(format!("Patch target is too large ({target_size} bytes)"));
+    }
+    Ok(())
+}
+
+fn check_crc(name: &str, data: &[u8], expected: u32) -> Result<(), String> {
+    let actual = crc32fast::hash(data);
+    if actual != expected {
+        return Err(format!(
+            "{name} checksum does not match (expected {expected:08x}, got {actual:08x})"
+        ));
+    }
+    Ok(())
+}
+
+/// Apply a BPS patch. The CRC32 of the source, target and patch are verified
+/// if `check` is true.
+pub fn apply_bps(source: &[u8], patch: &[u8], check: bool) -> Result<Vec<u8>, String> {
+    let (mut reader, source_crc, target_crc) = PatchReader::new(patch, b"BPS1", check)?;
+
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+
+    if source.len() != source_size {
+        return Err(format!(
+            "Source size does not match (expected {source_size}, got {})",
+            source.len()
+        ));
+    }
+    check_target_size(target_size)?;
+    if check {
+        check_crc("Source", source, source_crc)?;
+    }
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    let out_of_bounds = || "Patch reads out of bounds".to_string();
+
+    while !reader.is_empty() {
+        let data = reader.number()?;
+        let length = (data >> 2) + 1;
+        if length > target_size - target.len() {
+            return Err("Patch writes past the end of the target".to_string());
+        }
+
+        match data & 3 {
+            // Source read.
+            0 => {
+                let start = target.len();
+                let bytes = source
+                    .get(start..start + length)
+                    .ok_or_else(out_of_bounds)?;
+                target.extend_from_slice(bytes);
+            }
+            // Target read.
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            // Source copy.
+            2 => {
+                source_offset = source_offset
+                    .checked_add_signed(reader.offset()?)
+                    .ok_or_else(out_of_bounds)?;
+                let bytes = source_offset
+                    .checked_add(length)
+                    .and_then(|end| source.get(source_offset..end))
+                    .ok_or_else(out_of_bounds)?;
+                target.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // Target copy. The ranges can overlap, so copy one byte at a time.
+            _ => {
+                target_offset = target_offset
+                    .checked_add_signed(reader.offset()?)
+                    .ok_or_else(out_of_bounds)?;
+                for i in target_offset..target_offset + length {
+                    let byte = *target.get(i).ok_or_else(out_of_bounds)?;
+                    target.push(byte);
+                }
+                target_offset += length;
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(format!(
+            "Target size does not match (expected {target_size}, got {})",
+            target.len()
+        ));
+    }
+    if check {
+        check_crc("Target", &target, target_crc)?;
+    }
+
+    Ok(target)
+}
+
+/// Apply a UPS patch. The CRC32 of the source, target and patch are verified
+/// if `check` is true.
+pub fn apply_ups(source: &[u8], patch: &[u8], check: bool) -> Result<Vec<u8>, String> {
+    let (mut reader, source_crc, target_crc) = PatchReader::new(patch, b"UPS1", check)?;
+
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+
+    if source.len() != source_size {
+        return Err(format!(
+            "Source size does not match (expected {source_size}, got {})",
+            source.len()
+        ));
+    }
+    check_target_size(target_size)?;
+    if check {
+        check_crc("Source", source, source_crc)?;
+    }
+
+    let mut target = source.to_vec();
+    target.resize(target_size, 0);
+
+    let mut offset = 0usize;
+    while !reader.is_empty() {
+        offset = offset
+            .checked_add(reader.number()?)
+            .ok_or("Patch writes out of bounds")?;
+        loop {
+            let byte = reader.byte()?;
+            if byte == 0 {
+                offset += 1;
+                break;
+            }
+            *target.get_mut(offset).ok_or("Patch writes out of bounds")? ^= byte;
+            offset += 1;
+        }
+    }
+
+    if check {
+        check_crc("Target", &target, target_crc)?;
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+fn encode_number(mut value: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte | 0x80);
+            return;
+        }
+        out.push(byte);
+        value -= 1;
+    }
+}
+
+#[cfg(test)]
+fn finish_patch(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+    patch.extend_from_slice(&crc32fast::hash(source).to_le_bytes());
+    patch.extend_from_slice(&crc32fast::hash(target).to_le_bytes());
+    patch.extend_from_slice(&crc32fast::hash(&patch).to_le_bytes());
+    patch
+}
+
+#[test]
+fn bps() {
+    let source = b"Hello World!".to_vec();
+    let target = b"Hello, Hello World!!".to_vec();
+
+    let mut patch = b"BPS1".to_vec();
+    encode_number(source.len(), &mut patch);
+    encode_number(target.len(), &mut patch);
+    encode_number(0, &mut patch);
+    // Source read "Hello".
+    encode_number(4 << 2, &mut patch);
+    // Target read ", ".
+    encode_number((1 << 2) | 1, &mut patch);
+    patch.extend_from_slice(b", ");
+    // Source copy "Hello World!" from 0.
+    encode_number((11 << 2) | 2, &mut patch);
+    encode_number(0, &mut patch);
+    // Target copy "!" from 18.
+    encode_number(3, &mut patch);
+    encode_number(18 << 1, &mut patch);
+    let patch = finish_patch(patch, &source, &target);
+
+    assert_eq!(apply_bps(&source, &patch, true).unwrap(), target);
+    assert!(apply_bps(b"Hello World?", &patch, true).is_err());
+    assert!(apply_bps(b"Hello World?", &patch, false).is_ok());
+}
+
+#[test]
+fn bps_out_of_bounds() {
+    let source = b"Hello".to_vec();
+    let patch = |body: &[u8]| {
+        let mut patch = b"BPS1".to_vec();
+        encode_number(source.len(), &mut patch);
+        encode_number(2, &mut patch);
+        encode_number(0, &mut patch);
+        patch.extend_from_slice(body);
+        finish_patch(patch, &source, b"He")
+    };
+    let number = |value: usize| {
+        let mut out = Vec::new();
+        encode_number(value, &mut out);
+        out
+    };
+
+    // Target read "H", then a target copy past the size of the target.
+    let grows = [number(1), b"H".to_vec(), number((999 << 2) | 3), number(0)].concat();
+    assert!(apply_bps(&source, &patch(&grows), false).is_err());
+
+    // Source copy before the start of the source.
+    let underflow = [number(2), number((1 << 1) | 1)].concat();
+    assert!(apply_bps(&source, &patch(&underflow), false).is_err());
+
+    // Source copy from an offset that overflows.
+    let overflow = [number(2), number(usize::MAX >> 8 << 1)].concat();
+    assert!(apply_bps(&source, &patch(&overflow), false).is_err());
+
+    // A target too large to allocate.
+    let mut huge = b"BPS1".to_vec();
+    encode_number(source.len(), &mut huge);
+    encode_number(usize::MAX >> 1, &mut huge);
+    encode_number(0, &mut huge);
+    let huge = finish_patch(huge, &source, b"He");
+    assert!(apply_bps(&source, &huge, false).is_err());
+}
+
+#[test]
+fn ups() {
+    let source = b"Hello World!".to_vec();
+    let target = b"Hallo World!??".to_vec();
+
+    let mut patch = b"UPS1".to_vec();
+    encode_number(source.len(), &mut patch);
+    encode_number(target.len(), &mut patch);
+    encode_number(1, &mut patch);
+    patch.extend_from_slice(&[b'e' ^ b'a', 0]);
+    encode_number(9, &mut patch);
+    patch.extend_from_slice(&[b'?', b'?', 0]);
+    let patch = finish_patch(patch, &source, &target);
+
+    assert_eq!(apply_ups(&source, &patch, true).unwrap(), target);
+
+    let mut corrupted = patch.clone();
+    corrupted[7] ^= 1;
+    assert!(apply_ups(&source, &corrupted, true).is_err());
+}
+
+#[test]
+fn ups_out_of_bounds() {
+    let source = b"Hello".to_vec();
+    let patch = |target_size: usize, body: &[u8]| {
+        let mut patch = b"UPS1".to_vec();
+        encode_number(source.len(), &mut patch);
+        encode_number(target_size, &mut patch);
+        patch.extend_from_slice(body);
+        finish_patch(patch, &source, b"Hello")
+    };
+
+    // XOR past the end of the target.
+    let mut body = Vec::new();
+    encode_number(4, &mut body);
+    body.extend_from_slice(&[1, 1, 0]);
+    assert!(apply_ups(&source, &patch(5, &body), false).is_err());
+
+    // A target too large to allocate.
+    assert!(apply_ups(&source, &patch(usize::MAX >> 1, &[]), false).is_err());
+}
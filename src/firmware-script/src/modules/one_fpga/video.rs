@@ -1,10 +1,13 @@
 use crate::HostData;
+use boa_engine::object::builtins::JsArray;
 use boa_engine::value::TryIntoJs;
 use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
 use boa_interop::{ContextData, IntoJsFunctionCopied, IntoJsModule};
 use mister_fpga::config::edid::DefaultVideoMode;
 use mister_fpga::config::resolution;
 use mister_fpga::core::AsMisterCore;
+use mister_fpga::filters::{FilterKind, Filters};
+use mister_fpga::shadow_mask::ShadowMaskMode;
 use std::str::FromStr;
 
 fn set_mode_(mode: String, ContextData(data): ContextData<HostData>) -> JsResult<()> {
@@ -72,6 +75,65 @@ fn get_resolution_(
     Ok(Some(Resolution::from(resolution).try_into_js(context)?))
 }
 
+/// Change the filters of the current core, then save and send them.
+fn update_filters<R>(data: &HostData, f: impl FnOnce(&mut Filters) -> R) -> JsResult<R> {
+    let app = data.app_mut();
+    let mut core = app
+        .platform_mut()
+        .core_manager_mut()
+        .get_current_core()
+        .ok_or_else(|| js_error!("No core loaded"))?;
+
+    let core = core
+        .as_mister_core_mut()
+        .ok_or_else(|| js_error!("Core is not a MisterFpgaCore"))?;
+
+    core.update_filters(f)
+        .map_err(|e| js_error!("Failed to update the filters: {}", e))
+}
+
+fn list_shadow_masks_(
+    ContextData(data): ContextData<HostData>,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let app = data.app_mut();
+    let mut core = app
+        .platform_mut()
+        .core_manager_mut()
+        .get_current_core()
+        .ok_or_else(|| js_error!("No core loaded"))?;
+
+    let core = core
+        .as_mister_core_mut()
+        .ok_or_else(|| js_error!("Core is not a MisterFpgaCore"))?;
+
+    let masks = core
+        .filters()
+        .available(FilterKind::ShadowMask)
+        .iter()
+        .map(|name| JsString::from(name.as_str()).into())
+        .collect::<Vec<JsValue>>();
+    Ok(JsArray::from_iter(masks, context).into())
+}
+
+fn set_shadow_mask_(
+    name: Option<String>,
+    ContextData(data): ContextData<HostData>,
+) -> JsResult<()> {
+    let selected = update_filters(&data, |filters| {
+        filters.select(FilterKind::ShadowMask, name.as_deref())
+    })?;
+    if !selected {
+        return Err(js_error!("Unknown shadow mask: {:?}", name));
+    }
+    Ok(())
+}
+
+fn set_shadow_mask_mode_(mode: String, ContextData(data): ContextData<HostData>) -> JsResult<()> {
+    let mode = ShadowMaskMode::from_str(&mode).map_err(JsError::from_rust)?;
+    update_filters(&data, |filters| filters.set_shadow_mask_mode(mode))
+}
+
 pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
     Ok((
         js_string!("video"),
@@ -84,6 +146,18 @@ pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
                 js_string!("getResolution"),
                 get_resolution_.into_js_function_copied(context),
             ),
+            (
+                js_string!("listShadowMasks"),
+                list_shadow_masks_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("setShadowMask"),
+                set_shadow_mask_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("setShadowMaskMode"),
+                set_shadow_mask_mode_.into_js_function_copied(context),
+            ),
         ]
         .into_js_module(context),
     ))
@@ -5,7 +5,6 @@ use crate::input::shortcut::Shortcut;
 use crate::input::InputState;
 use crate::macguiver::application::EventLoopState;
 use crate::macguiver::buffer::DrawBuffer;
-use crate::platform::de10::De10Platform;
 use crate::platform::WindowManager;
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::pixelcolor::{BinaryColor, Rgb888};
@@ -22,7 +21,7 @@ mod toolbar;
 mod widgets;
 
 pub struct OneFpgaApp {
-    platform: De10Platform,
+    platform: WindowManager,
 
     toolbar: Toolbar,
 
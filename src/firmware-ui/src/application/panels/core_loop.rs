@@ -1,6 +1,9 @@
 use crate::application::OneFpgaApp;
 use crate::input::commands::CommandId;
+use crate::input::recording::{FrameClock, InputEvent, InputMovie};
 use image::DynamicImage;
+use mister_fpga::core::AsMisterCore;
+use one_fpga::inputs::mouse;
 use one_fpga::{Core, OneFpgaCore};
 use sdl3::event::Event;
 use std::fmt::Debug;
@@ -11,6 +14,7 @@ fn core_loop<C, E: Debug>(
     app: &mut OneFpgaApp,
     core: &mut OneFpgaCore,
     context: &mut C,
+    movie: &mut InputMovie,
     mut shortcut_handler: impl FnMut(
         &mut OneFpgaApp,
         &mut OneFpgaCore,
@@ -28,44 +32,94 @@ fn core_loop<C, E: Debug>(
 ) -> Result<(), E> {
     let mut should_check_savestates = matches!(core.save_state(0), Ok(Some(_)));
     let mut i = 0;
+    let mut clock = (!movie.is_off()).then(|| FrameClock::new(core));
+    // Sub-pixel mouse motion not sent yet, so slow movements add up.
+    let mut mouse_remainder = (0f32, 0f32);
 
     // This is a special loop that forwards everything to the core,
     // except for the menu button(s).
     app.event_loop(move |app, state| {
         i += 1;
+        let frame = clock.as_mut().map(|c| c.update()).unwrap_or_default();
+
+        if let InputMovie::Playback(playback) = movie {
+            if let Err(err) = playback.apply(core, frame) {
+                error!(?err, "Error playing back inputs. Stopping playback.");
+                *movie = InputMovie::Off;
+            }
+        }
+        let forward_inputs = movie.forwards_inputs();
 
         for ev in state.events() {
-            match ev {
+            let event = match ev {
                 Event::KeyDown {
                     scancode: Some(scancode),
-                    repeat,
+                    repeat: false,
                     ..
-                } => {
-                    if !repeat {
-                        let _ = core.key_down((*scancode).into());
-                    }
-                }
+                } => InputEvent::KeyDown {
+                    key: (*scancode).into(),
+                },
                 Event::KeyUp {
                     scancode: Some(scancode),
                     ..
-                } => {
-                    let _ = core.key_up((*scancode).into());
-                }
+                } => InputEvent::KeyUp {
+                    key: (*scancode).into(),
+                },
                 Event::ControllerButtonDown { which, button, .. } => {
-                    let _ = core.gamepad_button_down((which - 1) as usize, (*button).into());
+                    InputEvent::GamepadButtonDown {
+                        index: (which - 1) as usize,
+                        button: (*button).into(),
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => InputEvent::GamepadButtonUp {
+                    index: (which - 1) as usize,
+                    button: (*button).into(),
+                },
+                Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } => InputEvent::AxisMotion {
+                    index: (which - 1) as usize,
+                    axis: (*axis).into(),
+                    value: *value,
+                },
+                Event::MouseMotion { xrel, yrel, .. } => {
+                    let x = mouse_remainder.0 + xrel;
+                    let y = mouse_remainder.1 + yrel;
+                    let (dx, dy) = (x.trunc(), y.trunc());
+                    mouse_remainder = (x - dx, y - dy);
+                    if dx == 0. && dy == 0. {
+                        continue;
+                    }
+                    InputEvent::MouseMove {
+                        dx: dx as i32,
+                        dy: dy as i32,
+                    }
+                }
+                Event::MouseButtonDown { mouse_btn, .. } => {
+                    let Some(button) = mouse::Button::from_sdl(*mouse_btn) else {
+                        continue;
+                    };
+                    InputEvent::MouseButtonDown { button }
                 }
-                Event::ControllerButtonUp { which, button, .. } => {
-                    let _ = core.gamepad_button_up((which - 1) as usize, (*button).into());
+                Event::MouseButtonUp { mouse_btn, .. } => {
+                    let Some(button) = mouse::Button::from_sdl(*mouse_btn) else {
+                        continue;
+                    };
+                    InputEvent::MouseButtonUp { button }
+                }
+                _ => continue,
+            };
+
+            if forward_inputs {
+                let _ = event.send(core);
+                if let InputMovie::Record(recording) = movie {
+                    recording.push(frame, event);
                 }
-                // TODO: this.
-                // Event::ControllerAxisMotion {
-                //     which, axis, value, ..
-                // } => {
-                //     let _ = core.axis_motion((which - 1) as u8, *axis, *value);
-                // }
-                _ => {}
             }
         }
+        if let InputMovie::Record(recording) = movie {
+            recording.frames = frame;
+        }
 
         // Check if any action needs to be taken.
         for id in state.shortcuts() {
@@ -128,6 +182,15 @@ fn core_loop<C, E: Debug>(
             }
         }
 
+        // Check whether the core switched between PAL and NTSC, about every 100ms.
+        if i % 60 == 0 {
+            if let Some(core) = core.as_mister_core_mut() {
+                if let Err(err) = core.poll_video() {
+                    error!(?err, "Error polling the core video mode.");
+                }
+            }
+        }
+
         if i % 10 == 0 {
             if core.should_quit() {
                 return Some(Ok(()));
@@ -138,11 +201,13 @@ fn core_loop<C, E: Debug>(
     })
 }
 
-/// Run the core loop and send events to the core.
+/// Run the core loop and send events to the core. Inputs are recorded in,
+/// or played back from, `movie`.
 pub fn run_core_loop<C, E: Debug>(
     app: &mut OneFpgaApp,
     core: &mut OneFpgaCore,
     context: &mut C,
+    movie: &mut InputMovie,
     shortcut_handler: impl FnMut(&mut OneFpgaApp, &mut OneFpgaCore, CommandId, &mut C) -> Result<(), E>,
     savestate_handler: impl FnMut(
         &mut OneFpgaApp,
@@ -159,7 +224,14 @@ pub fn run_core_loop<C, E: Debug>(
     app.hide_toolbar();
     app.platform_mut().core_manager_mut().hide_osd();
 
-    let result = core_loop(app, core, context, shortcut_handler, savestate_handler);
+    let result = core_loop(
+        app,
+        core,
+        context,
+        movie,
+        shortcut_handler,
+        savestate_handler,
+    );
 
     debug!("Core loop ended");
     info!("Loading Main Menu");
@@ -7,6 +7,7 @@ use tracing::info;
 use mister_fpga::core::file::SdCard;
 use mister_fpga::core::{MenuCore, MisterFpgaCore};
 use mister_fpga::fpga::MisterFpga;
+use mister_fpga::mra::Mra;
 use one_fpga::core::SaveState;
 use one_fpga::runner::{CoreLaunchInfo, CoreType, Slot};
 use one_fpga::{Core, OneFpgaCore};
@@ -90,10 +91,26 @@ impl CoreManager {
             CoreType::Current => self.get_current_core().ok_or("No core running")?,
             CoreType::Menu => self.load_menu()?,
             CoreType::RbfFile(path) => self.load_core(path)?,
+            CoreType::Mra(ref path) => {
+                let mra = Mra::from_path(path).map_err(|e| e.to_string())?;
+                let rbf = mra.find_rbf(path).map_err(|e| e.to_string())?;
+                let mut core = self.load_core(rbf)?;
+                core.as_any_mut()
+                    .downcast_mut::<MisterFpgaCore>()
+                    .unwrap()
+                    .load_mra(&mra, path)?;
+                core
+            }
         };
 
         let mister_core = core.as_any_mut().downcast_mut::<MisterFpgaCore>().unwrap();
 
+        for bios in &info.bios {
+            mister_core
+                .send_bios(bios.clone())
+                .map_err(|e| e.to_string())?;
+        }
+
         if let Some(rom) = &info.rom {
             mister_core
                 .send_rom(rom.clone())
@@ -120,6 +137,10 @@ impl CoreManager {
             mister_core.end_send_file()?;
         }
 
+        if mister_core.supports_cheats() {
+            mister_core.send_cheats()?;
+        }
+
         // Load all savestates.
         if let Some(savestate_manager) = mister_core.save_states_mut() {
             for (slot, state) in savestate_manager.slots_mut().iter_mut().enumerate() {
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 pub fn config_root_path() -> PathBuf {
     // On DE10-Nano, the configurations are in /media/fat/1fpga
     cfg_if! {
-        if #[cfg(feature = "platform_de10")] {
+        if #[cfg(all(feature = "platform_de10", not(feature = "platform_desktop")))] {
             let p = PathBuf::from("/media/fat/1fpga");
         } else {
             let p = dirs::config_dir().unwrap().join("1fpga");
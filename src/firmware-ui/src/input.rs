@@ -6,6 +6,7 @@ use std::fmt::{Display, Formatter};
 
 pub mod commands;
 pub mod password;
+pub mod recording;
 pub mod shortcut;
 
 /// The current status of all inputs.
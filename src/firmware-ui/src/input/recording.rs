@@ -0,0 +1,317 @@
+//! Recording and playback of inputs sent to a core, timestamped by frame.
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use fce_movie_format::{FceFile, FceRecorder};
+use mister_fpga::core::AsMisterCore;
+use mister_fpga::framebuffer::FrameIter;
+use one_fpga::core::Error;
+use one_fpga::inputs::gamepad::ButtonSet;
+use one_fpga::inputs::{mouse, Axis, Button, Scancode};
+use one_fpga::{Core, OneFpgaCore};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Approximation of a frame length for cores that cannot tell when a frame
+/// changed (60Hz).
+const FALLBACK_FRAME_MICROS: u128 = 16_667;
+
+/// An input event sent to the core.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum InputEvent {
+    KeyDown {
+        #[serde_as(as = "DisplayFromStr")]
+        key: Scancode,
+    },
+    KeyUp {
+        #[serde_as(as = "DisplayFromStr")]
+        key: Scancode,
+    },
+    GamepadButtonDown {
+        index: usize,
+        button: Button,
+    },
+    GamepadButtonUp {
+        index: usize,
+        button: Button,
+    },
+    AxisMotion {
+        index: usize,
+        #[serde_as(as = "DisplayFromStr")]
+        axis: Axis,
+        value: i16,
+    },
+    MouseMove {
+        dx: i32,
+        dy: i32,
+    },
+    MouseButtonDown {
+        button: mouse::Button,
+    },
+    MouseButtonUp {
+        button: mouse::Button,
+    },
+}
+
+impl InputEvent {
+    /// Send the event to the core directly.
+    pub fn send(&self, core: &mut OneFpgaCore) -> Result<(), Error> {
+        match *self {
+            InputEvent::KeyDown { key } => core.key_down(key),
+            InputEvent::KeyUp { key } => core.key_up(key),
+            InputEvent::GamepadButtonDown { index, button } => {
+                core.gamepad_button_down(index, button)
+            }
+            InputEvent::GamepadButtonUp { index, button } => core.gamepad_button_up(index, button),
+            InputEvent::AxisMotion { index, axis, value } => core.axis_motion(index, axis, value),
+            InputEvent::MouseMove { dx, dy } => core.mouse_move(dx, dy),
+            InputEvent::MouseButtonDown { button } => core.mouse_button(button, true),
+            InputEvent::MouseButtonUp { button } => core.mouse_button(button, false),
+        }
+    }
+}
+
+/// An input event, with the frame it happened on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordedInput {
+    pub frame: u64,
+    #[serde(flatten)]
+    pub event: InputEvent,
+}
+
+/// A recording of all inputs sent to a core.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    /// The number of frames recorded.
+    pub frames: u64,
+    pub events: Vec<RecordedInput>,
+}
+
+impl InputRecording {
+    pub fn push(&mut self, frame: u64, event: InputEvent) {
+        self.frames = self.frames.max(frame);
+        self.events.push(RecordedInput { frame, event });
+    }
+
+    /// Convert the buttons of the first two gamepads to an FM2 movie, for
+    /// FCEUX. Other inputs are dropped.
+    pub fn to_fm2(&self) -> FceFile {
+        let mut recorder = FceRecorder::default();
+        let mut gamepads = [ButtonSet::new(); 2];
+        let mut events = self.events.iter().peekable();
+
+        for frame in 0..=self.frames {
+            while let Some(input) = events.next_if(|input| input.frame <= frame) {
+                match input.event {
+                    InputEvent::GamepadButtonDown { index, button } => {
+                        if let Some(gamepad) = gamepads.get_mut(index) {
+                            gamepad.insert(button);
+                        }
+                    }
+                    InputEvent::GamepadButtonUp { index, button } => {
+                        if let Some(gamepad) = gamepads.get_mut(index) {
+                            gamepad.remove(button);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            recorder.record_buttons(&gamepads);
+        }
+
+        recorder.finish()
+    }
+}
+
+/// Replays an [`InputRecording`]. Gamepad states are sent with
+/// [`Core::gamepad_buttons_set`] once per frame, keys are sent as events.
+#[derive(Debug, Clone)]
+pub struct InputPlayback {
+    recording: InputRecording,
+    next: usize,
+    gamepads: BTreeMap<usize, ButtonSet>,
+}
+
+impl InputPlayback {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            next: 0,
+            gamepads: BTreeMap::new(),
+        }
+    }
+
+    /// Whether all events were sent.
+    pub fn is_done(&self) -> bool {
+        self.next >= self.recording.events.len()
+    }
+
+    /// Send all events up to (and including) `frame` to the core.
+    pub fn apply(&mut self, core: &mut OneFpgaCore, frame: u64) -> Result<(), Error> {
+        let mut changed = Vec::new();
+
+        while let Some(input) = self.recording.events.get(self.next) {
+            if input.frame > frame {
+                break;
+            }
+            self.next += 1;
+
+            match input.event {
+                InputEvent::GamepadButtonDown { index, button } => {
+                    self.gamepads.entry(index).or_default().insert(button);
+                    changed.push(index);
+                }
+                InputEvent::GamepadButtonUp { index, button } => {
+                    self.gamepads.entry(index).or_default().remove(button);
+                    changed.push(index);
+                }
+                event => event.send(core)?,
+            }
+        }
+
+        changed.sort_unstable();
+        changed.dedup();
+        for index in changed {
+            core.gamepad_buttons_set(index, self.gamepads[&index])?;
+        }
+        Ok(())
+    }
+}
+
+enum FrameSource {
+    Fpga(FrameIter),
+    Timer(Instant),
+}
+
+/// Counts the frames output by a core. MiSTer cores are followed using the
+/// framebuffer, other cores use a 60Hz timer.
+pub struct FrameClock {
+    source: FrameSource,
+    frame: u64,
+}
+
+impl FrameClock {
+    pub fn new(core: &mut OneFpgaCore) -> Self {
+        let source = match core.as_mister_core_mut() {
+            Some(core) => FrameSource::Fpga(core.frame_iter()),
+            None => FrameSource::Timer(Instant::now()),
+        };
+
+        Self { source, frame: 0 }
+    }
+
+    /// Update the clock, and return the current frame number. This should be
+    /// called at least once every 7 frames, as frame counters wrap around.
+    pub fn update(&mut self) -> u64 {
+        match &mut self.source {
+            FrameSource::Fpga(iter) => {
+                self.frame += iter.poll() as u64;
+            }
+            FrameSource::Timer(start) => {
+                self.frame = (start.elapsed().as_micros() / FALLBACK_FRAME_MICROS) as u64;
+            }
+        }
+        self.frame
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+}
+
+/// Recording or playback of inputs during the core loop.
+#[derive(Debug, Default)]
+pub enum InputMovie {
+    #[default]
+    Off,
+    Record(InputRecording),
+    Playback(InputPlayback),
+}
+
+impl InputMovie {
+    pub fn is_off(&self) -> bool {
+        matches!(self, InputMovie::Off)
+    }
+
+    /// Whether live inputs should be sent to the core.
+    pub fn forwards_inputs(&self) -> bool {
+        match self {
+            InputMovie::Playback(playback) => playback.is_done(),
+            _ => true,
+        }
+    }
+}
+
+#[test]
+fn recording_serialization() {
+    let mut recording = InputRecording::default();
+    recording.push(
+        3,
+        InputEvent::GamepadButtonDown {
+            index: 0,
+            button: Button::A,
+        },
+    );
+    recording.push(
+        5,
+        InputEvent::KeyDown {
+            key: sdl3::keyboard::Scancode::Space.into(),
+        },
+    );
+
+    let json = serde_json::to_value(&recording).unwrap();
+    assert_eq!(json["frames"], 5);
+    assert_eq!(json["events"][0]["kind"], "gamepadButtonDown");
+    assert_eq!(json["events"][1]["key"], "Space");
+
+    let parsed: InputRecording = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, recording);
+}
+
+#[test]
+fn recording_to_fm2() {
+    use fce_movie_format::FceInputButton;
+
+    let mut recording = InputRecording::default();
+    for (frame, event) in [
+        (
+            1,
+            InputEvent::GamepadButtonDown {
+                index: 0,
+                button: Button::A,
+            },
+        ),
+        (
+            1,
+            InputEvent::GamepadButtonDown {
+                index: 1,
+                button: Button::Start,
+            },
+        ),
+        (
+            2,
+            InputEvent::GamepadButtonUp {
+                index: 0,
+                button: Button::A,
+            },
+        ),
+    ] {
+        recording.push(frame, event);
+    }
+
+    let movie = recording.to_fm2();
+    let pressed = movie
+        .frames()
+        .map(|frame| {
+            let port0 = frame.port0.unwrap();
+            let port1 = frame.port1.unwrap();
+            (
+                port0.as_gamepad().unwrap().has(FceInputButton::A),
+                port1.as_gamepad().unwrap().has(FceInputButton::Start),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(pressed, [(false, false), (true, true), (false, true)]);
+}
@@ -8,9 +8,11 @@
 //!
 //! Platforms are responsible for mocking the FPGA logic, graphics and initializing SDL.
 
-pub use crate::core_manager::CoreManager;
+use cfg_if::cfg_if;
 
 pub mod de10;
+#[cfg(feature = "platform_desktop")]
+pub mod desktop;
 
 // In tests, this is unused as there are no OSD.
 #[cfg_attr(test, allow(unused))]
@@ -25,4 +27,14 @@ mod sizes {
     pub const MAIN: Size = Size::new(256, 16 * 8);
 }
 
-pub use de10::De10Platform as WindowManager;
+// The desktop platform takes precedence, since `platform_de10` is a default feature of
+// this crate and will be enabled alongside it in most workspace builds.
+cfg_if! {
+    if #[cfg(feature = "platform_desktop")] {
+        pub use desktop::DesktopCoreManager as CoreManager;
+        pub use desktop::DesktopPlatform as WindowManager;
+    } else {
+        pub use crate::core_manager::CoreManager;
+        pub use de10::De10Platform as WindowManager;
+    }
+}
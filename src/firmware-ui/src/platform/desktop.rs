@@ -0,0 +1,195 @@
+//! A desktop platform that simulates the DE10-Nano on a workstation.
+//!
+//! The OSD and the toolbar are rendered into SDL windows, and the FPGA is
+//! replaced by a mock [`DesktopCoreManager`] which never touches `/dev/mem`.
+//! This allows running and debugging the whole frontend without a board.
+use std::path::Path;
+
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888};
+use sdl3::event::Event;
+use tracing::{debug, info};
+
+use one_fpga::core::Rom;
+use one_fpga::runner::{CoreLaunchInfo, CoreType};
+use one_fpga::OneFpgaCore;
+
+use crate::macguiver::buffer::DrawBuffer;
+use crate::macguiver::platform::sdl::settings::OutputSettingsBuilder;
+use crate::macguiver::platform::sdl::theme::BinaryColorTheme;
+use crate::macguiver::platform::sdl::{SdlInitState, SdlPlatform, Window};
+use crate::macguiver::platform::{Platform, PlatformWindow};
+use crate::platform::sizes;
+
+/// A core manager that mocks the FPGA. Cores are never programmed, and a
+/// [`one_fpga::core::NullCore`] is returned for every launch.
+pub struct DesktopCoreManager {
+    current_core: Option<OneFpgaCore>,
+    osd_visible: bool,
+}
+
+impl Default for DesktopCoreManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DesktopCoreManager {
+    pub fn new() -> Self {
+        Self {
+            current_core: None,
+            osd_visible: false,
+        }
+    }
+
+    /// Whether the OSD would be shown on top of the core.
+    pub fn is_osd_visible(&self) -> bool {
+        self.osd_visible
+    }
+
+    fn create_core(&mut self) -> OneFpgaCore {
+        let core = OneFpgaCore::null();
+        self.current_core = Some(core.clone());
+        core
+    }
+
+    pub fn load_menu(&mut self) -> Result<OneFpgaCore, String> {
+        info!("Loading menu (simulated)");
+        let core = self.create_core();
+        self.show_osd();
+        Ok(core)
+    }
+
+    pub fn load_core(&mut self, path: impl AsRef<Path>) -> Result<OneFpgaCore, String> {
+        let path = path.as_ref();
+        info!("Loading core from: {:?} (simulated)", path.display());
+
+        // Still validate that the core exists, to catch errors in the frontend.
+        let metadata = std::fs::metadata(path).map_err(|e| e.to_string())?;
+        debug!(size = metadata.len(), "Core would be programmed");
+
+        Ok(self.create_core())
+    }
+
+    pub fn launch(&mut self, info: CoreLaunchInfo<()>) -> Result<OneFpgaCore, String> {
+        let core = match info.core {
+            CoreType::Current => self.get_current_core().ok_or("No core running")?,
+            CoreType::Menu => self.load_menu()?,
+            CoreType::RbfFile(path) => self.load_core(path)?,
+            CoreType::Mra(path) => {
+                info!(?path, "Loading MRA (simulated)");
+                self.load_core(path)?
+            }
+        };
+
+        for bios in &info.bios {
+            info!(path = ?bios.path(), index = ?bios.index(), "Sending BIOS (simulated)");
+        }
+        match &info.rom {
+            Some(Rom::File(path)) => info!(?path, "Sending ROM (simulated)"),
+            Some(Rom::Archive(archive)) => info!(?archive, "Sending ROM (simulated)"),
+            Some(Rom::Memory(path, data)) => {
+                info!(?path, size = data.get_ref().len(), "Sending ROM (simulated)")
+            }
+            None => {}
+        }
+        for (idx, f) in info.files {
+            debug!(idx, ?f, "Mounting file (simulated)");
+        }
+
+        Ok(core)
+    }
+
+    pub fn get_current_core(&mut self) -> Option<OneFpgaCore> {
+        self.current_core.clone()
+    }
+
+    pub fn show_osd(&mut self) {
+        self.osd_visible = true;
+    }
+
+    pub fn hide_osd(&mut self) {
+        self.osd_visible = false;
+    }
+}
+
+pub struct DesktopPlatform {
+    pub platform: SdlPlatform<BinaryColor>,
+    toolbar_window: Window<BinaryColor>,
+    osd_window: Window<BinaryColor>,
+    core_manager: DesktopCoreManager,
+    core_framebuffer: DrawBuffer<Rgb888>,
+}
+
+impl Default for DesktopPlatform {
+    fn default() -> Self {
+        let output_settings = OutputSettingsBuilder::new()
+            .theme(BinaryColorTheme::OledBlue)
+            .build();
+        let mut platform = SdlPlatform::init(SdlInitState::new(output_settings));
+
+        info!("Creating simulator windows.");
+        let mut toolbar_window = platform.window("1FPGA - Toolbar", sizes::TITLE);
+        let mut osd_window = platform.window("1FPGA", sizes::MAIN);
+
+        // Stack the toolbar right on top of the OSD, like on the real OSD.
+        let osd_position = osd_window.position();
+        let toolbar_height = toolbar_window.size().height as i32;
+        toolbar_window.set_position(Point::new(osd_position.x, osd_position.y - toolbar_height));
+        osd_window.focus();
+
+        Self {
+            platform,
+            toolbar_window,
+            osd_window,
+            core_manager: DesktopCoreManager::new(),
+            core_framebuffer: DrawBuffer::new(sizes::MAIN),
+        }
+    }
+}
+
+impl DesktopPlatform {
+    pub fn init(&mut self) {
+        self.core_manager.load_menu().unwrap();
+    }
+
+    pub fn update_toolbar(&mut self, buffer: &DrawBuffer<BinaryColor>) {
+        self.toolbar_window.update(buffer);
+    }
+
+    pub fn update_osd(&mut self, buffer: &DrawBuffer<BinaryColor>) {
+        if self.core_manager.is_osd_visible() {
+            self.osd_window.update(buffer);
+        } else {
+            self.osd_window.update(&DrawBuffer::new(buffer.size()));
+        }
+    }
+
+    pub fn toolbar_dimensions(&self) -> Size {
+        sizes::TITLE
+    }
+
+    pub fn osd_dimensions(&self) -> Size {
+        sizes::MAIN
+    }
+
+    pub fn main_buffer(&mut self) -> &mut DrawBuffer<Rgb888> {
+        &mut self.core_framebuffer
+    }
+
+    pub fn events(&mut self) -> Vec<Event> {
+        self.platform.events()
+    }
+
+    pub fn sdl(&mut self) -> &mut SdlPlatform<BinaryColor> {
+        &mut self.platform
+    }
+
+    pub fn start_loop(&mut self) {}
+
+    pub fn end_loop(&mut self) {}
+
+    pub fn core_manager_mut(&mut self) -> &mut DesktopCoreManager {
+        &mut self.core_manager
+    }
+}
@@ -12,23 +12,46 @@ declare module "1fpga:core" {
     path: string;
   }
 
+  /**
+   * A path to an MRA file (arcade game). The core is found from the `rbf` of
+   * the MRA, and its ROMs are assembled from the zip files it references.
+   */
+  export interface CoreMra {
+    type: "Mra";
+    path: string;
+  }
+
   /**
    * The type of core to start.
    */
-  export type CoreType = CorePath;
+  export type CoreType = CorePath | CoreMra;
 
   /**
-   * A path to a game ROM.
+   * A path to a game ROM. The path can go through a zip archive, e.g.
+   * `/media/fat/games/NES/roms.zip/game.nes`.
    */
   export interface GameRomPath {
     type: "RomPath";
     path: string;
   }
 
+  /**
+   * A game ROM in memory, e.g. after patching it.
+   */
+  export interface GameRomBuffer {
+    type: "RomBuffer";
+    /**
+     * The file name of the ROM. Its extension is used to find the file type
+     * of the core. If missing, the first file type of the core is used.
+     */
+    name?: string;
+    data: ArrayBuffer;
+  }
+
   /**
    * The type of game to load.
    */
-  export type GameType = GameRomPath;
+  export type GameType = GameRomPath | GameRomBuffer;
 
   /**
    * Options for running a core.
@@ -44,6 +67,12 @@ declare module "1fpga:core" {
      */
     game?: GameType;
 
+    /**
+     * BIOS files to load before the game. Each BIOS is sent to the file type
+     * matching its extension, or to an explicit file index.
+     */
+    bios?: (string | { path: string; index: number })[];
+
     /**
      * The save file path to load (or save to). If missing the core will
      * not use any save file.
@@ -125,10 +154,95 @@ declare module "1fpga:core" {
     | CoreSettingBoolOption
     | CoreSettingIntOption;
 
+  /**
+   * An input event sent to the core, with the frame it happened on.
+   */
+  export type RecordedInput = { frame: number } & (
+    | { kind: "keyDown"; key: string }
+    | { kind: "keyUp"; key: string }
+    | { kind: "gamepadButtonDown"; index: number; button: string }
+    | { kind: "gamepadButtonUp"; index: number; button: string }
+    | { kind: "axisMotion"; index: number; axis: string; value: number }
+    | { kind: "mouseMove"; dx: number; dy: number }
+    | { kind: "mouseButtonDown"; button: string }
+    | { kind: "mouseButtonUp"; button: string }
+  );
+
+  /**
+   * A recording of the inputs sent to the core during a loop.
+   */
+  export interface InputRecording {
+    /**
+     * The number of frames recorded.
+     */
+    frames: number;
+    events: RecordedInput[];
+  }
+
   /**
    * Options for the core loop.
    */
-  export interface LoopOptions {}
+  export interface LoopOptions {
+    /**
+     * Record the inputs sent to the core. The recording is returned by
+     * `loop()` when the core is unloaded.
+     */
+    record?: boolean;
+
+    /**
+     * When recording, also write the recording to this path as an FM2 movie
+     * (FCEUX). Only the buttons of the first two gamepads are kept.
+     */
+    fm2?: string;
+
+    /**
+     * Play back a recording of inputs. Live inputs are ignored until the
+     * playback ends. Shortcuts still work.
+     */
+    playback?: InputRecording;
+  }
+
+  /**
+   * Options for capturing frames of the core. Either `path` or `stream` must
+   * be set.
+   */
+  export interface CaptureOptions {
+    /**
+     * The number of frames to capture.
+     */
+    frames: number;
+
+    /**
+     * Only capture one frame out of `interval` frames.
+     * @default 1
+     */
+    interval?: number;
+
+    /**
+     * The maximum width of captured frames. Larger frames are downscaled,
+     * keeping their aspect ratio.
+     */
+    width?: number;
+
+    /**
+     * The maximum height of captured frames. Larger frames are downscaled,
+     * keeping their aspect ratio.
+     */
+    height?: number;
+
+    /**
+     * A path to write the frames to. Paths ending in `.gif` are written as an
+     * animated GIF, other paths are a directory of numbered PNG files.
+     */
+    path?: string;
+
+    /**
+     * A TCP address (e.g. `127.0.0.1:9000`) to stream frames to. Each frame is
+     * sent as its width and height (32-bit big endian), followed by its RGB
+     * pixels.
+     */
+    stream?: string;
+  }
 
   /**
    * Callback for when the core wants to save a savestate.
@@ -172,8 +286,10 @@ declare module "1fpga:core" {
      * The core's main loop, sending any inputs to the core, and checking for
      * shortcuts. This function will return when the core is unloaded by the
      * user.
+     *
+     * If `options.record` is set, returns the recording of inputs.
      */
-    loop(options?: LoopOptions): void;
+    loop(options?: LoopOptions): InputRecording | undefined;
 
     /**
      * Take a screenshot. Output the screenshot to the given path.
@@ -181,6 +297,15 @@ declare module "1fpga:core" {
      */
     screenshot(path: string): void;
 
+    /**
+     * Capture frames of the core as they are completed, to a file or a
+     * stream. Only supported by MiSTer cores.
+     * This is a blocking operation. GIF frames are shown for as long as the
+     * core displayed them.
+     * @throws If the core does not output a frame for one second.
+     */
+    capture(options: CaptureOptions): void;
+
     /**
      * Show the menu for the core. This is different from just the OSD.
      */
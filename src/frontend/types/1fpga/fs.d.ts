@@ -26,7 +26,8 @@ declare module "1fpga:fs" {
   ): Promise<string[]>;
 
   /**
-   * Get the SHA-256 hash of a file.
+   * Get the SHA-256 hash of a file. Paths going through a zip archive
+   * (e.g. `roms.zip/game.nes`) hash the uncompressed member.
    * @param path The path to the file.
    */
   export function sha256(path: string): Promise<string>;
@@ -37,6 +38,11 @@ declare module "1fpga:fs" {
    */
   export function sha256(path: string[]): Promise<string[]>;
 
+  /**
+   * Get the size of a file, in bytes. Paths going through a zip archive
+   * return the uncompressed size of the member.
+   * @param path The path to the file.
+   */
   export function fileSize(path: string): Promise<number>;
   export function fileSize(path: string[]): Promise<number[]>;
 }
@@ -4,24 +4,67 @@
  * Various utility functions.
  */
 declare module "1fpga:utils" {
-  export interface IpsPatchOptions {
+  export interface PatchOptions {
     /**
-     * Whether to skip the checksum verification or not.
+     * Whether to skip the CRC32 verification of the source, target and patch
+     * or not. IPS patches have no checksum.
      * @default false
      */
     skipChecksum?: boolean;
+
+    /**
+     * The expected SHA-256 of the patched ROM, in hexadecimal. Patching fails
+     * if it does not match.
+     */
+    sha256?: string;
+
+    /**
+     * A path to write the patched ROM to, instead of keeping it in memory.
+     */
+    output?: string;
   }
 
   /**
-   * Apply an IPS patch to a ROM. The patch will be applied in-place.
-   * @param rom The ROM to patch.
-   * @param ips The IPS patch to apply.
+   * Apply an IPS patch to a ROM. The patch will be applied in-place on the
+   * `ArrayBuffer`, unless an `output` path is given. ROM files are never
+   * modified, so `output` is required when `rom` is a path.
+   * @param rom The ROM to patch, or a path to it.
+   * @param ips The IPS patch to apply, or a path to it.
    * @param options The options for patching.
-   * @throws If the IPS patch is invalid (e.g. the checksum doesn't match).
+   * @throws If the IPS patch is invalid (e.g. the SHA-256 doesn't match), or
+   *         if `rom` is a path and there is no `output`.
    */
   export function ipsPatch(
-    rom: ArrayBuffer,
-    ips: ArrayBuffer,
-    options?: IpsPatchOptions,
+    rom: ArrayBuffer | string,
+    ips: ArrayBuffer | string,
+    options?: PatchOptions,
   ): Promise<void>;
+
+  /**
+   * Apply a BPS patch to a ROM.
+   * @param rom The ROM to patch, or a path to it.
+   * @param bps The BPS patch to apply, or a path to it.
+   * @param options The options for patching.
+   * @returns The patched ROM, or nothing if it was written to `output`.
+   * @throws If the BPS patch is invalid (e.g. a checksum doesn't match).
+   */
+  export function bpsPatch(
+    rom: ArrayBuffer | string,
+    bps: ArrayBuffer | string,
+    options?: PatchOptions,
+  ): Promise<ArrayBuffer | undefined>;
+
+  /**
+   * Apply a UPS patch to a ROM.
+   * @param rom The ROM to patch, or a path to it.
+   * @param ups The UPS patch to apply, or a path to it.
+   * @param options The options for patching.
+   * @returns The patched ROM, or nothing if it was written to `output`.
+   * @throws If the UPS patch is invalid (e.g. a checksum doesn't match).
+   */
+  export function upsPatch(
+    rom: ArrayBuffer | string,
+    ups: ArrayBuffer | string,
+    options?: PatchOptions,
+  ): Promise<ArrayBuffer | undefined>;
 }
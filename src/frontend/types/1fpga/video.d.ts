@@ -14,4 +14,25 @@ declare module "1fpga:video" {
   export function getResolution():
     | { width: number; height: number }
     | undefined;
+
+  /**
+   * List the shadow masks available for the current core, as paths relative
+   * to the shadow masks directory, without the `.txt` extension.
+   */
+  export function listShadowMasks(): string[];
+
+  /**
+   * Select the shadow mask of the current core. The selection is saved for
+   * this core.
+   * @param name The name of the shadow mask, as returned by `listShadowMasks`,
+   *             or undefined to disable the shadow mask.
+   */
+  export function setShadowMask(name?: string): void;
+
+  /**
+   * Set the shadow mask mode of the current core. `2x` doubles the size of
+   * the mask, for high resolutions.
+   * @param mode The shadow mask mode.
+   */
+  export function setShadowMaskMode(mode: "off" | "on" | "2x"): void;
 }
@@ -0,0 +1,203 @@
+//! Cheats for cores that support them (with a `C` entry in their config string).
+//!
+//! MiSTer cheats are distributed as one zip file per game, in
+//! `cheats/{core}/{game}.zip`. Each file in the zip is a cheat (e.g.
+//! `Infinite Lives.gg`), containing binary codes of 16 bytes each. The codes of
+//! all enabled cheats are concatenated into a table that is sent to the core.
+use std::path::{Path, PathBuf};
+
+use one_fpga::archive::ArchivePath;
+use one_fpga::core::{CoreSettingItem, SettingId};
+use tracing::warn;
+
+/// The root directory of the cheat files.
+pub const CHEATS_ROOT: &str = "/media/fat/cheats";
+
+/// The file index used to send the code table to the core.
+pub const CHEAT_FILE_INDEX: u8 = 255;
+
+/// The size of a single cheat code, in bytes.
+pub const CHEAT_CODE_SIZE: usize = 16;
+
+/// The maximum number of codes that can be enabled at once.
+pub const MAX_CHEAT_CODES: usize = 128;
+
+/// A single cheat, which can contain multiple codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    name: String,
+    codes: Vec<u8>,
+    enabled: bool,
+}
+
+impl Cheat {
+    /// Create a cheat from its name and codes. The size of the codes must be a
+    /// multiple of [`CHEAT_CODE_SIZE`].
+    pub fn new(name: impl Into<String>, codes: Vec<u8>) -> Result<Self, String> {
+        let name = name.into();
+        if codes.is_empty() || codes.len() % CHEAT_CODE_SIZE != 0 {
+            return Err(format!("Invalid code size for cheat {name:?}"));
+        }
+
+        Ok(Self {
+            name,
+            codes,
+            enabled: false,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn codes(&self) -> &[u8] {
+        &self.codes
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The number of codes in this cheat.
+    pub fn nb_codes(&self) -> usize {
+        self.codes.len() / CHEAT_CODE_SIZE
+    }
+
+    pub fn setting_id(&self) -> SettingId {
+        SettingId::from_label(&format!("cheat:{}", self.name))
+    }
+}
+
+/// The cheats available for the current game.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new(cheats: Vec<Cheat>) -> Self {
+        Self { cheats }
+    }
+
+    /// Load the cheats from a zip file. Files with invalid codes are skipped.
+    pub fn from_zip(path: impl AsRef<Path>) -> Result<Self, String> {
+        let mut cheats = Vec::new();
+        for member in ArchivePath::list(path).map_err(|e| e.to_string())? {
+            let name = Path::new(member.member())
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let codes = member.read().map_err(|e| e.to_string())?;
+
+            match Cheat::new(name, codes) {
+                Ok(cheat) => cheats.push(cheat),
+                Err(e) => warn!(?member, "{e}"),
+            }
+        }
+
+        cheats.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self::new(cheats))
+    }
+
+    /// Find the cheat zip file of a game in `{root}/{core}/`, by the name of
+    /// its ROM (case-insensitive).
+    pub fn find(root: impl AsRef<Path>, core: &str, rom: impl AsRef<Path>) -> Option<PathBuf> {
+        let stem = rom.as_ref().file_stem()?.to_string_lossy().to_lowercase();
+
+        std::fs::read_dir(root.as_ref().join(core))
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+                    && path
+                        .file_stem()
+                        .is_some_and(|s| s.to_string_lossy().to_lowercase() == stem)
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    /// The number of codes that are enabled.
+    pub fn enabled_codes(&self) -> usize {
+        self.cheats
+            .iter()
+            .filter(|c| c.enabled)
+            .map(Cheat::nb_codes)
+            .sum()
+    }
+
+    /// Enable or disable a cheat by its setting ID. Returns `None` if no cheat
+    /// has this ID, otherwise whether the cheat is enabled. Cheats cannot be
+    /// enabled past [`MAX_CHEAT_CODES`].
+    pub fn set_enabled(&mut self, id: SettingId, enabled: bool) -> Option<bool> {
+        let total = self.enabled_codes();
+        let cheat = self.cheats.iter_mut().find(|c| c.setting_id() == id)?;
+
+        if enabled && !cheat.enabled && total + cheat.nb_codes() > MAX_CHEAT_CODES {
+            warn!(name = cheat.name, "Too many cheat codes enabled");
+        } else {
+            cheat.enabled = enabled;
+        }
+        Some(cheat.enabled)
+    }
+
+    /// The table of all enabled codes, to send to the core.
+    pub fn code_table(&self) -> Vec<u8> {
+        self.cheats
+            .iter()
+            .filter(|c| c.enabled)
+            .flat_map(|c| c.codes.iter().copied())
+            .collect()
+    }
+
+    /// The setting items for the cheats page.
+    pub fn as_setting_items(&self) -> Vec<CoreSettingItem> {
+        self.cheats
+            .iter()
+            .map(|c| CoreSettingItem::bool_option(c.setting_id(), &c.name, Some(c.enabled)))
+            .collect()
+    }
+}
+
+#[test]
+fn code_table() {
+    let mut cheats = Cheats::new(vec![
+        Cheat::new("Infinite Lives", vec![1; 16]).unwrap(),
+        Cheat::new("Moon Jump", vec![2; 32]).unwrap(),
+        Cheat::new("Max Money", vec![3; 16]).unwrap(),
+    ]);
+    assert!(Cheat::new("Invalid", vec![0; 15]).is_err());
+    assert_eq!(cheats.code_table(), Vec::<u8>::new());
+
+    let ids: Vec<_> = cheats.iter().map(Cheat::setting_id).collect();
+    assert_eq!(cheats.set_enabled(ids[2], true), Some(true));
+    assert_eq!(cheats.set_enabled(ids[1], true), Some(true));
+    assert_eq!(cheats.set_enabled(SettingId::new(0), true), None);
+    assert_eq!(cheats.enabled_codes(), 3);
+
+    let mut expected = vec![2; 32];
+    expected.extend([3; 16]);
+    assert_eq!(cheats.code_table(), expected);
+
+    assert_eq!(cheats.set_enabled(ids[1], false), Some(false));
+    assert_eq!(cheats.code_table(), vec![3; 16]);
+}
+
+#[test]
+fn too_many_codes() {
+    let mut cheats = Cheats::new(vec![
+        Cheat::new("A", vec![0; CHEAT_CODE_SIZE * (MAX_CHEAT_CODES - 1)]).unwrap(),
+        Cheat::new("B", vec![0; CHEAT_CODE_SIZE * 2]).unwrap(),
+    ]);
+    let ids: Vec<_> = cheats.iter().map(Cheat::setting_id).collect();
+    assert_eq!(cheats.set_enabled(ids[0], true), Some(true));
+    assert_eq!(cheats.set_enabled(ids[1], true), Some(false));
+}
@@ -1,3 +1,5 @@
+use crate::filters::FilterKind;
+use crate::shadow_mask::ShadowMaskMode;
 use merg::Merge;
 use num_traits::FloatConst;
 use serde::Deserialize;
@@ -5,6 +7,7 @@ use serde_with::{serde_as, DeserializeFromStr, DurationSeconds};
 use std::collections::HashMap;
 use std::ffi::{c_char, c_int};
 use std::io;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
@@ -552,6 +555,12 @@ pub struct MisterConfig {
     #[merge(strategy = merg::option::overwrite_some)]
     spinner_axis: Option<u8>,
 
+    /// Percentage of the analog stick range, around the center, that is
+    /// ignored. Default is 10.
+    #[validate(range(max = 100))]
+    #[merge(strategy = merg::option::overwrite_some)]
+    pub analog_dead_zone: Option<u8>,
+
     #[serde(with = "mister_bool")]
     #[merge(strategy = merg::option::overwrite_some)]
     sniper_mode: Option<bool>,
@@ -752,7 +761,7 @@ impl MisterConfig {
         self.video_hue.get_or_insert(0);
         self.video_gain_offset
             .get_or_insert("1, 0, 1, 0, 1, 0".parse().unwrap());
-        self.video_conf.get_or_insert("6".to_string());
+        self.video_conf.get_or_insert("8".to_string());
     }
 
     pub fn custom_aspect_ratio(&self) -> Vec<AspectRatio> {
@@ -887,6 +896,13 @@ impl MisterConfig {
         }
     }
 
+    /// The range of core refresh rates (in Hz) vsync_adjust applies to.
+    pub fn refresh_range(&self) -> RangeInclusive<f32> {
+        let min = self.refresh_min.filter(|r| *r > 0.).unwrap_or(0.);
+        let max = self.refresh_max.filter(|r| *r > 0.).unwrap_or(f32::MAX);
+        min..=max
+    }
+
     /// Whether to use PAL in the menu.
     #[inline]
     pub fn menu_pal(&self) -> bool {
@@ -898,6 +914,36 @@ impl MisterConfig {
     pub fn forced_scandoubler(&self) -> bool {
         self.forced_scandoubler.unwrap_or_default()
     }
+
+    /// The mouse speed divider, at least 1.
+    #[inline]
+    pub fn mouse_throttle(&self) -> u8 {
+        self.mouse_throttle.unwrap_or(1).clamp(1, 100)
+    }
+
+    /// The analog stick dead zone, in percent.
+    #[inline]
+    pub fn analog_dead_zone(&self) -> u8 {
+        self.analog_dead_zone.unwrap_or(10).min(100)
+    }
+
+    /// The default filter of a kind, as a path relative to its filters directory.
+    pub fn filter_default(&self, kind: FilterKind) -> Option<&str> {
+        match kind {
+            FilterKind::Horizontal => self.vfilter_default.as_deref(),
+            FilterKind::Vertical => self.vfilter_vertical_default.as_deref(),
+            FilterKind::Scanlines => self.vfilter_scanlines_default.as_deref(),
+            FilterKind::Audio => self.afilter_default.as_deref(),
+            FilterKind::ShadowMask => self.shmask_default.as_deref(),
+        }
+    }
+
+    /// The default shadow mask mode, off if unset or invalid.
+    pub fn shadow_mask_mode(&self) -> ShadowMaskMode {
+        self.shmask_mode_default
+            .and_then(ShadowMaskMode::from_repr)
+            .unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -1011,6 +1057,7 @@ impl Config {
                 | "osd_timeout"
                 | "spinner_throttle"
                 | "spinner_axis"
+                | "analog_dead_zone"
                 | "shmask_mode_default"
                 | "bt_auto_disconnect"
                 | "wheel_force"
@@ -1253,6 +1300,18 @@ fn works_with_empty_file() {
     }
 }
 
+#[test]
+fn default_video_mode_is_1080p() {
+    // The defaults `Config::base()` uses without a MiSTer.ini.
+    let mut config = Config::default();
+    config.mister.set_defaults();
+
+    let modes = video::edid::select_video_mode(&config.mister).unwrap();
+    let mode = modes.vmode_def.unwrap();
+    assert_eq!((mode.param.hact, mode.param.vact), (1920, 1080));
+    assert!((mode.frame_rate() - 60.).abs() < 0.01);
+}
+
 #[cfg(test)]
 mod examples {
     use crate::config::*;
@@ -11,6 +11,12 @@ use crate::config::MisterConfig;
 use crate::fpga::user_io::SetVideoMode;
 use crate::fpga::Spi;
 
+/// The maximum pixel clock supported by the HDMI output, in MHz.
+const MAX_PIXEL_CLOCK_MHZ: f64 = 210.;
+
+/// The lowest pixel clock a mode can be adjusted to, in MHz.
+const MIN_PIXEL_CLOCK_MHZ: f64 = 2.;
+
 pub struct Edid {
     inner: [u8; 256],
 }
@@ -251,7 +257,7 @@ fn parse_edid_vmode_(options: &MisterConfig, edid: &[u8]) -> Result<CustomVideoM
         hact, vact, frame_rate, f_pix
     );
 
-    if f_pix > 210. {
+    if f_pix > MAX_PIXEL_CLOCK_MHZ {
         warn!(
             "EDID: Preferred mode has too high pixel clock ({:.3}MHz).",
             f_pix
@@ -267,7 +273,7 @@ fn parse_edid_vmode_(options: &MisterConfig, edid: &[u8]) -> Result<CustomVideoM
         } else if frame_rate > 60. {
             let f_pix =
                 60. * (((hact + hfp + hbp + hsync) * (vact + vfp + vbp + vsync)) as f64) / 1000000.;
-            if f_pix <= 210. {
+            if f_pix <= MAX_PIXEL_CLOCK_MHZ {
                 warn!(
                     "EDID: Reducing frame rate to 60Hz with new pixel clock {:.3}MHz.",
                     f_pix
@@ -403,6 +409,17 @@ pub enum DefaultVideoMode {
 }
 
 impl DefaultVideoMode {
+    /// Find a preset by name, case-insensitive. Names are the name of the
+    /// variant, with or without the `V` prefix (e.g. `1920x1080r60`), or the
+    /// resolution and frame rate (e.g. `1920x1080@60`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase().replace('@', "r");
+        (0..=u8::MAX).map_while(Self::from_repr).find(|mode| {
+            let variant = format!("{mode:?}").to_ascii_lowercase();
+            variant == name || variant.strip_prefix('v') == Some(name.as_str())
+        })
+    }
+
     pub fn v_param(&self) -> &'static [u32; 8] {
         #[rustfmt::skip]
         const V_PARAM_DEFAULT_MODES: [[u32; 8]; 19] = [
@@ -509,7 +526,7 @@ impl From<DefaultVideoMode> for CustomVideoModeParam {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct CustomVideoModeParam {
     pub mode: u32, // 0
 
@@ -535,12 +552,16 @@ pub struct CustomVideoModeParam {
     pub pr: u32,  // 25
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct CustomVideoMode {
     pub param: CustomVideoModeParam,
 
     pub vrr: bool,
     pub f_pix: f64,
+
+    /// Use a single buffer in the scaler, for the low latency vsync_adjust
+    /// mode. Only set when the pixel clock matches the core refresh rate.
+    pub low_latency: bool,
 }
 
 impl CustomVideoMode {
@@ -627,10 +648,36 @@ impl CustomVideoMode {
         self.f_pix = f_pix;
     }
 
-    pub fn frame_rate(&self) -> f64 {
+    /// The number of pixels in a frame, including blanking.
+    fn frame_pixels(&self) -> f64 {
         let p = &self.param;
-        self.f_pix * 1000000.
-            / (((p.hact + p.hfp + p.hbp + p.hs) * (p.vact + p.vfp + p.vbp + p.vs)) as f64)
+        ((p.hact + p.hfp + p.hbp + p.hs) * (p.vact + p.vfp + p.vbp + p.vs)) as f64
+    }
+
+    pub fn frame_rate(&self) -> f64 {
+        self.f_pix * 1000000. / self.frame_pixels()
+    }
+
+    /// This mode with its pixel clock adjusted so the output refresh rate matches
+    /// a core with this frame time (vsync_adjust). Returns `None` if the pixel
+    /// clock needed is out of range.
+    pub fn with_frame_time(&self, vtime: std::time::Duration) -> Option<Self> {
+        if vtime.is_zero() {
+            return None;
+        }
+
+        let f_pix = self.frame_pixels() / vtime.as_secs_f64() / 1000000.;
+        if !(MIN_PIXEL_CLOCK_MHZ..=MAX_PIXEL_CLOCK_MHZ).contains(&f_pix) {
+            warn!(
+                f_pix,
+                "Adjusted pixel clock is out of range, not adjusting."
+            );
+            return None;
+        }
+
+        let mut mode = *self;
+        mode.set_pll(f_pix);
+        Some(mode)
     }
 }
 
@@ -644,6 +691,14 @@ impl From<DefaultVideoMode> for CustomVideoMode {
     }
 }
 
+/// Cores with a longer frame time (under ~55Hz) are considered PAL.
+const PAL_FRAME_TIME: std::time::Duration = std::time::Duration::from_millis(18);
+
+/// Whether a core with this frame time outputs a PAL (50Hz) picture.
+pub fn is_pal_frame_time(vtime: std::time::Duration) -> bool {
+    vtime > PAL_FRAME_TIME
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct VideoModeDef {
     pub vmode_def: Option<CustomVideoMode>,
@@ -651,16 +706,108 @@ pub struct VideoModeDef {
     pub vmode_ntsc: Option<CustomVideoMode>,
 }
 
-fn parse_custom_video_mode(video_mode: Option<&str>) -> CustomVideoMode {
-    if video_mode.is_none() || matches!(video_mode, Some("auto")) || matches!(video_mode, Some(""))
-    {
-        return DefaultVideoMode::V1920x1080r60.into();
-        // return DefaultVideoMode::V1280x720r60.into();
-        // return DefaultVideoMode::V640x480r60.into();
+impl VideoModeDef {
+    /// Whether both PAL and NTSC modes are defined, to switch between them.
+    pub fn has_pal_ntsc(&self) -> bool {
+        self.vmode_pal.is_some() && self.vmode_ntsc.is_some()
+    }
+
+    /// The video mode to use for a core with this frame time. PAL and NTSC
+    /// modes are only used if both are defined.
+    pub fn for_frame_time(&self, vtime: std::time::Duration) -> Option<CustomVideoMode> {
+        match (self.vmode_pal, self.vmode_ntsc) {
+            (Some(pal), Some(_)) if is_pal_frame_time(vtime) => Some(pal),
+            (Some(_), Some(ntsc)) => Some(ntsc),
+            _ => self.vmode_def,
+        }
+    }
+}
+
+/// Parse an optional PAL or NTSC video mode. Invalid modes are ignored.
+fn parse_standard_video_mode(name: &str, video_mode: Option<&str>) -> Option<CustomVideoMode> {
+    let video_mode = video_mode.filter(|v| !v.trim().is_empty())?;
+    parse_custom_video_mode(video_mode)
+        .map_err(|e| warn!("{e} Ignoring the {name} video mode."))
+        .ok()
+}
+
+/// Parse a `video_mode` option. It can be one of:
+/// - the index of a preset (see [`DefaultVideoMode`]), e.g. `8`;
+/// - the name of a preset, e.g. `1920x1080@60`, `1920x1080r60` or `ntsc15k`;
+/// - explicit timings, `hact,hfp,hs,hbp,vact,vfp,vs,vbp,pclk`, with the pixel
+///   clock in MHz (`148.5`) or KHz (`148500`), optionally followed by the sync
+///   polarities (`+hsync`, `-hsync`, `+vsync`, `-vsync`).
+fn parse_custom_video_mode(video_mode: &str) -> Result<CustomVideoMode, String> {
+    let video_mode = video_mode.trim();
+    let (flags, values): (Vec<&str>, Vec<&str>) = video_mode
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .partition(|v| v.starts_with('+') || v.starts_with('-'));
+
+    let mut v = match values.as_slice() {
+        [] => return Err("Empty video mode.".to_string()),
+        [preset] => {
+            let mode = match preset.parse::<u8>() {
+                Ok(index) => DefaultVideoMode::from_repr(index)
+                    .ok_or_else(|| format!("Unknown video mode index {index}."))?,
+                Err(_) => DefaultVideoMode::from_name(preset)
+                    .ok_or_else(|| format!("Unknown video mode {preset:?}."))?,
+            };
+            let mut v = CustomVideoMode::from(mode);
+            v.param.vic = mode.vic_mode();
+            v
+        }
+        [timings @ .., pclk] if timings.len() == 8 => {
+            let timings = timings
+                .iter()
+                .map(|t| t.parse::<u32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Invalid video mode timing: {e}"))?;
+            let pclk = pclk
+                .parse::<f64>()
+                .map_err(|e| format!("Invalid pixel clock {pclk:?}: {e}"))?;
+            // Old configurations have the pixel clock in KHz.
+            let f_pix = if pclk >= 1000. { pclk / 1000. } else { pclk };
+
+            let mut v = CustomVideoMode::default();
+            v.param.hact = timings[0];
+            v.param.hfp = timings[1];
+            v.param.hs = timings[2];
+            v.param.hbp = timings[3];
+            v.param.vact = timings[4];
+            v.param.vfp = timings[5];
+            v.param.vs = timings[6];
+            v.param.vbp = timings[7];
+            v.f_pix = f_pix;
+            v
+        }
+        _ => return Err(format!("Invalid video mode {video_mode:?}.")),
+    };
+
+    for flag in flags {
+        match flag.to_ascii_lowercase().as_str() {
+            "+hsync" => v.param.hpol = 0,
+            "-hsync" => v.param.hpol = 1,
+            "+vsync" => v.param.vpol = 0,
+            "-vsync" => v.param.vpol = 1,
+            _ => return Err(format!("Unknown video mode flag {flag:?}.")),
+        }
+    }
+
+    let p = &v.param;
+    if p.hact == 0 || p.vact == 0 || p.hs == 0 || p.vs == 0 {
+        return Err(format!("Invalid video mode timings {video_mode:?}."));
+    }
+    if p.hact > 2048 {
+        return Err(format!("Video mode is too wide ({}).", p.hact));
+    }
+    if v.f_pix <= 0. || v.f_pix > MAX_PIXEL_CLOCK_MHZ {
+        return Err(format!("Invalid pixel clock ({:.3}MHz).", v.f_pix));
     }
-    DefaultVideoMode::V1920x1080r60.into()
 
-    // todo!("parse_custom_video_mode")
+    v.set_pll(v.f_pix);
+    Ok(v)
 }
 
 pub fn select_video_mode(options: &MisterConfig) -> Result<VideoModeDef, String> {
@@ -701,12 +848,24 @@ pub fn select_video_mode(options: &MisterConfig) -> Result<VideoModeDef, String>
             }
         }
 
-        let def = parse_custom_video_mode(options.video_conf.as_deref());
+        let def = match options.video_conf.as_deref() {
+            None | Some("") | Some("auto") => DefaultVideoMode::V1920x1080r60.into(),
+            Some(video_mode) => parse_custom_video_mode(video_mode).unwrap_or_else(|e| {
+                warn!("{e} Using the default video mode.");
+                DefaultVideoMode::V1920x1080r60.into()
+            }),
+        };
+
+        let vmode_pal = parse_standard_video_mode("PAL", options.video_conf_pal.as_deref());
+        let vmode_ntsc = parse_standard_video_mode("NTSC", options.video_conf_ntsc.as_deref());
+        if vmode_pal.is_some() != vmode_ntsc.is_some() {
+            warn!("Both PAL and NTSC video modes are needed to switch between them.");
+        }
 
         Ok(VideoModeDef {
             vmode_def: Some(def),
-            vmode_pal: None,
-            vmode_ntsc: None,
+            vmode_pal,
+            vmode_ntsc,
         })
     }
 }
@@ -743,3 +902,106 @@ fn parse_4k_hdmi_edid() {
     assert_eq!(vmode.param.vact, 2160);
     assert_eq!(vmode.frame_rate(), 60.);
 }
+
+#[test]
+fn parse_video_mode_preset() {
+    let v = parse_custom_video_mode("8").unwrap();
+    assert_eq!((v.param.hact, v.param.vact), (1920, 1080));
+    assert_eq!(v.param.vic, 6);
+    assert!((v.f_pix - 148.5).abs() < 0.01);
+
+    for name in ["1280x720@50", "V1280x720r50", "1280x720R50"] {
+        let v = parse_custom_video_mode(name).unwrap();
+        assert_eq!((v.param.hact, v.param.vact, v.param.hfp), (1280, 720, 440));
+    }
+    assert_eq!(parse_custom_video_mode("pal15k").unwrap().param.vact, 288);
+
+    assert!(parse_custom_video_mode("42").is_err());
+    assert!(parse_custom_video_mode("640x400@70").is_err());
+}
+
+#[test]
+fn parse_video_mode_timings() {
+    let v = parse_custom_video_mode("1280,110,40,220,720,5,5,20,74.25,-hsync,+vsync").unwrap();
+    assert_eq!(
+        [v.param.hact, v.param.hfp, v.param.hs, v.param.hbp],
+        [1280, 110, 40, 220]
+    );
+    assert_eq!(
+        [v.param.vact, v.param.vfp, v.param.vs, v.param.vbp],
+        [720, 5, 5, 20]
+    );
+    assert_eq!((v.param.hpol, v.param.vpol), (1, 0));
+    assert_ne!(v.param.pll, [0; 12]);
+    assert!((v.frame_rate() - 60.).abs() < 0.1);
+
+    // Pixel clock in KHz.
+    let v = parse_custom_video_mode("1280,110,40,220,720,5,5,20,74250").unwrap();
+    assert!((v.f_pix - 74.25).abs() < 0.01);
+
+    assert!(parse_custom_video_mode("1280,110,40,220,720,5,5,74.25").is_err());
+    assert!(parse_custom_video_mode("1280,110,40,220,720,5,5,20,500").is_err());
+    assert!(parse_custom_video_mode("1280,110,40,220,720,5,5,20,74.25,+csync").is_err());
+}
+
+#[test]
+fn video_mode_for_frame_time() {
+    use std::time::Duration;
+
+    let pal = Duration::from_micros(20_000);
+    let ntsc = Duration::from_micros(16_639);
+    let mut modes = VideoModeDef {
+        vmode_def: Some(DefaultVideoMode::V1920x1080r60.into()),
+        vmode_pal: Some(parse_custom_video_mode("9").unwrap()),
+        vmode_ntsc: None,
+    };
+    assert!(!modes.has_pal_ntsc());
+    assert_eq!(modes.for_frame_time(pal).unwrap().param.mode, 8);
+
+    modes.vmode_ntsc = Some(parse_custom_video_mode("8").unwrap());
+    assert!(modes.has_pal_ntsc());
+    assert_eq!(modes.for_frame_time(pal).unwrap().param.mode, 9);
+    assert_eq!(modes.for_frame_time(ntsc).unwrap().param.mode, 8);
+}
+
+#[test]
+fn video_mode_with_frame_time() {
+    use std::time::Duration;
+
+    let mode = parse_custom_video_mode("8").unwrap();
+    assert!((mode.frame_rate() - 60.).abs() < 0.01);
+
+    // A PAL core running at 50.007Hz.
+    let vtime = Duration::from_nanos(19_997_200);
+    let adjusted = mode.with_frame_time(vtime).unwrap();
+    assert!((adjusted.frame_rate() - 50.007).abs() < 0.001);
+    assert_eq!(adjusted.param.hact, mode.param.hact);
+    assert_ne!(adjusted.param.pll, mode.param.pll);
+
+    // 1080p at 0.5Hz would need a pixel clock too low, and at 120Hz too high.
+    assert!(mode.with_frame_time(Duration::from_secs(2)).is_none());
+    assert!(mode.with_frame_time(Duration::from_micros(8_333)).is_none());
+    assert!(mode.with_frame_time(Duration::ZERO).is_none());
+}
+
+#[test]
+fn select_pal_ntsc_video_modes() {
+    let config = MisterConfig {
+        video_conf_pal: Some("9".to_string()),
+        video_conf_ntsc: Some("1920x1080@60".to_string()),
+        ..MisterConfig::default()
+    };
+    let modes = select_video_mode(&config).unwrap();
+    assert!(modes.has_pal_ntsc());
+    assert_eq!(modes.vmode_pal.unwrap().param.mode, 9);
+    assert_eq!(modes.vmode_ntsc.unwrap().param.mode, 8);
+
+    // Invalid modes are ignored.
+    let config = MisterConfig {
+        video_conf_pal: Some("640x400@70".to_string()),
+        ..config
+    };
+    let modes = select_video_mode(&config).unwrap();
+    assert!(modes.vmode_pal.is_none());
+    assert!(!modes.has_pal_ntsc());
+}
@@ -12,6 +12,7 @@ use std::ops::{Deref, Range};
 use std::path::Path;
 use std::str::FromStr;
 
+use cyclone_v::memory::MemoryMapper;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use tracing::{debug, warn};
@@ -19,6 +20,9 @@ use tracing::{debug, warn};
 use one_fpga::core::{CoreSettingItem, CoreSettings, SettingId};
 pub use types::*;
 
+use crate::cheats::Cheats;
+use crate::dip::DipSwitches;
+use crate::filters::Filters;
 use crate::fpga::user_io;
 use crate::types::StatusBitMap;
 
@@ -272,6 +276,18 @@ impl ConfigMenu {
             ConfigMenu::Page { label, .. } => {
                 vec![CoreSettingItem::page(label, label, label, Vec::new())]
             }
+            ConfigMenu::Cheat(label) => {
+                let label = label.as_deref().unwrap_or("Cheats");
+                vec![CoreSettingItem::page(label, label, label, Vec::new())]
+            }
+            ConfigMenu::Dip => {
+                vec![CoreSettingItem::page(
+                    "DIP Switches",
+                    "DIP Switches",
+                    "DIP Switches",
+                    Vec::new(),
+                )]
+            }
             ConfigMenu::PageItem(_, sub) => sub.as_core_menu_item(status),
             ConfigMenu::HideIf(mask, sub) => {
                 if status.get(*mask as usize) {
@@ -351,8 +367,7 @@ impl ConfigMenu {
             | ConfigMenu::DisableUnless(_, sub)
             | ConfigMenu::HideIf(_, sub)
             | ConfigMenu::HideUnless(_, sub) => sub.label(),
-            // TODO: add those.
-            // ConfigMenu::Cheat(name) => name.as_ref().map(|x| x.as_str()),
+            ConfigMenu::Cheat(name) => name.as_ref().map(|x| x.as_str()),
             ConfigMenu::LoadFileAndRemember(info) | ConfigMenu::LoadFile(info) => {
                 info.label.as_ref().map(|l| l.as_str())
             }
@@ -390,7 +405,9 @@ pub struct Config {
 impl Config {
     /// Create a new config from the FPGA.
     /// This is disabled in Test as this module is still included in the test build.
-    pub fn from_fpga(fpga: &mut crate::fpga::MisterFpga) -> Result<Self, String> {
+    pub fn from_fpga(
+        fpga: &mut crate::fpga::MisterFpga<impl MemoryMapper>,
+    ) -> Result<Self, String> {
         let mut cfg_string = String::with_capacity(1024);
         fpga.spi_mut()
             .execute(user_io::UserIoGetString(&mut cfg_string))?;
@@ -459,7 +476,15 @@ impl Config {
         None
     }
 
-    pub fn as_core_settings(&self, bits: &StatusBitMap) -> CoreSettings {
+    /// The settings menu of the core. The cheats and DIP switches pages are filled
+    /// with those of the current game, and disabled if there are none.
+    pub fn as_core_settings(
+        &self,
+        bits: &StatusBitMap,
+        cheats: &Cheats,
+        dips: Option<&DipSwitches>,
+        filters: &Filters,
+    ) -> CoreSettings {
         let it = self.menu.iter().flat_map(|item| {
             item.as_core_menu_item(bits)
                 .into_iter()
@@ -474,6 +499,27 @@ impl Config {
                 root.push(core_menu);
                 continue;
             }
+            if let ConfigMenu::Cheat(_) = config_menu {
+                let mut page = core_menu.with_disabled(cheats.is_empty());
+                for item in cheats.as_setting_items() {
+                    page.add_item(item);
+                }
+                root.push(page);
+                continue;
+            }
+            if let ConfigMenu::Dip = config_menu {
+                let page = match dips {
+                    Some(dips) => CoreSettingItem::page(
+                        "DIP Switches",
+                        dips.page(),
+                        dips.page(),
+                        dips.as_setting_items(),
+                    ),
+                    None => core_menu.with_disabled(true),
+                };
+                root.push(page);
+                continue;
+            }
 
             // Find the page number.
             match config_menu.page() {
@@ -489,6 +535,7 @@ impl Config {
             }
         }
 
+        root.extend(filters.as_setting_item());
         CoreSettings::new(self.name.clone(), root)
     }
 }
@@ -600,7 +647,12 @@ fn config_string_nes() {
 #[test]
 fn config_string_nes_menu() {
     let config = Config::from_str(CONFIG_STRING_NES).unwrap();
-    config.as_core_settings(&StatusBitMap::new());
+    config.as_core_settings(
+        &StatusBitMap::new(),
+        &Cheats::default(),
+        None,
+        &Filters::default(),
+    );
 }
 
 #[test]
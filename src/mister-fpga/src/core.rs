@@ -1,3 +1,4 @@
+pub mod analog;
 pub mod buttons;
 pub mod file;
 pub mod volume;
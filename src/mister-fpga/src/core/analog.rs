@@ -0,0 +1,89 @@
+use crate::fpga::user_io::AnalogInput;
+use one_fpga::inputs::Axis;
+
+/// The default dead zone, in percent of the stick range.
+pub const DEFAULT_DEAD_ZONE: u8 = 10;
+
+/// Scale a stick value from SDL (-32768 to 32767) to the core range (-128 to
+/// 127), ignoring values inside the dead zone. The range outside the dead
+/// zone is scaled to the full range of the core.
+pub fn stick_value(value: i16, dead_zone: u8) -> i8 {
+    let threshold = dead_zone.min(100) as i32 * 32768 / 100;
+    let value = value as i32;
+    if value.abs() <= threshold {
+        return 0;
+    }
+
+    let scaled = (value - value.signum() * threshold) * 128 / (32768 - threshold);
+    scaled.clamp(-128, 127) as i8
+}
+
+/// The state of the analog inputs of a joystick, in SDL values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AnalogState {
+    left: (i16, i16),
+    right: (i16, i16),
+    triggers: (i16, i16),
+}
+
+impl AnalogState {
+    /// Update an axis, and return the input to send to the core. Triggers
+    /// act as a paddle, with the left trigger going down and the right
+    /// trigger going up from the center.
+    pub fn update(&mut self, axis: Axis, value: i16, dead_zone: u8) -> Option<AnalogInput> {
+        let stick = |(x, y): (i16, i16)| (stick_value(x, dead_zone), stick_value(y, dead_zone));
+
+        match axis {
+            Axis::LEFT_X => self.left.0 = value,
+            Axis::LEFT_Y => self.left.1 = value,
+            Axis::RIGHT_X => self.right.0 = value,
+            Axis::RIGHT_Y => self.right.1 = value,
+            Axis::TRIGGER_LEFT => self.triggers.0 = value.max(0),
+            Axis::TRIGGER_RIGHT => self.triggers.1 = value.max(0),
+            _ => return None,
+        }
+
+        match axis {
+            Axis::LEFT_X | Axis::LEFT_Y => {
+                let (x, y) = stick(self.left);
+                Some(AnalogInput::LeftStick(x, y))
+            }
+            Axis::RIGHT_X | Axis::RIGHT_Y => {
+                let (x, y) = stick(self.right);
+                Some(AnalogInput::RightStick(x, y))
+            }
+            _ => {
+                let position = self.triggers.1 as i32 - self.triggers.0 as i32 + 32768;
+                Some(AnalogInput::Paddle((position >> 8).clamp(0, 255) as u8))
+            }
+        }
+    }
+}
+
+#[test]
+fn stick_dead_zone() {
+    assert_eq!(stick_value(0, 10), 0);
+    assert_eq!(stick_value(3000, 10), 0);
+    assert_eq!(stick_value(-3000, 10), 0);
+    assert_eq!(stick_value(i16::MAX, 10), 127);
+    assert_eq!(stick_value(i16::MIN, 10), -128);
+    assert_eq!(stick_value(256, 0), 1);
+    assert_eq!(stick_value(i16::MAX, 100), 0);
+}
+
+#[test]
+fn triggers_as_paddle() {
+    let mut state = AnalogState::default();
+    assert_eq!(
+        state.update(Axis::TRIGGER_RIGHT, i16::MAX, 10),
+        Some(AnalogInput::Paddle(255))
+    );
+    assert_eq!(
+        state.update(Axis::TRIGGER_LEFT, i16::MAX, 10),
+        Some(AnalogInput::Paddle(128))
+    );
+    assert_eq!(
+        state.update(Axis::LEFT_Y, -16384, 0),
+        Some(AnalogInput::LeftStick(0, -64))
+    );
+}
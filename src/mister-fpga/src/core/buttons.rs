@@ -166,6 +166,11 @@ impl ButtonMap {
         self.bits.fill(false);
     }
 
+    /// Whether the core has a bit for this button.
+    pub fn has_button(&self, button: MisterFpgaButtons) -> bool {
+        self.core_map.contains_key(button)
+    }
+
     pub fn press(&mut self, button: MisterFpgaButtons) {
         if button != MisterFpgaButtons::NoMapping {
             self.bits
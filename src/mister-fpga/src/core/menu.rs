@@ -7,6 +7,8 @@ use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
 use one_fpga::core::{Bios, CoreSettings, Error, MountedFile, Rom, SaveState, SettingId};
 use one_fpga::inputs::gamepad::ButtonSet;
 use one_fpga::inputs::keyboard::ScancodeSet;
+use one_fpga::inputs::mouse;
+use one_fpga::inputs::Axis;
 use one_fpga::inputs::Button;
 use one_fpga::inputs::Scancode;
 use one_fpga::Core;
@@ -147,6 +149,18 @@ impl Core for MenuCore {
         unreachable!("Menu core does not support inputs")
     }
 
+    fn axis_motion(&mut self, _index: usize, _axis: Axis, _value: i16) -> Result<(), Error> {
+        unreachable!("Menu core does not support inputs")
+    }
+
+    fn mouse_move(&mut self, _dx: i32, _dy: i32) -> Result<(), Error> {
+        unreachable!("Menu core does not support inputs")
+    }
+
+    fn mouse_button(&mut self, _button: mouse::Button, _pressed: bool) -> Result<(), Error> {
+        unreachable!("Menu core does not support inputs")
+    }
+
     fn settings(&self) -> Result<CoreSettings, Error> {
         unreachable!("Menu core does not have a core menu")
     }
@@ -3,38 +3,50 @@ use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use image::DynamicImage;
-use tracing::{debug, info, trace};
+use tracing::{debug, info, trace, warn};
 
 use cyclone_v::memory::{DevMemMemoryMapper, MemoryMapper};
+use one_fpga::archive::ArchivePath;
 use one_fpga::core::{Bios, CoreSettings, Error, MountedFile, Rom, SaveState, SettingId};
 use one_fpga::inputs::gamepad::ButtonSet;
 use one_fpga::inputs::keyboard::ScancodeSet;
-use one_fpga::inputs::{Button, Scancode};
+use one_fpga::inputs::{mouse, Axis, Button, Scancode};
 use one_fpga::Core;
 
-use crate::config::{Config, HdmiLimitedConfig, VgaMode};
+use crate::cheats::{Cheats, CHEATS_ROOT, CHEAT_FILE_INDEX};
+use crate::config::edid::{select_video_mode, CustomVideoMode, VideoModeDef};
+use crate::config::{Config, HdmiLimitedConfig, VgaMode, VsyncAdjustConfig};
 use crate::config_string;
 use crate::config_string::{ConfigMenu, FpgaRamMemoryAddress, LoadFileInfo};
+use crate::core::analog::AnalogState;
 use crate::core::buttons::ButtonMap;
 use crate::core::file::SdCard;
 use crate::core::video;
 use crate::core::video::VideoInfo;
 use crate::core::volume::{IntoVolume, Volume};
+use crate::dip::{DipSwitches, DIPS_ROOT, DIP_FILE_INDEX};
+use crate::filters::{
+    FilterKind, Filters, AUDIO_FILTERS_ROOT, FILTERS_CONFIG_ROOT, VIDEO_FILTERS_ROOT,
+};
 use crate::fpga::file_io::{
     FileExtension, FileIndex, FileTxData16Bits, FileTxData8Bits, FileTxDisabled, FileTxEnabled,
 };
 use crate::fpga::user_io::{
-    ButtonSwitches, GetSdStat, GetStatusBits, SdRead, SdStatOutput, SdWrite, SetSdConf, SetSdInfo,
-    SetSdStat, SetStatusBits, UserIoButtonSwitch, UserIoJoystick, UserIoKeyboardKeyDown,
-    UserIoKeyboardKeyUp, UserIoRtc,
+    ButtonSwitches, GetSdStat, GetStatusBits, SdRead, SdStatOutput, SdWrite, SetAudioFilter,
+    SetScalerCoefficients, SetScalerFilter, SetSdConf, SetSdInfo, SetSdStat, SetShadowMask,
+    SetStatusBits, UserIoAnalogJoystick, UserIoButtonSwitch, UserIoJoystick, UserIoKeyboardKeyDown,
+    UserIoKeyboardKeyUp, UserIoMouse, UserIoRtc,
 };
 use crate::fpga::{user_io, CoreInterfaceType, CoreType, MisterFpga};
 use crate::keyboard::Ps2Scancode;
+use crate::mra::{Mra, ROM_DIRECTORIES};
 use crate::savestate::SaveStateManager;
+use crate::shadow_mask::SHADOW_MASKS_ROOT;
 use crate::types::StatusBitMap;
 
 #[derive(Debug)]
@@ -57,7 +69,10 @@ impl MisterFpgaSendFileInfo {
         }
     }
 
-    pub fn from_path(path: impl AsRef<Path>, core: &MisterFpgaCore) -> Result<Self, String> {
+    pub fn from_path(
+        path: impl AsRef<Path>,
+        core: &MisterFpgaCore<impl MemoryMapper>,
+    ) -> Result<Self, String> {
         let info = core
             .config
             .load_info(path)?
@@ -73,8 +88,49 @@ impl MisterFpgaSendFileInfo {
     }
 }
 
-pub struct MisterFpgaCore {
-    fpga: MisterFpga,
+/// Copy `size` bytes of a reader to the start of a memory region, in chunks.
+/// Returns the CRC32 of the data.
+fn copy_to_memory(
+    mem: &mut impl MemoryMapper,
+    size: usize,
+    mut reader: impl Read,
+) -> Result<u32, String> {
+    const CHUNK_SIZE: usize = 0x1_0000;
+    if mem.len() < size {
+        return Err("File does not fit in memory.".to_string());
+    }
+
+    let mut crc = crc32fast::Hasher::new();
+    let mut offset = 0;
+    while offset < size {
+        let end = (offset + CHUNK_SIZE).min(size);
+        let chunk = mem.as_mut_range(offset..end);
+        reader
+            .read_exact(chunk)
+            .map_err(|e| format!("Could not read the file at offset {offset}: {e}"))?;
+        crc.update(chunk);
+        offset = end;
+    }
+    Ok(crc.finalize())
+}
+
+/// The extension sent to the core for a file. Files without a path use the
+/// first extension of their file type.
+fn file_extension(path: Option<&Path>, info: &LoadFileInfo) -> String {
+    match path.and_then(Path::extension) {
+        Some(ext) => ext.to_string_lossy().to_uppercase(),
+        None => info
+            .extensions
+            .first()
+            .map(|ext| ext.to_uppercase())
+            .unwrap_or_default(),
+    }
+}
+
+/// A MiSTer core running on the FPGA. The memory mapper is only changed for
+/// testing, see `VirtualCore` in the `fake` feature.
+pub struct MisterFpgaCore<M: MemoryMapper = DevMemMemoryMapper> {
+    fpga: MisterFpga<M>,
     pub is_menu: bool,
     pub core_type: CoreType,
     pub spi_type: CoreInterfaceType,
@@ -86,23 +142,56 @@ pub struct MisterFpgaCore {
     // All the images that are mounted. Can only have 16 images at once.
     cards: Box<[Option<SdCard>; 16]>,
 
-    save_states: Option<SaveStateManager<DevMemMemoryMapper>>,
+    save_states: Option<SaveStateManager<M>>,
     gamepads: [ButtonMap; 6],
+    analog: [AnalogState; 6],
+
+    // The keys and gamepad buttons currently pressed.
+    keys: ScancodeSet,
+    buttons: [ButtonSet; 6],
+
+    // The analog stick dead zone, in percent.
+    analog_dead_zone: u8,
+
+    // Mouse buttons pressed, the movement left after throttling and the divider.
+    mouse_buttons: u8,
+    mouse_remainder: (i32, i32),
+    mouse_throttle: u8,
+
+    // The cheats of the current game.
+    cheats: Cheats,
+
+    // The DIP switches of the current game, and where to save them.
+    dips: Option<DipSwitches>,
+    dips_path: Option<PathBuf>,
+
+    // The video and audio filters selected, and where to save them.
+    filters: Filters,
+    filters_path: Option<PathBuf>,
 
     status: StatusBitMap,
     status_counter: u8,
 
-    framebuffer: crate::framebuffer::FpgaFramebuffer<DevMemMemoryMapper>,
+    framebuffer: crate::framebuffer::FpgaFramebuffer<M>,
 
     // A cache for the video_info.
     video_info: Option<VideoInfo>,
 
+    // The video modes to switch between, the last one sent and the video
+    // counter of the core when it was sent.
+    video_modes: Option<VideoModeDef>,
+    video_mode: Option<CustomVideoMode>,
+    video_counter: Option<u8>,
+    vsync_adjust: VsyncAdjustConfig,
+    refresh_range: RangeInclusive<f32>,
+    direct_video: bool,
+
     // Whether we should quit.
     should_quit: bool,
 }
 
-impl MisterFpgaCore {
-    pub fn new(mut fpga: MisterFpga) -> Result<Self, String> {
+impl<M: MemoryMapper> MisterFpgaCore<M> {
+    pub fn new(mut fpga: MisterFpga<M>) -> Result<Self, String> {
         fpga.wait_for_ready();
 
         let config = config_string::Config::from_fpga(&mut fpga)?;
@@ -143,15 +232,33 @@ impl MisterFpgaCore {
             cards: Box::new([NONE; 16]),
             save_states,
             gamepads: [map; 6],
+            analog: Default::default(),
+            keys: ScancodeSet::new(),
+            buttons: Default::default(),
+            analog_dead_zone: crate::core::analog::DEFAULT_DEAD_ZONE,
+            mouse_buttons: 0,
+            mouse_remainder: (0, 0),
+            mouse_throttle: 1,
+            cheats: Cheats::default(),
+            dips: None,
+            dips_path: None,
+            filters: Filters::default(),
+            filters_path: None,
             status: Default::default(),
             status_counter: 0,
             framebuffer: crate::framebuffer::FpgaFramebuffer::default(),
             video_info: None,
+            video_modes: None,
+            video_mode: None,
+            video_counter: None,
+            vsync_adjust: VsyncAdjustConfig::Disabled,
+            refresh_range: 0.0..=f32::MAX,
+            direct_video: false,
             should_quit: false,
         })
     }
 
-    pub fn spi_mut(&mut self) -> &mut crate::fpga::Spi<DevMemMemoryMapper> {
+    pub fn spi_mut(&mut self) -> &mut crate::fpga::Spi<M> {
         self.fpga.spi_mut()
     }
 
@@ -182,6 +289,13 @@ impl MisterFpgaCore {
         crate::framebuffer::FrameIter::new(&self.framebuffer)
     }
 
+    /// Capture the frames of the core as they are completed. See
+    /// [`crate::framebuffer::FrameCapture`].
+    pub fn capture_frames(&mut self) -> crate::framebuffer::FrameCapture<M> {
+        self.framebuffer.update_type_from_core();
+        crate::framebuffer::FrameCapture::new(&self.framebuffer)
+    }
+
     /// Send a file (ROM or BIOS) to the core on an index.
     pub fn load_file(
         &mut self,
@@ -202,21 +316,131 @@ impl MisterFpgaCore {
             .unwrap_or("")
             .to_uppercase();
 
-        let now = std::time::Instant::now();
         debug!("Sending file {:?} to core", path);
 
         let file = File::open(path).map_err(|e| e.to_string())?;
         let size = file.metadata().map_err(|e| e.to_string())?.len() as u32;
 
-        self.start_send_file(info.index(), &ext, size)?;
+        self.send_file(info, &ext, size, file)
+    }
+
+    /// Load a file from memory. The path does not need to exist, and is only used
+    /// to find the index and extension of the file. Without a path, the first file
+    /// type of the core is used.
+    pub fn load_memory(
+        &mut self,
+        path: Option<&Path>,
+        data: &[u8],
+        file_info: Option<LoadFileInfo>,
+    ) -> Result<(), String> {
+        info!(
+            ?path,
+            size = data.len(),
+            ?file_info,
+            "Loading file from memory"
+        );
+        let file_info = match (file_info, path) {
+            (Some(info), _) => info,
+            (None, Some(path)) => self
+                .config
+                .load_info(path)?
+                .ok_or("Could not find info for extension")?,
+            (None, None) => self
+                .config
+                .menu
+                .iter()
+                .filter_map(ConfigMenu::as_load_file)
+                .find_map(ConfigMenu::as_load_file_info)
+                .cloned()
+                .ok_or("Core does not load files")?,
+        };
+
+        let ext = file_extension(path, &file_info);
+
+        let info = MisterFpgaSendFileInfo::from_file_info(file_info)?;
+        self.send_file(info, &ext, data.len() as u32, data)
+    }
+
+    /// Load a file inside an archive. The member is decompressed while it is sent,
+    /// and its own extension is used to find the index of the file.
+    pub fn load_archive(
+        &mut self,
+        archive: &ArchivePath,
+        file_info: Option<LoadFileInfo>,
+    ) -> Result<(), String> {
+        info!(?archive, ?file_info, "Loading file from archive");
+        let member = Path::new(archive.member());
+        let info = file_info.map_or_else(
+            || MisterFpgaSendFileInfo::from_path(member, self),
+            MisterFpgaSendFileInfo::from_file_info,
+        )?;
+        let ext = archive.extension().unwrap_or("").to_uppercase();
+
+        archive
+            .with_reader(|size, reader| self.send_file(info, &ext, size as u32, reader))
+            .map_err(|e| e.to_string())?
+    }
+
+    /// Load a BIOS. The file index is the explicit index of the BIOS, or the one
+    /// matching the extension of its path. The transfer is ended, so the ROM can be
+    /// sent after.
+    pub fn load_bios(&mut self, mut bios: Bios) -> Result<(), String> {
+        info!(?bios, "Loading BIOS");
+        let path = bios.path().map(Path::to_path_buf);
+        let file_info = match bios.index() {
+            Some(index) => self
+                .config
+                .menu
+                .iter()
+                .filter_map(ConfigMenu::as_load_file)
+                .filter_map(ConfigMenu::as_load_file_info)
+                .find(|info| info.index == index)
+                .cloned()
+                .unwrap_or(LoadFileInfo {
+                    save_support: false,
+                    index,
+                    extensions: Vec::new(),
+                    label: None,
+                    address: None,
+                }),
+            None => {
+                let path = path.as_ref().ok_or("BIOS needs a path or an index")?;
+                self.config
+                    .load_info(path)?
+                    .ok_or("Could not find info for BIOS extension")?
+            }
+        };
+
+        let ext = file_extension(path.as_deref(), &file_info);
+        let size = bios.size().map_err(|e| e.to_string())? as u32;
+
+        // Files are shared between clones of a BIOS, so it might have been
+        // read already.
+        bios.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+
+        let info = MisterFpgaSendFileInfo::from_file_info(file_info)?;
+        self.send_file(info, &ext, size, &mut bios)?;
+        self.end_send_file()
+    }
+
+    fn send_file(
+        &mut self,
+        info: MisterFpgaSendFileInfo,
+        ext: &str,
+        size: u32,
+        reader: impl Read,
+    ) -> Result<(), String> {
+        let now = std::time::Instant::now();
+
+        self.start_send_file(info.index(), ext, size)?;
         match info {
             MisterFpgaSendFileInfo::Memory { index, address } => {
                 trace!(?index, ?address, ?ext, ?size, "File info (memory)");
-                self.send_file_to_sdram_(size, address, file)?;
+                self.send_file_to_sdram_(size, address, reader)?;
             }
             MisterFpgaSendFileInfo::Buffered { index } => {
                 trace!(?index, ?ext, ?size, "File info (buffered)");
-                self.send_file_to_buffer_(size, file)?;
+                self.send_file_to_buffer_(size, reader)?;
             }
         }
         self.read_status_bits();
@@ -241,6 +465,210 @@ impl MisterFpgaCore {
         self.fpga.spi_mut().execute(FileTxDisabled)
     }
 
+    /// Whether the core has a cheats menu.
+    pub fn supports_cheats(&self) -> bool {
+        self.config
+            .menu
+            .iter()
+            .any(|item| matches!(item, ConfigMenu::Cheat(_)))
+    }
+
+    pub fn cheats(&self) -> &Cheats {
+        &self.cheats
+    }
+
+    /// Replace the cheats of the current game, and send the enabled codes.
+    pub fn load_cheats(&mut self, cheats: Cheats) -> Result<(), String> {
+        self.cheats = cheats;
+        self.send_cheats()
+    }
+
+    /// Find the cheats of a ROM in the cheats directory, if the core supports
+    /// cheats. Missing cheat files are not an error. The codes are not sent, as
+    /// the ROM is still being transferred; see [`Self::send_cheats`].
+    fn find_cheats(&mut self, rom: &Path) -> Result<(), String> {
+        self.cheats = Cheats::default();
+        if !self.supports_cheats() {
+            return Ok(());
+        }
+
+        if let Some(path) = Cheats::find(CHEATS_ROOT, &self.config.name, rom) {
+            info!(?path, "Loading cheats");
+            self.cheats = Cheats::from_zip(path)?;
+        }
+        Ok(())
+    }
+
+    /// Send the table of enabled cheat codes to the core. An empty table is
+    /// sent as 2 zero bytes, as cores expect at least one word.
+    pub fn send_cheats(&mut self) -> Result<(), String> {
+        let mut table = self.cheats.code_table();
+        if table.is_empty() {
+            table = vec![0; 2];
+        }
+
+        self.start_send_file(CHEAT_FILE_INDEX, "", table.len() as u32)?;
+        self.send_file_to_buffer_(table.len() as u32, table.as_slice())?;
+        self.end_send_file()
+    }
+
+    /// Send the ROMs and DIP switches of an arcade game. Zip files are searched
+    /// next to the MRA file, in its `mame` sub-directory, then in the default ROM
+    /// directories.
+    pub fn load_mra(&mut self, mra: &Mra, mra_path: &Path) -> Result<(), String> {
+        info!(name = mra.name, "Loading MRA");
+        let dir = mra_path.parent().unwrap_or(Path::new("."));
+        let dirs: Vec<PathBuf> = [dir.to_path_buf(), dir.join("mame")]
+            .into_iter()
+            .chain(ROM_DIRECTORIES.iter().map(PathBuf::from))
+            .collect();
+
+        for rom in &mra.roms {
+            let data = rom.assemble(&dirs).map_err(|e| e.to_string())?;
+            let info = MisterFpgaSendFileInfo::Buffered { index: rom.index };
+            self.send_file(info, "", data.len() as u32, data.as_slice())?;
+            self.end_send_file()?;
+        }
+
+        if let Some(dips) = &mra.switches {
+            let path = Path::new(DIPS_ROOT).join(format!("{}.dip", mra.game_name()));
+            self.load_dip_switches(dips.clone(), Some(path))?;
+        }
+        Ok(())
+    }
+
+    pub fn dip_switches(&self) -> Option<&DipSwitches> {
+        self.dips.as_ref()
+    }
+
+    /// Set the DIP switches of the current game and send them to the core. If
+    /// there is a path, the values saved there are loaded, and changes are saved
+    /// to it.
+    pub fn load_dip_switches(
+        &mut self,
+        mut dips: DipSwitches,
+        path: Option<PathBuf>,
+    ) -> Result<(), String> {
+        if let Some(path) = &path {
+            dips.load(path)?;
+        }
+        self.dips = Some(dips);
+        self.dips_path = path;
+        self.send_dip_switches()
+    }
+
+    /// Send the DIP switches to the core, if there are any.
+    pub fn send_dip_switches(&mut self) -> Result<(), String> {
+        let Some(data) = self.dips.as_ref().map(DipSwitches::data) else {
+            return Ok(());
+        };
+
+        self.start_send_file(DIP_FILE_INDEX, "", data.len() as u32)?;
+        self.send_file_to_buffer_(data.len() as u32, data.as_slice())?;
+        self.end_send_file()
+    }
+
+    /// Select the choice of a DIP switch by its setting ID, then save and send
+    /// the switches. Returns `None` if no switch has this ID.
+    fn set_dip_choice(&mut self, id: SettingId, choice: usize) -> Result<Option<usize>, String> {
+        let Some(choice) = self.dips.as_mut().and_then(|d| d.set_choice(id, choice)) else {
+            return Ok(None);
+        };
+
+        if let (Some(dips), Some(path)) = (&self.dips, &self.dips_path) {
+            dips.save(path)?;
+        }
+        self.send_dip_switches()?;
+        Ok(Some(choice))
+    }
+
+    pub fn filters(&self) -> &Filters {
+        &self.filters
+    }
+
+    /// Set the filters available for this core and send the ones selected. If a
+    /// path is given, the filters saved there are selected first, and changes
+    /// are saved to it.
+    pub fn load_filters(
+        &mut self,
+        mut filters: Filters,
+        path: Option<PathBuf>,
+    ) -> Result<(), String> {
+        if let Some(path) = &path {
+            filters.load(path)?;
+        }
+        self.filters = filters;
+        self.filters_path = path;
+        self.send_filters()
+    }
+
+    /// Send the scaler coefficients, the audio filter and the shadow mask to the
+    /// core. Without an audio filter selected, a flat filter is sent.
+    ///
+    /// Cores without a polyphase filter (version 0) cannot use video filters.
+    /// The audio filter and shadow mask are still sent, but an error is
+    /// returned if a video filter is selected.
+    pub fn send_filters(&mut self) -> Result<(), String> {
+        let horizontal = self.filters.video_filter(FilterKind::Horizontal)?;
+        let mut version = 0;
+        self.spi_mut().execute(SetScalerFilter {
+            enabled: horizontal.is_some(),
+            version: &mut version,
+        })?;
+
+        let mut result = Ok(());
+        if let Some(horizontal) = horizontal {
+            if version == 0 {
+                result = Err("The core does not support scaler filters.".to_string());
+            } else {
+                let vertical = self.filters.video_filter(FilterKind::Vertical)?;
+                let scanlines = self.filters.video_filter(FilterKind::Scanlines)?;
+                let vertical = vertical.as_ref().unwrap_or(&horizontal);
+                self.spi_mut().execute(SetScalerCoefficients {
+                    version,
+                    horizontal: &horizontal,
+                    vertical,
+                    scanlines: scanlines.as_ref().unwrap_or(vertical),
+                })?;
+            }
+        }
+
+        let audio = self.filters.audio_filter()?.unwrap_or_default();
+        self.spi_mut().execute(SetAudioFilter(&audio))?;
+
+        let mask = self.filters.shadow_mask()?;
+        self.spi_mut().execute(SetShadowMask {
+            mode: self.filters.shadow_mask_mode(),
+            mask: mask.as_ref(),
+        })?;
+        result
+    }
+
+    /// Change the filters, then save and send them.
+    pub fn update_filters<R>(&mut self, f: impl FnOnce(&mut Filters) -> R) -> Result<R, String> {
+        let result = f(&mut self.filters);
+        self.save_and_send_filters()?;
+        Ok(result)
+    }
+
+    fn save_and_send_filters(&mut self) -> Result<(), String> {
+        if let Some(path) = &self.filters_path {
+            self.filters.save(path)?;
+        }
+        self.send_filters()
+    }
+
+    /// Select a filter by its setting ID, then save and send the filters.
+    /// Returns `None` if no filter has this ID.
+    fn set_filter_choice(&mut self, id: SettingId, choice: usize) -> Result<Option<usize>, String> {
+        let Some(choice) = self.filters.set_choice(id, choice) else {
+            return Ok(None);
+        };
+
+        self.save_and_send_filters()?;
+        Ok(Some(choice))
+    }
+
     /// Return the core parsed config structure.
     pub fn config(&self) -> &config_string::Config {
         &self.config
@@ -257,6 +685,80 @@ impl MisterFpgaCore {
         Ok(video_info)
     }
 
+    /// Set the video modes to switch between when the core changes between PAL
+    /// and NTSC. This is done by [`Core::init`] from the configuration.
+    pub fn set_video_modes(&mut self, modes: Option<VideoModeDef>, direct_video: bool) {
+        self.video_modes = modes;
+        self.video_mode = None;
+        self.video_counter = None;
+        self.direct_video = direct_video;
+    }
+
+    /// Set whether to adjust the output refresh rate to match the core, for
+    /// cores with a refresh rate (in Hz) within `refresh_range`. The low
+    /// latency mode also uses a single buffer in the scaler when adjusted.
+    pub fn set_vsync_adjust(
+        &mut self,
+        vsync_adjust: VsyncAdjustConfig,
+        refresh_range: RangeInclusive<f32>,
+    ) {
+        self.vsync_adjust = vsync_adjust;
+        self.refresh_range = refresh_range;
+        self.video_counter = None;
+    }
+
+    /// The last video mode sent by [`Self::poll_video`], if any.
+    pub fn video_mode(&self) -> Option<&CustomVideoMode> {
+        self.video_mode.as_ref()
+    }
+
+    /// Read the video info of the core again. When the video of the core
+    /// changes, switch between the PAL and NTSC video modes if both are
+    /// defined, and adjust the pixel clock so the output refresh rate matches
+    /// the core if vsync_adjust is enabled. Returns whether the output mode was
+    /// changed.
+    pub fn poll_video(&mut self) -> Result<bool, String> {
+        let info = VideoInfo::create(self.spi_mut())?;
+        self.video_info = Some(info);
+
+        let vsync_adjust = !matches!(self.vsync_adjust, VsyncAdjustConfig::Disabled);
+        let Some(modes) = self
+            .video_modes
+            .filter(|modes| vsync_adjust || modes.has_pal_ntsc())
+        else {
+            return Ok(false);
+        };
+
+        let vtime = info.vtime();
+        if vtime.is_zero() || self.video_counter == Some(info.counter()) {
+            return Ok(false);
+        }
+        self.video_counter = Some(info.counter());
+
+        let Some(mut mode) = modes.for_frame_time(vtime) else {
+            return Ok(false);
+        };
+        if vsync_adjust {
+            let refresh = 1. / vtime.as_secs_f32();
+            if self.refresh_range.contains(&refresh) {
+                if let Some(adjusted) = mode.with_frame_time(vtime) {
+                    mode = adjusted;
+                    mode.low_latency = matches!(self.vsync_adjust, VsyncAdjustConfig::LowLatency);
+                }
+            } else {
+                debug!(refresh, "Refresh rate out of range, not adjusting vsync.");
+            }
+        }
+        if self.video_mode == Some(mode) {
+            return Ok(false);
+        }
+
+        info!(?vtime, f_pix = mode.f_pix, "Switching video mode");
+        video::select_mode(mode, self.direct_video, None, None, self.spi_mut(), false)?;
+        self.video_mode = Some(mode);
+        Ok(true)
+    }
+
     pub fn status_mask(&self) -> StatusBitMap {
         self.config().status_bit_map_mask()
     }
@@ -363,13 +865,90 @@ impl MisterFpgaCore {
             .unwrap();
     }
 
+    /// Set the dead zone of analog sticks, in percent of their range.
+    pub fn set_analog_dead_zone(&mut self, dead_zone: u8) {
+        self.analog_dead_zone = dead_zone.min(100);
+    }
+
+    pub fn analog_dead_zone(&self) -> u8 {
+        self.analog_dead_zone
+    }
+
+    /// Notify the core of an analog axis motion. The value is in SDL range.
+    pub fn analog_axis_motion(
+        &mut self,
+        joystick_idx: usize,
+        axis: Axis,
+        value: i16,
+    ) -> Result<(), String> {
+        let state = self
+            .analog
+            .get_mut(joystick_idx)
+            .ok_or_else(|| format!("Invalid gamepad index {joystick_idx}"))?;
+        let Some(input) = state.update(axis, value, self.analog_dead_zone) else {
+            return Ok(());
+        };
+
+        trace!(joystick_idx, ?input, "Analog input");
+        let command = UserIoAnalogJoystick::new(joystick_idx as u8, input, self.io_version)?;
+        self.fpga.spi_mut().execute(command)
+    }
+
+    /// Set the mouse speed divider. Movements are divided by this value.
+    pub fn set_mouse_throttle(&mut self, throttle: u8) {
+        self.mouse_throttle = throttle.max(1);
+        self.mouse_remainder = (0, 0);
+    }
+
+    /// Notify the core of a relative mouse movement. Positive values go right and down.
+    pub fn mouse_move(&mut self, dx: i32, dy: i32) {
+        let throttle = self.mouse_throttle as i32;
+        let x = self.mouse_remainder.0 + dx;
+        let y = self.mouse_remainder.1 + dy;
+        self.mouse_remainder = (x % throttle, y % throttle);
+
+        let (x, y) = (x / throttle, y / throttle);
+        if x != 0 || y != 0 {
+            self.send_mouse(x, y);
+        }
+    }
+
+    /// Notify the core of a mouse button event. Only the left, right and middle
+    /// buttons are supported.
+    pub fn mouse_button(&mut self, button: mouse::Button, pressed: bool) {
+        let bit = match button {
+            mouse::Button::Left => 0x01,
+            mouse::Button::Right => 0x02,
+            mouse::Button::Middle => 0x04,
+            _ => return,
+        };
+
+        if pressed {
+            self.mouse_buttons |= bit;
+        } else {
+            self.mouse_buttons &= !bit;
+        }
+        self.send_mouse(0, 0);
+    }
+
+    fn send_mouse(&mut self, dx: i32, dy: i32) {
+        let packet = UserIoMouse {
+            buttons: self.mouse_buttons,
+            dx: dx.clamp(-128, 127) as i8,
+            // PS/2 mice count up, SDL counts down.
+            dy: (-dy).clamp(-128, 127) as i8,
+        };
+        trace!(?packet, "Mouse");
+        self.fpga.spi_mut().execute(packet).unwrap();
+    }
+
     /// Access the internal save state manager, in readonly.
-    pub fn save_states(&self) -> Option<&SaveStateManager<DevMemMemoryMapper>> {
+    pub fn save_states(&self) -> Option<&SaveStateManager<M>> {
         self.save_states.as_ref()
     }
 
     /// Access the internal save state manager.
-    pub fn save_states_mut(&mut self) -> Option<&mut SaveStateManager<DevMemMemoryMapper>> {
+    pub fn save_states_mut(&mut self) -> Option<&mut SaveStateManager<M>> {
         self.save_states.as_mut()
     }
 
@@ -378,7 +957,7 @@ impl MisterFpgaCore {
         self.framebuffer.take_screenshot()
     }
 
-    pub fn framebuffer(&self) -> &crate::framebuffer::FpgaFramebuffer<DevMemMemoryMapper> {
+    pub fn framebuffer(&self) -> &crate::framebuffer::FpgaFramebuffer<M> {
         &self.framebuffer
     }
 
@@ -459,27 +1038,14 @@ impl MisterFpgaCore {
         &mut self,
         size: u32,
         address: FpgaRamMemoryAddress,
-        mut reader: impl Read,
+        reader: impl Read,
     ) -> Result<(), String> {
         // Verify invariants.
         if size >= 0x2000_0000 {
             return Err("File too large.".to_string());
         }
-        let mut crc = crc32fast::Hasher::new();
-        let mut mem = DevMemMemoryMapper::create(address.as_usize(), size as usize)?;
-
-        let mut bytes2send = size;
-        while bytes2send > 0 {
-            let sz = reader
-                .read(mem.as_mut_range(..))
-                .map_err(|e| e.to_string())?;
-
-            // crc.update(mem.as_range(start..start + len));
-            bytes2send -= sz as u32;
-        }
-        crc.update(mem.as_range(..));
-
-        let crc = crc.finalize();
+        let mut mem = M::create(address.as_usize(), size as usize)?;
+        let crc = copy_to_memory(&mut mem, size as usize, reader)?;
         debug!("CRC: {:08X}", crc);
         Ok(())
     }
@@ -571,12 +1137,16 @@ impl MisterFpgaCore {
     }
 }
 
-impl Core for MisterFpgaCore {
+fn invalid_gamepad(index: usize) -> Error {
+    Error::Message(format!("Invalid gamepad index {index}"))
+}
+
+impl<M: MemoryMapper + 'static> Core for MisterFpgaCore<M> {
     fn init(&mut self) -> Result<(), Error> {
         self.soft_reset();
         self.fpga
             .spi_mut()
-            .execute(user_io::SetMemorySize::from_fpga().unwrap())
+            .execute(user_io::SetMemorySize::from_fpga::<M>().unwrap())
             .map_err(Error::Message)?;
 
         // Initialize the framebuffer.
@@ -588,6 +1158,8 @@ impl Core for MisterFpgaCore {
         }
 
         let options = Config::base().into_inner();
+        self.set_analog_dead_zone(options.analog_dead_zone());
+        self.set_mouse_throttle(options.mouse_throttle());
 
         let mut switches = UserIoButtonSwitch::new();
         if options.vga_scaler == Some(true) {
@@ -624,6 +1196,21 @@ impl Core for MisterFpgaCore {
 
         video::init(&options);
         video::init_mode(&options, self, self.is_menu);
+        if !self.is_menu {
+            self.set_video_modes(select_video_mode(&options).ok(), options.direct_video());
+            self.set_vsync_adjust(options.vsync_adjust(), options.refresh_range());
+
+            let mut filters = Filters::scan(VIDEO_FILTERS_ROOT, AUDIO_FILTERS_ROOT)
+                .with_shadow_masks(SHADOW_MASKS_ROOT);
+            for kind in FilterKind::ALL {
+                filters.select(kind, options.filter_default(kind));
+            }
+            filters.set_shadow_mask_mode(options.shadow_mask_mode());
+            let path = Path::new(FILTERS_CONFIG_ROOT).join(format!("{}.txt", self.config.name));
+            if let Err(error) = self.load_filters(filters, Some(path)) {
+                warn!(?error, "Could not load the filters");
+            }
+        }
         self.framebuffer.update_type_from_core();
 
         Ok(())
@@ -697,56 +1284,131 @@ impl Core for MisterFpgaCore {
     }
 
     fn send_rom(&mut self, rom: Rom) -> Result<(), Error> {
+        let path = match &rom {
+            Rom::Memory(path, _) => path.clone(),
+            Rom::File(path) => Some(path.clone()),
+            Rom::Archive(archive) => Some(archive.member().into()),
+        };
+
         match rom {
-            Rom::Memory(_, _) => Err(Error::Message(
-                "Memory ROMs are not supported yet.".to_string(),
-            )),
-            Rom::File(path) => self.load_file(&path, None).map_err(Error::Message),
+            Rom::Memory(path, data) => self
+                .load_memory(path.as_deref(), data.get_ref(), None)
+                .map_err(Error::Message)?,
+            Rom::File(path) => self.load_file(&path, None).map_err(Error::Message)?,
+            Rom::Archive(archive) => self.load_archive(&archive, None).map_err(Error::Message)?,
         }
+
+        if let Some(path) = path {
+            self.find_cheats(&path).map_err(Error::Message)?;
+        }
+        Ok(())
     }
 
-    fn send_bios(&mut self, _bios: Bios) -> Result<(), Error> {
-        todo!()
+    fn send_bios(&mut self, bios: Bios) -> Result<(), Error> {
+        self.load_bios(bios).map_err(Error::Message)
     }
 
     fn key_up(&mut self, key: Scancode) -> Result<(), Error> {
         self.key_up(key);
+        self.keys.remove(key);
         Ok(())
     }
 
     fn key_down(&mut self, key: Scancode) -> Result<(), Error> {
         self.key_down(key);
+        self.keys.insert(key);
         Ok(())
     }
 
-    fn keys_set(&mut self, _keys: ScancodeSet) -> Result<(), Error> {
-        todo!()
+    fn keys_set(&mut self, keys: ScancodeSet) -> Result<(), Error> {
+        let released: Vec<Scancode> = self.keys.iter().filter(|k| !keys.contains(*k)).collect();
+        let pressed: Vec<Scancode> = keys.iter().filter(|k| !self.keys.contains(*k)).collect();
+
+        for key in released {
+            self.key_up(key);
+        }
+        for key in pressed {
+            self.key_down(key);
+        }
+        self.keys = keys;
+        Ok(())
     }
 
     fn keys(&self) -> Result<ScancodeSet, Error> {
-        todo!()
+        Ok(self.keys.clone())
     }
 
     fn gamepad_button_up(&mut self, index: usize, button: Button) -> Result<(), Error> {
+        let buttons = self
+            .buttons
+            .get_mut(index)
+            .ok_or_else(|| invalid_gamepad(index))?;
+        buttons.remove(button);
         self.gamepad_button_up(index as u8, button as u8);
         Ok(())
     }
 
     fn gamepad_button_down(&mut self, index: usize, button: Button) -> Result<(), Error> {
+        let buttons = self
+            .buttons
+            .get_mut(index)
+            .ok_or_else(|| invalid_gamepad(index))?;
+        buttons.insert(button);
         self.gamepad_button_down(index as u8, button as u8);
         Ok(())
     }
 
-    fn gamepad_buttons_set(&mut self, _index: usize, _buttons: ButtonSet) -> Result<(), Error> {
-        todo!()
+    fn gamepad_buttons_set(&mut self, index: usize, buttons: ButtonSet) -> Result<(), Error> {
+        let current = *self
+            .buttons
+            .get(index)
+            .ok_or_else(|| invalid_gamepad(index))?;
+        if current == buttons {
+            return Ok(());
+        }
+
+        // Update the whole map, then send the joystick state once.
+        let mut map = self.gamepads[index];
+        for button in current.iter().filter(|b| !buttons.contains(*b)) {
+            map.up(button as u8);
+        }
+        for button in buttons.iter().filter(|b| !current.contains(*b)) {
+            map.down(button as u8);
+        }
+        self.send_gamepad(index as u8, map);
+        self.buttons[index] = buttons;
+        Ok(())
+    }
+
+    fn gamepad_buttons(&self, index: usize) -> Result<Option<ButtonSet>, Error> {
+        Ok(self.buttons.get(index).copied())
     }
 
-    fn gamepad_buttons(&self, _index: usize) -> Result<Option<ButtonSet>, Error> {
-        todo!()
+    fn axis_motion(&mut self, index: usize, axis: Axis, value: i16) -> Result<(), Error> {
+        if index >= self.analog.len() {
+            return Err(invalid_gamepad(index));
+        }
+        self.analog_axis_motion(index, axis, value)
+            .map_err(Error::Message)
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32) -> Result<(), Error> {
+        self.mouse_move(dx, dy);
+        Ok(())
+    }
+
+    fn mouse_button(&mut self, button: mouse::Button, pressed: bool) -> Result<(), Error> {
+        self.mouse_button(button, pressed);
+        Ok(())
     }
 
     fn settings(&self) -> Result<CoreSettings, Error> {
-        Ok(self.config.as_core_settings(self.status_bits()))
+        Ok(self.config.as_core_settings(
+            self.status_bits(),
+            &self.cheats,
+            self.dips.as_ref(),
+            &self.filters,
+        ))
     }
 
     fn trigger(&mut self, id: SettingId) -> Result<(), Error> {
@@ -779,6 +1441,19 @@ impl Core for MisterFpgaCore {
     }
 
     fn int_option(&mut self, id: SettingId, value: u32) -> Result<u32, Error> {
+        if let Some(choice) = self
+            .set_filter_choice(id, value as usize)
+            .map_err(Error::Message)?
+        {
+            return Ok(choice as u32);
+        }
+        if let Some(choice) = self
+            .set_dip_choice(id, value as usize)
+            .map_err(Error::Message)?
+        {
+            return Ok(choice as u32);
+        }
+
         if let Some(ConfigMenu::Option { bits, choices, .. }) = self
             .menu_options()
             .iter()
@@ -798,6 +1473,17 @@ impl Core for MisterFpgaCore {
     }
 
     fn bool_option(&mut self, id: SettingId, value: bool) -> Result<bool, Error> {
+        if let Some(enabled) = self.cheats.set_enabled(id, value) {
+            self.send_cheats().map_err(Error::Message)?;
+            return Ok(enabled);
+        }
+        if let Some(choice) = self
+            .set_dip_choice(id, value as usize)
+            .map_err(Error::Message)?
+        {
+            return Ok(choice != 0);
+        }
+
         if let Some(ConfigMenu::Option { bits, .. }) = self
             .menu_options()
             .iter()
@@ -831,3 +1517,26 @@ impl Core for MisterFpgaCore {
         self.should_quit
     }
 }
+
+#[test]
+fn copy_to_memory_from_zip() {
+    use cyclone_v::memory::BufferMemoryMapper;
+
+    // Deflated entries are read in short chunks.
+    let data = (0..200_000u32)
+        .map(|i| (i * 7 / 3) as u8)
+        .collect::<Vec<_>>();
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    zip.start_file("game.bin", Default::default()).unwrap();
+    zip.write_all(&data).unwrap();
+    let mut archive = zip::ZipArchive::new(zip.finish().unwrap()).unwrap();
+
+    let mut mem = BufferMemoryMapper::create(0x3000_0000, data.len()).unwrap();
+    let crc = copy_to_memory(&mut mem, data.len(), archive.by_index(0).unwrap()).unwrap();
+    assert_eq!(mem.as_range(..), data);
+    assert_eq!(crc, crc32fast::hash(&data));
+
+    // The file ends early.
+    let mut mem = BufferMemoryMapper::create(0x3000_0000, 20).unwrap();
+    assert!(copy_to_memory(&mut mem, 20, &data[..10]).is_err());
+}
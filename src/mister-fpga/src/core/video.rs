@@ -34,7 +34,7 @@ mod private {
 
     pub fn init_mode(
         options: &config::MisterConfig,
-        _core: &mut crate::core::MisterFpgaCore,
+        _core: &mut crate::core::MisterFpgaCore<impl MemoryMapper>,
         _is_menu: bool,
     ) -> Result<(), String> {
         debug!(
@@ -85,7 +85,7 @@ pub fn select_mode(
 
 pub fn init_mode(
     options: &config::MisterConfig,
-    core: &mut crate::core::MisterFpgaCore,
+    core: &mut crate::core::MisterFpgaCore<impl MemoryMapper>,
     is_menu: bool,
 ) {
     if !is_menu {
@@ -193,6 +193,11 @@ impl VideoInfo {
         self.aspect_ratio
     }
 
+    /// A counter incremented by the core every time its video output changes.
+    pub(crate) fn counter(&self) -> u8 {
+        self.res as u8
+    }
+
     pub fn vtime(&self) -> Duration {
         Duration::from_nanos(self.vtime_ms as u64 * 10)
     }
@@ -564,7 +564,7 @@ fn hdmi_config_set_hdr(device: &mut LinuxI2CDevice, options: &MisterConfig) -> R
 
 pub fn init_mode(
     options: &config::MisterConfig,
-    core: &mut crate::core::MisterFpgaCore,
+    core: &mut crate::core::MisterFpgaCore<impl MemoryMapper>,
     is_menu: bool,
 ) -> Result<(), String> {
     video_mode::init_mode(options, core.spi_mut(), is_menu)
@@ -0,0 +1,304 @@
+//! DIP switches of arcade cores (with a `DIP` entry in their config string).
+//!
+//! The switches are defined in the `<switches>` element of an MRA file:
+//!
+//! ```xml
+//! <switches default="FF,02" page_name="Switches">
+//!   <dip bits="0,1" name="Lives" ids="2,3,4,5"/>
+//!   <dip bits="9" name="Demo Sounds" ids="Off,On"/>
+//! </switches>
+//! ```
+//!
+//! `bits` is a single bit or an inclusive range of bits in the 64-bit DIP value,
+//! `ids` are the names of the choices, and the optional `values` are the value
+//! of each choice (by default, its index). `default` is a list of bytes in the
+//! base of the optional `base` attribute (16 by default), least significant first.
+use std::ops::Range;
+use std::path::Path;
+
+use one_fpga::core::{CoreSettingItem, SettingId};
+
+/// The file index used to send the DIP switches to the core.
+pub const DIP_FILE_INDEX: u8 = 254;
+
+/// The directory where DIP switches are saved, per game.
+pub const DIPS_ROOT: &str = "/media/fat/config/dips";
+
+/// A single DIP switch, which can span multiple bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DipSwitch {
+    name: String,
+    bits: Range<u8>,
+    choices: Vec<String>,
+    values: Vec<u64>,
+}
+
+impl DipSwitch {
+    fn from_node(node: roxmltree::Node) -> Result<Self, String> {
+        let name = node.attribute("name").ok_or("DIP switch without a name")?;
+        let bits = node
+            .attribute("bits")
+            .ok_or_else(|| format!("DIP switch {name:?} without bits"))?;
+        let bits = bits
+            .split(',')
+            .map(|b| b.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid bits for DIP switch {name:?}: {e}"))?;
+        let bits = match bits.as_slice() {
+            [bit] if *bit < 64 => *bit..*bit + 1,
+            [from, to] if from <= to && *to < 64 => *from..*to + 1,
+            _ => return Err(format!("Invalid bits for DIP switch {name:?}")),
+        };
+
+        let choices: Vec<String> = node
+            .attribute("ids")
+            .unwrap_or("Off,On")
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .collect();
+        let values = match node.attribute("values") {
+            Some(values) => values
+                .split(',')
+                .map(|v| parse_number(v.trim()))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (0..choices.len() as u64).collect(),
+        };
+        if values.len() != choices.len() {
+            return Err(format!(
+                "DIP switch {name:?} has {} values for {} ids",
+                values.len(),
+                choices.len()
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            bits,
+            choices,
+            values,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn choices(&self) -> &[String] {
+        &self.choices
+    }
+
+    pub fn setting_id(&self) -> SettingId {
+        SettingId::from_label(&format!("dip:{}", self.name))
+    }
+
+    fn mask(&self) -> u64 {
+        let len = self.bits.end - self.bits.start;
+        let mask = if len >= 64 { u64::MAX } else { (1 << len) - 1 };
+        mask << self.bits.start
+    }
+
+    /// The index of the choice selected by a DIP value. Unknown values select
+    /// the first choice.
+    pub fn choice(&self, value: u64) -> usize {
+        let bits = (value & self.mask()) >> self.bits.start;
+        self.values.iter().position(|v| *v == bits).unwrap_or(0)
+    }
+
+    /// Update a DIP value with a choice.
+    pub fn with_choice(&self, value: u64, choice: usize) -> u64 {
+        let bits = self.values[choice % self.values.len()] << self.bits.start;
+        (value & !self.mask()) | (bits & self.mask())
+    }
+}
+
+/// Parse a number in an MRA, which can be decimal or hexadecimal (`0x` prefix).
+fn parse_number(value: &str) -> Result<u64, String> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|e| format!("Invalid number {value:?}: {e}"))
+}
+
+/// The DIP switches of a game, and their current value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DipSwitches {
+    page: String,
+    default: u64,
+    value: u64,
+    switches: Vec<DipSwitch>,
+}
+
+impl DipSwitches {
+    /// Parse the `<switches>` element of an MRA file. Returns `None` if there
+    /// is no such element.
+    pub fn from_mra(xml: &str) -> Result<Option<Self>, String> {
+        let doc = roxmltree::Document::parse(xml).map_err(|e| e.to_string())?;
+        doc.root_element()
+            .children()
+            .find(|n| n.has_tag_name("switches"))
+            .map(Self::from_node)
+            .transpose()
+    }
+
+    /// Parse a `<switches>` element.
+    pub fn from_node(node: roxmltree::Node) -> Result<Self, String> {
+        let base = node
+            .attribute("base")
+            .map(|b| b.parse::<u32>().map_err(|e| format!("Invalid base: {e}")))
+            .transpose()?
+            .unwrap_or(16);
+        let default = node
+            .attribute("default")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|b| !b.trim().is_empty())
+            .take(8)
+            .enumerate()
+            .map(|(i, b)| {
+                u8::from_str_radix(b.trim(), base)
+                    .map(|b| (b as u64) << (i * 8))
+                    .map_err(|e| format!("Invalid default DIP value {b:?}: {e}"))
+            })
+            .sum::<Result<u64, String>>()?;
+
+        let switches = node
+            .children()
+            .filter(|n| n.has_tag_name("dip"))
+            .map(DipSwitch::from_node)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            page: node
+                .attribute("page_name")
+                .unwrap_or("DIP Switches")
+                .to_string(),
+            default,
+            value: default,
+            switches,
+        })
+    }
+
+    /// The label of the DIP switches page.
+    pub fn page(&self) -> &str {
+        &self.page
+    }
+
+    pub fn switches(&self) -> &[DipSwitch] {
+        &self.switches
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: u64) {
+        self.value = value;
+    }
+
+    /// Reset the switches to their default value.
+    pub fn reset(&mut self) {
+        self.value = self.default;
+    }
+
+    /// Select a choice of a switch by its setting ID. Returns `None` if no
+    /// switch has this ID, otherwise the new choice.
+    pub fn set_choice(&mut self, id: SettingId, choice: usize) -> Option<usize> {
+        let switch = self.switches.iter().find(|s| s.setting_id() == id)?;
+        self.value = switch.with_choice(self.value, choice);
+        Some(switch.choice(self.value))
+    }
+
+    /// The data to send to the core.
+    pub fn data(&self) -> [u8; 8] {
+        self.value.to_le_bytes()
+    }
+
+    /// Load a value saved with [`Self::save`]. Missing files keep the current value.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        match std::fs::read(path) {
+            Ok(data) => {
+                let mut bytes = [0u8; 8];
+                let len = data.len().min(8);
+                bytes[..len].copy_from_slice(&data[..len]);
+                self.value = u64::from_le_bytes(bytes);
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Save the current value.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, self.data()).map_err(|e| e.to_string())
+    }
+
+    /// The setting items for the DIP switches page. Switches with 2 choices
+    /// are boolean options.
+    pub fn as_setting_items(&self) -> Vec<CoreSettingItem> {
+        self.switches
+            .iter()
+            .map(|s| {
+                let choice = s.choice(self.value);
+                if s.choices.len() == 2 {
+                    CoreSettingItem::bool_option(s.setting_id(), &s.name, Some(choice != 0))
+                } else {
+                    CoreSettingItem::int_option(
+                        s.setting_id(),
+                        &s.name,
+                        s.choices.clone(),
+                        Some(choice),
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn switches() {
+    let mut dips = DipSwitches::from_mra(
+        r#"<misterromdescription>
+            <name>Test</name>
+            <switches default="FF,02" page_name="Switches">
+                <dip bits="0,1" name="Lives" ids="2,3,4,5" values="3,2,1,0"/>
+                <dip bits="9" name="Demo Sounds" ids="Off,On"/>
+            </switches>
+        </misterromdescription>"#,
+    )
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(dips.page(), "Switches");
+    assert_eq!(dips.value(), 0x02FF);
+
+    let lives = dips.switches()[0].clone();
+    let sounds = dips.switches()[1].clone();
+    assert_eq!(lives.choice(dips.value()), 0);
+    assert_eq!(sounds.choice(dips.value()), 1);
+
+    assert_eq!(dips.set_choice(lives.setting_id(), 2), Some(2));
+    assert_eq!(dips.set_choice(sounds.setting_id(), 0), Some(0));
+    assert_eq!(dips.value(), 0x00FD);
+    assert_eq!(dips.data(), [0xFD, 0, 0, 0, 0, 0, 0, 0]);
+
+    dips.reset();
+    assert_eq!(dips.value(), 0x02FF);
+}
+
+#[test]
+fn no_switches() {
+    assert_eq!(DipSwitches::from_mra("<misterromdescription/>"), Ok(None));
+    assert!(DipSwitches::from_mra(
+        r#"<misterromdescription><switches><dip name="Bad" bits="70" ids="A,B"/></switches></misterromdescription>"#
+    )
+    .is_err());
+}
@@ -0,0 +1,609 @@
+//! Video scaler and audio filters.
+//!
+//! Video filters are MiSTer polyphase coefficient files, in `filters/`. Each
+//! line is a phase of 4 comma separated taps (e.g. `-4, 132, -4, 0`), and `#`
+//! starts a comment. Files have 16, 32 or 64 phases; shorter files are repeated
+//! to 64 phases. The horizontal filter is also used vertically, and the vertical
+//! filter for scanlines, unless they are selected separately.
+//!
+//! Audio filters are IIR filters, in `filters_audio/`. Each line is a value:
+//! the gain, the 3 `x` coefficients (integers) and the 3 `y` coefficients.
+//!
+//! Shadow masks (see [`crate::shadow_mask`]) are selected with the filters.
+use std::path::{Path, PathBuf};
+
+use fixed_map::{Key, Map};
+use one_fpga::core::{CoreSettingItem, SettingId};
+use tracing::warn;
+
+use crate::shadow_mask::{ShadowMask, ShadowMaskMode};
+
+/// The root directory of the video filters.
+pub const VIDEO_FILTERS_ROOT: &str = "/media/fat/filters";
+
+/// The root directory of the audio filters.
+pub const AUDIO_FILTERS_ROOT: &str = "/media/fat/filters_audio";
+
+/// The directory where the filters selected are saved, per core.
+pub const FILTERS_CONFIG_ROOT: &str = "/media/fat/config/filters";
+
+/// The number of phases of a polyphase filter, as sent to the scaler.
+pub const FILTER_PHASES: usize = 64;
+
+/// The number of fractional bits of the audio filter gain.
+const AUDIO_GAIN_BITS: u32 = 40;
+
+/// The number of fractional bits of the audio filter `y` coefficients.
+const AUDIO_Y_FRACTION_BITS: u32 = 21;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Key)]
+pub enum FilterKind {
+    Horizontal,
+    Vertical,
+    Scanlines,
+    Audio,
+    ShadowMask,
+}
+
+impl FilterKind {
+    pub const ALL: [FilterKind; 5] = [
+        FilterKind::Horizontal,
+        FilterKind::Vertical,
+        FilterKind::Scanlines,
+        FilterKind::Audio,
+        FilterKind::ShadowMask,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterKind::Horizontal => "Horizontal Filter",
+            FilterKind::Vertical => "Vertical Filter",
+            FilterKind::Scanlines => "Scanline Filter",
+            FilterKind::Audio => "Audio Filter",
+            FilterKind::ShadowMask => "Shadow Mask",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            FilterKind::Horizontal => "horizontal",
+            FilterKind::Vertical => "vertical",
+            FilterKind::Scanlines => "scanlines",
+            FilterKind::Audio => "audio",
+            FilterKind::ShadowMask => "shadow_mask",
+        }
+    }
+
+    /// The label of the first choice, when no filter file is selected.
+    fn none_label(self) -> &'static str {
+        match self {
+            FilterKind::Horizontal => "Off",
+            FilterKind::Vertical => "Same as Horizontal",
+            FilterKind::Scanlines => "Same as Vertical",
+            FilterKind::Audio => "Default",
+            FilterKind::ShadowMask => "None",
+        }
+    }
+
+    pub fn setting_id(self) -> SettingId {
+        SettingId::from_label(&format!("filter:{}", self.key()))
+    }
+}
+
+/// Split a filter file into its values, without comments and empty lines.
+fn filter_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+}
+
+/// A polyphase filter of the video scaler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolyphaseFilter {
+    phases: Vec<[i16; 4]>,
+}
+
+impl PolyphaseFilter {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let phases = filter_lines(content)
+            .map(|line| {
+                let taps = line
+                    .split(',')
+                    .map(|t| t.trim().parse::<i16>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Invalid filter phase {line:?}: {e}"))?;
+                <[i16; 4]>::try_from(taps)
+                    .map_err(|_| format!("Filter phase {line:?} does not have 4 taps"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match phases.len() {
+            16 | 32 | 64 => {
+                let repeat = FILTER_PHASES / phases.len();
+                Ok(Self {
+                    phases: phases
+                        .iter()
+                        .flat_map(|p| std::iter::repeat_n(*p, repeat))
+                        .collect(),
+                })
+            }
+            n => Err(format!("Invalid number of filter phases: {n}")),
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::parse(&std::fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    /// The [`FILTER_PHASES`] phases of the filter.
+    pub fn phases(&self) -> &[[i16; 4]] {
+        &self.phases
+    }
+}
+
+/// An IIR audio filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFilter {
+    gain: f64,
+    x: [u8; 3],
+    y: [f64; 3],
+}
+
+impl Default for AudioFilter {
+    /// A flat filter, which passes the audio through unchanged.
+    fn default() -> Self {
+        Self {
+            gain: 1. - 1. / (1u64 << AUDIO_GAIN_BITS) as f64,
+            x: [1, 0, 0],
+            y: [0.; 3],
+        }
+    }
+}
+
+impl AudioFilter {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let values: Vec<&str> = filter_lines(content).collect();
+        let [gain, x0, x1, x2, y0, y1, y2] = values[..] else {
+            return Err(format!(
+                "Audio filters have 7 values, found {}",
+                values.len()
+            ));
+        };
+
+        let float = |v: &str| {
+            v.parse::<f64>()
+                .map_err(|e| format!("Invalid audio filter value {v:?}: {e}"))
+        };
+        let int = |v: &str| {
+            v.parse::<u8>()
+                .map_err(|e| format!("Invalid audio filter value {v:?}: {e}"))
+        };
+
+        let gain = float(gain)?;
+        if !(0.0..1.0).contains(&gain) {
+            return Err(format!("Invalid audio filter gain {gain}"));
+        }
+        Ok(Self {
+            gain,
+            x: [int(x0)?, int(x1)?, int(x2)?],
+            y: [float(y0)?, float(y1)?, float(y2)?],
+        })
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::parse(&std::fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    /// The gain, as a 40 bits fraction.
+    pub fn gain(&self) -> u64 {
+        (self.gain * (1u64 << AUDIO_GAIN_BITS) as f64).round() as u64
+    }
+
+    pub fn x(&self) -> [u8; 3] {
+        self.x
+    }
+
+    /// The `y` coefficients, as 24 bits fixed point values with 21 fractional
+    /// bits.
+    pub fn y(&self) -> [i32; 3] {
+        self.y.map(|y| {
+            let y = (y * (1 << AUDIO_Y_FRACTION_BITS) as f64).round() as i32;
+            y.clamp(-(1 << 23), (1 << 23) - 1)
+        })
+    }
+}
+
+/// List the filter files in a directory and its subdirectories, as paths
+/// relative to the root without the `.txt` extension.
+fn list_filters(root: &Path) -> Vec<String> {
+    fn walk(root: &Path, dir: &Path, names: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(root, &path, names);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("txt"))
+            {
+                if let Ok(relative) = path.with_extension("").strip_prefix(root) {
+                    names.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+    walk(root, root, &mut names);
+    names.sort();
+    names
+}
+
+/// The key of the shadow mask mode, in saved filters.
+const SHADOW_MASK_MODE_KEY: &str = "shadow_mask_mode";
+
+/// The filters available, and the ones selected for the current core.
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    video_root: PathBuf,
+    audio_root: PathBuf,
+    shadow_mask_root: PathBuf,
+    available: Map<FilterKind, Vec<String>>,
+    selected: Map<FilterKind, String>,
+    shadow_mask_mode: ShadowMaskMode,
+}
+
+impl Filters {
+    /// List the filters available in the video and audio filter directories.
+    pub fn scan(video_root: impl AsRef<Path>, audio_root: impl AsRef<Path>) -> Self {
+        let video_root = video_root.as_ref().to_path_buf();
+        let audio_root = audio_root.as_ref().to_path_buf();
+        let video = list_filters(&video_root);
+
+        let mut available = Map::new();
+        available.insert(FilterKind::Horizontal, video.clone());
+        available.insert(FilterKind::Vertical, video.clone());
+        available.insert(FilterKind::Scanlines, video);
+        available.insert(FilterKind::Audio, list_filters(&audio_root));
+
+        Self {
+            video_root,
+            audio_root,
+            available,
+            ..Default::default()
+        }
+    }
+
+    /// List the shadow masks available in a directory.
+    pub fn with_shadow_masks(mut self, root: impl AsRef<Path>) -> Self {
+        self.shadow_mask_root = root.as_ref().to_path_buf();
+        self.available
+            .insert(FilterKind::ShadowMask, list_filters(&self.shadow_mask_root));
+        self
+    }
+
+    /// Whether there are no filters to select.
+    pub fn is_empty(&self) -> bool {
+        self.available.values().all(Vec::is_empty)
+    }
+
+    pub fn available(&self, kind: FilterKind) -> &[String] {
+        self.available
+            .get(kind)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn selected(&self, kind: FilterKind) -> Option<&str> {
+        self.selected.get(kind).map(String::as_str)
+    }
+
+    /// Select a filter by name, with or without its `.txt` extension. Unknown
+    /// names are ignored. Returns whether the filter was selected.
+    pub fn select(&mut self, kind: FilterKind, name: Option<&str>) -> bool {
+        let Some(name) = name.map(|n| n.trim()).filter(|n| !n.is_empty()) else {
+            self.selected.remove(kind);
+            return true;
+        };
+        let name = name.strip_suffix(".txt").unwrap_or(name);
+        if !self.available(kind).iter().any(|n| n == name) {
+            warn!(?kind, name, "Unknown filter");
+            return false;
+        }
+        self.selected.insert(kind, name.to_string());
+        true
+    }
+
+    /// The index of the choice selected, 0 being no filter file.
+    fn choice(&self, kind: FilterKind) -> usize {
+        self.selected(kind)
+            .and_then(|name| self.available(kind).iter().position(|n| n == name))
+            .map_or(0, |i| i + 1)
+    }
+
+    pub fn shadow_mask_mode(&self) -> ShadowMaskMode {
+        self.shadow_mask_mode
+    }
+
+    pub fn set_shadow_mask_mode(&mut self, mode: ShadowMaskMode) {
+        self.shadow_mask_mode = mode;
+    }
+
+    pub fn shadow_mask_mode_id() -> SettingId {
+        SettingId::from_label(&format!("filter:{SHADOW_MASK_MODE_KEY}"))
+    }
+
+    /// Select a filter by its setting ID and choice index. Returns `None` if no
+    /// filter kind has this ID, otherwise the new choice.
+    pub fn set_choice(&mut self, id: SettingId, choice: usize) -> Option<usize> {
+        if id == Self::shadow_mask_mode_id() {
+            self.shadow_mask_mode = ShadowMaskMode::ALL[choice % ShadowMaskMode::ALL.len()];
+            return Some(self.shadow_mask_mode as usize);
+        }
+
+        let kind = FilterKind::ALL.into_iter().find(|k| k.setting_id() == id)?;
+        let name = match choice {
+            0 => None,
+            i => self.available(kind).get(i - 1).cloned(),
+        };
+        self.select(kind, name.as_deref());
+        Some(self.choice(kind))
+    }
+
+    /// The video filter to use for a kind, falling back to the horizontal
+    /// filter for vertical, and to the vertical filter for scanlines.
+    pub fn video_filter(&self, kind: FilterKind) -> Result<Option<PolyphaseFilter>, String> {
+        let name = match kind {
+            FilterKind::Horizontal => self.selected(FilterKind::Horizontal),
+            FilterKind::Vertical => self
+                .selected(FilterKind::Vertical)
+                .or(self.selected(FilterKind::Horizontal)),
+            FilterKind::Scanlines => self
+                .selected(FilterKind::Scanlines)
+                .or(self.selected(FilterKind::Vertical))
+                .or(self.selected(FilterKind::Horizontal)),
+            FilterKind::Audio | FilterKind::ShadowMask => {
+                return Err("Not a video filter".to_string())
+            }
+        };
+
+        name.map(|name| PolyphaseFilter::from_path(self.video_root.join(format!("{name}.txt"))))
+            .transpose()
+    }
+
+    pub fn audio_filter(&self) -> Result<Option<AudioFilter>, String> {
+        self.selected(FilterKind::Audio)
+            .map(|name| AudioFilter::from_path(self.audio_root.join(format!("{name}.txt"))))
+            .transpose()
+    }
+
+    pub fn shadow_mask(&self) -> Result<Option<ShadowMask>, String> {
+        self.selected(FilterKind::ShadowMask)
+            .map(|name| ShadowMask::from_path(self.shadow_mask_root.join(format!("{name}.txt"))))
+            .transpose()
+    }
+
+    /// Load the filters saved with [`Self::save`]. Missing files keep the
+    /// current selection, and an empty name selects no filter.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        for (key, name) in content.lines().filter_map(|l| l.split_once('=')) {
+            if key.trim() == SHADOW_MASK_MODE_KEY {
+                if let Some(mode) = name.trim().parse().ok().and_then(ShadowMaskMode::from_repr) {
+                    self.shadow_mask_mode = mode;
+                }
+            } else if let Some(kind) = FilterKind::ALL.into_iter().find(|k| k.key() == key.trim()) {
+                self.select(kind, Some(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the filters selected. Every kind is written, with an empty name
+    /// when no filter is selected, so it overrides the MiSTer.ini default.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut content: String = FilterKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let name = self.selected(kind).unwrap_or_default();
+                format!("{}={name}\n", kind.key())
+            })
+            .collect();
+        content += &format!("{SHADOW_MASK_MODE_KEY}={}\n", self.shadow_mask_mode as u8);
+        std::fs::write(path, content).map_err(|e| e.to_string())
+    }
+
+    /// The settings page to select filters, if there are any.
+    pub fn as_setting_item(&self) -> Option<CoreSettingItem> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut items: Vec<_> = FilterKind::ALL
+            .into_iter()
+            .filter(|kind| !self.available(*kind).is_empty())
+            .map(|kind| {
+                let choices = std::iter::once(kind.none_label().to_string())
+                    .chain(self.available(kind).iter().cloned())
+                    .collect();
+                CoreSettingItem::int_option(
+                    kind.setting_id(),
+                    kind.label(),
+                    choices,
+                    Some(self.choice(kind)),
+                )
+            })
+            .collect();
+        if !self.available(FilterKind::ShadowMask).is_empty() {
+            items.push(CoreSettingItem::int_option(
+                Self::shadow_mask_mode_id(),
+                "Shadow Mask Mode",
+                ShadowMaskMode::ALL
+                    .iter()
+                    .map(|m| m.label().to_string())
+                    .collect(),
+                Some(self.shadow_mask_mode as usize),
+            ));
+        }
+        Some(CoreSettingItem::page(
+            "filters", "Filters", "Filters", items,
+        ))
+    }
+}
+
+#[test]
+fn polyphase_filter() {
+    let content = "# A nearest neighbour filter.\n".to_string()
+        + &"0, 128, 0, 0\n".repeat(8)
+        + &"0, 0, 128, 0 # Second half.\n".repeat(8);
+    let filter = PolyphaseFilter::parse(&content).unwrap();
+    assert_eq!(filter.phases().len(), FILTER_PHASES);
+    assert_eq!(filter.phases()[31], [0, 128, 0, 0]);
+    assert_eq!(filter.phases()[32], [0, 0, 128, 0]);
+
+    assert!(PolyphaseFilter::parse(&"0, 128, 0, 0\n".repeat(15)).is_err());
+    assert!(PolyphaseFilter::parse(&"0, 128, 0\n".repeat(16)).is_err());
+}
+
+#[test]
+fn audio_filter() {
+    let filter =
+        AudioFilter::parse("# 3 pole butterworth, 16kHz\n0.5\n3\n3\n1\n-2.5\n2.0\n-0.75\n")
+            .unwrap();
+    assert_eq!(filter.gain(), 1 << 39);
+    assert_eq!(filter.x(), [3, 3, 1]);
+    assert_eq!(filter.y(), [-5 << 20, 1 << 22, -3 << 19]);
+
+    assert!(AudioFilter::parse("0.5\n3\n3\n1\n-2.5\n2.0\n").is_err());
+    assert!(AudioFilter::parse("2\n3\n3\n1\n-2.5\n2.0\n-0.75\n").is_err());
+}
+
+#[test]
+fn audio_filter_default_is_flat() {
+    let filter = AudioFilter::default();
+    assert_eq!(filter.gain(), (1 << AUDIO_GAIN_BITS) - 1);
+    assert_eq!(filter.x(), [1, 0, 0]);
+    assert_eq!(filter.y(), [0; 3]);
+}
+
+#[test]
+fn select_and_save() {
+    let dir = tempdir::TempDir::new("filters").unwrap();
+    let video = dir.path().join("filters");
+    std::fs::create_dir_all(video.join("Scanlines")).unwrap();
+    let phases = "0, 128, 0, 0\n".repeat(16);
+    std::fs::write(video.join("Sharp.txt"), &phases).unwrap();
+    std::fs::write(video.join("Scanlines/Dark.txt"), &phases).unwrap();
+    std::fs::write(video.join("README.md"), "").unwrap();
+
+    let mut filters = Filters::scan(&video, dir.path().join("filters_audio"));
+    assert_eq!(
+        filters.available(FilterKind::Horizontal),
+        ["Scanlines/Dark", "Sharp"]
+    );
+    assert!(filters.available(FilterKind::Audio).is_empty());
+
+    assert!(filters.select(FilterKind::Horizontal, Some("Sharp.txt")));
+    assert!(!filters.select(FilterKind::Vertical, Some("Unknown")));
+    assert_eq!(
+        filters.set_choice(FilterKind::Scanlines.setting_id(), 1),
+        Some(1)
+    );
+    assert_eq!(
+        filters.selected(FilterKind::Scanlines),
+        Some("Scanlines/Dark")
+    );
+    assert!(filters
+        .video_filter(FilterKind::Vertical)
+        .unwrap()
+        .is_some());
+    assert_eq!(filters.audio_filter(), Ok(None));
+
+    let path = dir.path().join("config/TEST.txt");
+    filters.save(&path).unwrap();
+    let mut loaded = Filters::scan(&video, dir.path().join("filters_audio"));
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.selected(FilterKind::Horizontal), Some("Sharp"));
+    assert_eq!(loaded.selected(FilterKind::Vertical), None);
+    assert_eq!(
+        loaded.selected(FilterKind::Scanlines),
+        Some("Scanlines/Dark")
+    );
+}
+
+#[test]
+fn save_default_turned_off() {
+    let dir = tempdir::TempDir::new("filters").unwrap();
+    let video = dir.path().join("filters");
+    std::fs::create_dir_all(&video).unwrap();
+    std::fs::write(video.join("Sharp.txt"), "0, 128, 0, 0\n".repeat(16)).unwrap();
+
+    // The MiSTer.ini default is selected before the saved filters are loaded.
+    let with_default = || {
+        let mut filters = Filters::scan(&video, dir.path().join("filters_audio"));
+        filters.select(FilterKind::Horizontal, Some("Sharp"));
+        filters
+    };
+
+    let mut filters = with_default();
+    assert_eq!(
+        filters.set_choice(FilterKind::Horizontal.setting_id(), 0),
+        Some(0)
+    );
+    let path = dir.path().join("TEST.txt");
+    filters.save(&path).unwrap();
+
+    let mut loaded = with_default();
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.selected(FilterKind::Horizontal), None);
+}
+
+#[test]
+fn shadow_masks() {
+    let dir = tempdir::TempDir::new("shadow_masks").unwrap();
+    let masks = dir.path().join("shadow_masks");
+    std::fs::create_dir_all(&masks).unwrap();
+    std::fs::write(masks.join("Grille.txt"), "2,1\n4,1\n").unwrap();
+
+    let mut filters = Filters::scan(dir.path().join("filters"), dir.path().join("filters_audio"))
+        .with_shadow_masks(&masks);
+    assert_eq!(filters.available(FilterKind::ShadowMask), ["Grille"]);
+    assert_eq!(filters.shadow_mask(), Ok(None));
+
+    assert!(filters.select(FilterKind::ShadowMask, Some("Grille")));
+    assert_eq!(
+        filters.set_choice(Filters::shadow_mask_mode_id(), 2),
+        Some(2)
+    );
+    assert_eq!(filters.shadow_mask_mode(), ShadowMaskMode::Double);
+    assert_eq!(
+        filters.shadow_mask().unwrap().unwrap().pixels(),
+        [0x800, 0x008]
+    );
+
+    let path = dir.path().join("TEST.txt");
+    filters.save(&path).unwrap();
+    let mut loaded = Filters::default().with_shadow_masks(&masks);
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.selected(FilterKind::ShadowMask), Some("Grille"));
+    assert_eq!(loaded.shadow_mask_mode(), ShadowMaskMode::Double);
+    // Turning off the mask and its mode overrides the MiSTer.ini defaults.
+    assert!(filters.select(FilterKind::ShadowMask, None));
+    filters.set_shadow_mask_mode(ShadowMaskMode::Off);
+    filters.save(&path).unwrap();
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.selected(FilterKind::ShadowMask), None);
+    assert_eq!(loaded.shadow_mask_mode(), ShadowMaskMode::Off);
+}
@@ -9,12 +9,14 @@ use tracing::{debug, error, info, trace};
 
 use cyclone_v::fpgamgrregs::ctrl::{FpgaCtrlCfgWidth, FpgaCtrlEn, FpgaCtrlNce};
 use cyclone_v::fpgamgrregs::stat::StatusRegisterMode;
-use cyclone_v::memory::DevMemMemoryMapper;
+use cyclone_v::memory::{BufferMemoryMapper, DevMemMemoryMapper, MemoryMapper};
 pub use program::Program;
 pub use spi::*;
 
 use crate::fpga::osd_io::{OsdDisable, OsdEnable};
 
+#[cfg(any(test, feature = "fake"))]
+pub mod fake;
 mod program;
 mod spi;
 
@@ -101,19 +103,30 @@ impl CoreInterfaceType {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct MisterFpga {
-    soc: Arc<UnsafeCell<cyclone_v::SocFpga<DevMemMemoryMapper>>>,
-    spi: Spi<DevMemMemoryMapper>,
+/// The FPGA, accessed through a memory mapper. By default, this is the physical
+/// memory of the DE10-Nano, but tests can use a [`BufferMemoryMapper`] instead.
+#[derive(Debug)]
+pub struct MisterFpga<M: MemoryMapper = DevMemMemoryMapper> {
+    soc: Arc<UnsafeCell<cyclone_v::SocFpga<M>>>,
+    spi: Spi<M>,
+}
+
+impl<M: MemoryMapper> Clone for MisterFpga<M> {
+    fn clone(&self) -> Self {
+        Self {
+            soc: self.soc.clone(),
+            spi: self.spi.clone(),
+        }
+    }
 }
 
 // SAFETY:
 // Since the FPGA is using memory-mapped I/O, it is not safe to send it to another thread.
-unsafe impl Send for MisterFpga {}
-unsafe impl Sync for MisterFpga {}
+unsafe impl<M: MemoryMapper> Send for MisterFpga<M> {}
+unsafe impl<M: MemoryMapper> Sync for MisterFpga<M> {}
 
 // OSD specific functions.
-impl MisterFpga {
+impl<M: MemoryMapper> MisterFpga<M> {
     pub fn osd_enable(&mut self) {
         let _ = self.spi_mut().execute(OsdEnable);
     }
@@ -122,28 +135,7 @@ impl MisterFpga {
     }
 }
 
-impl MisterFpga {
-    fn new(soc: Arc<UnsafeCell<cyclone_v::SocFpga<DevMemMemoryMapper>>>) -> Self {
-        Self {
-            soc: soc.clone(),
-            spi: Spi::new(soc),
-        }
-    }
-
-    #[inline]
-    #[allow(clippy::mut_from_ref)]
-    fn soc_mut(&self) -> &mut cyclone_v::SocFpga<DevMemMemoryMapper> {
-        unsafe { &mut (*self.soc.get()) }
-    }
-
-    fn regs(&self) -> &cyclone_v::fpgamgrregs::FpgaManagerRegs {
-        self.soc_mut().regs()
-    }
-
-    fn regs_mut(&mut self) -> &mut cyclone_v::fpgamgrregs::FpgaManagerRegs {
-        self.soc_mut().regs_mut()
-    }
-
+impl MisterFpga<DevMemMemoryMapper> {
     pub fn init() -> Result<Self, &'static str> {
         unsafe {
             if INITIALIZED.load(Ordering::Relaxed) {
@@ -154,13 +146,7 @@ impl MisterFpga {
 
             info!("Initializing FPGA");
 
-            let soc = cyclone_v::SocFpga::default();
-
-            // TODO: Remove UnsafeCell here.
-            #[allow(clippy::arc_with_non_send_sync)]
-            let soc = Arc::new(UnsafeCell::new(soc));
-            let mut fpga = Self::new(soc.clone());
-
+            let mut fpga = Self::from_soc(cyclone_v::SocFpga::default());
             fpga.regs_mut().set_gpo(0);
 
             FPGA_SINGLETON = Some(fpga.clone());
@@ -169,22 +155,65 @@ impl MisterFpga {
             Ok(fpga)
         }
     }
+}
+
+impl MisterFpga<BufferMemoryMapper> {
+    /// Create an FPGA over a heap buffer instead of physical memory. Nothing
+    /// will answer on the SPI bus, see `FakeHpsBridge` in the `fake` feature
+    /// for that.
+    pub fn create_for_test() -> Self {
+        Self::from_soc(cyclone_v::SocFpga::create_for_test())
+    }
+}
+
+impl<M: MemoryMapper> MisterFpga<M> {
+    /// Create an FPGA from a SoC. There should only be one per memory region.
+    pub fn from_soc(soc: cyclone_v::SocFpga<M>) -> Self {
+        // TODO: Remove UnsafeCell here.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let soc = Arc::new(UnsafeCell::new(soc));
+
+        Self {
+            soc: soc.clone(),
+            spi: Spi::new(soc),
+        }
+    }
 
-    pub fn spi(&self) -> &Spi<DevMemMemoryMapper> {
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    fn soc_mut(&self) -> &mut cyclone_v::SocFpga<M> {
+        unsafe { &mut (*self.soc.get()) }
+    }
+
+    fn regs(&self) -> &cyclone_v::fpgamgrregs::FpgaManagerRegs {
+        self.soc_mut().regs()
+    }
+
+    fn regs_mut(&mut self) -> &mut cyclone_v::fpgamgrregs::FpgaManagerRegs {
+        self.soc_mut().regs_mut()
+    }
+
+    pub fn spi(&self) -> &Spi<M> {
         &self.spi
     }
 
-    pub fn spi_mut(&mut self) -> &mut Spi<DevMemMemoryMapper> {
+    pub fn spi_mut(&mut self) -> &mut Spi<M> {
         &mut self.spi
     }
 
-    pub fn core_type(&mut self) -> Option<CoreType> {
-        let regs = self.regs_mut();
+    /// Let the memory mapper react to register writes. This is a no-op on hardware.
+    #[inline]
+    fn sync(&mut self) {
+        self.soc_mut().memory.sync();
+    }
 
-        let gpo = regs.gpo() & 0x7FFF_FFFF;
-        regs.set_gpo(0);
-        let core_type: u32 = regs.gpi();
-        regs.set_gpo(gpo | 0x80000000);
+    pub fn core_type(&mut self) -> Option<CoreType> {
+        let gpo = self.regs().gpo() & 0x7FFF_FFFF;
+        self.regs_mut().set_gpo(0);
+        self.sync();
+        let core_type: u32 = self.regs().gpi();
+        self.regs_mut().set_gpo(gpo | 0x80000000);
+        self.sync();
 
         if (core_type & 0xFFFFFF00) != 0x5CA62300 {
             error!("FPGA core type mismatch");
@@ -237,6 +266,7 @@ impl MisterFpga {
         // Core Reset.
         let gpo = fpga_manager.gpo() & (!0xC000_0000);
         fpga_manager.set_gpo(gpo | 0x4000_0000);
+        self.sync();
     }
 
     #[inline]
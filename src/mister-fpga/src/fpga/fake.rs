@@ -0,0 +1,247 @@
+//! A fake HPS bridge, to run SPI commands against a scripted core instead of an
+//! actual FPGA.
+//!
+//! The bridge is attached as a device to a [`BufferMemoryMapper`], and watches
+//! the GPO register every time the [`crate::fpga::Spi`] writes it. It then
+//! answers on the GPI register the same way the `hps_io` module of a core
+//! would, recording every transaction it sees. See [`VirtualCore`] for a core
+//! that understands the commands.
+use std::sync::{Arc, Mutex};
+
+use cyclone_v::fpgamgrregs::stat::StatusRegisterMode;
+use cyclone_v::memory::{BufferMemoryMapper, MemoryMapper, RegionMemoryMapper};
+use cyclone_v::{ranges, SocFpga};
+
+use crate::fpga::feature::SpiFeatureSet;
+use crate::fpga::spi::{GPO_SPI_ENABLE, SSPI_ACK, SSPI_DATA_MASK, SSPI_STROBE};
+use crate::fpga::{CoreInterfaceType, CoreType, MisterFpga};
+
+mod virtual_core;
+pub use virtual_core::*;
+
+/// Offset of the GPIO Port A External Port Register in the FPGA Manager.
+const GPIO_EXT_PORTA_OFFSET: usize = 0x850;
+
+/// A single transaction on the SPI bus, from the moment features are enabled to
+/// the moment they are disabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpiTransaction {
+    /// The features enabled during the transaction.
+    pub features: SpiFeatureSet,
+
+    /// All the words written by the HPS, starting with the command.
+    pub words: Vec<u16>,
+}
+
+impl SpiTransaction {
+    /// The command of this transaction, if any word was sent.
+    pub fn command(&self) -> Option<u16> {
+        self.words.first().copied()
+    }
+
+    /// The words sent after the command.
+    pub fn data(&self) -> &[u16] {
+        self.words.get(1..).unwrap_or_default()
+    }
+}
+
+/// Something that answers the words sent by the HPS, like a core would.
+pub trait HpsResponder: Send + 'static {
+    /// Returns the word to put on the bus in response to the last word of the
+    /// transaction in progress.
+    fn respond(&mut self, transaction: &SpiTransaction) -> u16;
+}
+
+impl<F: FnMut(&SpiTransaction) -> u16 + Send + 'static> HpsResponder for F {
+    fn respond(&mut self, transaction: &SpiTransaction) -> u16 {
+        self(transaction)
+    }
+}
+
+struct BridgeState {
+    responder: Box<dyn HpsResponder>,
+    transactions: Vec<SpiTransaction>,
+    in_progress: bool,
+    gpo: u32,
+    core_type: u8,
+    interface_type: u8,
+    io_version: u8,
+}
+
+impl BridgeState {
+    fn gpi_high(&self) -> u32 {
+        ((self.interface_type as u32) << 16) | ((self.io_version as u32) << 18)
+    }
+
+    fn sync(&mut self, memory: &mut [u8]) {
+        let mut soc = SocFpga::new(RegionMemoryMapper::new(memory));
+        let regs = soc.regs_mut();
+        let gpo = regs.gpo();
+        let previous = std::mem::replace(&mut self.gpo, gpo);
+
+        if gpo & GPO_SPI_ENABLE == 0 {
+            regs.set_gpi(0x5CA6_2300 | self.core_type as u32);
+            return;
+        }
+
+        let features = SpiFeatureSet::from(gpo);
+        let new_features = features.as_u32() & !SpiFeatureSet::from(previous).as_u32();
+        if features == SpiFeatureSet::NONE {
+            self.in_progress = false;
+        } else if new_features != 0 || !self.in_progress {
+            self.transactions.push(SpiTransaction {
+                features,
+                words: vec![],
+            });
+            self.in_progress = true;
+        }
+
+        let strobe = gpo & SSPI_STROBE != 0;
+        let was_strobe = previous & SSPI_STROBE != 0;
+        if strobe && !was_strobe {
+            if !self.in_progress {
+                self.transactions.push(SpiTransaction {
+                    features,
+                    words: vec![],
+                });
+                self.in_progress = true;
+            }
+
+            let transaction = self.transactions.last_mut().unwrap();
+            transaction.words.push((gpo & SSPI_DATA_MASK) as u16);
+            let response = self.responder.respond(transaction);
+            regs.set_gpi(self.gpi_high() | SSPI_ACK | response as u32);
+        } else if !strobe {
+            // Release the ACK, but keep the response on the bus.
+            let gpi = regs.gpi() & SSPI_DATA_MASK;
+            regs.set_gpi(self.gpi_high() | gpi);
+        }
+    }
+}
+
+/// A fake HPS bridge. Clones share the same state, so one can be kept around
+/// to inspect transactions while the FPGA is in use.
+#[derive(Clone)]
+pub struct FakeHpsBridge {
+    state: Arc<Mutex<BridgeState>>,
+}
+
+impl FakeHpsBridge {
+    /// Create a bridge that answers with the responder.
+    pub fn new(responder: impl HpsResponder) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BridgeState {
+                responder: Box::new(responder),
+                transactions: vec![],
+                in_progress: false,
+                gpo: 0,
+                core_type: CoreType::CoreTypeGeneric as u8,
+                interface_type: CoreInterfaceType::SpiBus8Bit as u8,
+                io_version: 1,
+            })),
+        }
+    }
+
+    /// Create a bridge that answers zero to everything.
+    pub fn silent() -> Self {
+        Self::new(|_: &SpiTransaction| 0)
+    }
+
+    pub fn with_core_type(self, core_type: CoreType) -> Self {
+        self.state.lock().unwrap().core_type = core_type as u8;
+        self
+    }
+
+    pub fn with_interface_type(self, interface_type: CoreInterfaceType) -> Self {
+        self.state.lock().unwrap().interface_type = interface_type as u8;
+        self
+    }
+
+    pub fn with_io_version(self, io_version: u8) -> Self {
+        self.state.lock().unwrap().io_version = io_version;
+        self
+    }
+
+    /// Create an FPGA in user mode, whose SPI bus is answered by this bridge.
+    pub fn fpga(&self) -> MisterFpga<BufferMemoryMapper> {
+        let state = self.state.clone();
+        let mut memory = BufferMemoryMapper::create(ranges::BASE.start, ranges::BASE.len())
+            .unwrap()
+            .with_device(move |memory| state.lock().unwrap().sync(memory));
+
+        // The FPGA is configured and ready (INIT_DONE).
+        let regs_offset = ranges::FPGAMGRREGS.start - ranges::BASE.start;
+        let porta = regs_offset + GPIO_EXT_PORTA_OFFSET;
+        memory
+            .as_mut_range(porta..porta + 4)
+            .copy_from_slice(&0b100u32.to_le_bytes());
+
+        let mut soc = SocFpga::new(memory);
+        soc.regs_mut()
+            .update_stat(|stat| stat.set_mode(StatusRegisterMode::UserMode));
+        soc.regs_mut().set_gpo(GPO_SPI_ENABLE);
+        soc.memory.sync();
+
+        MisterFpga::from_soc(soc)
+    }
+
+    /// All the transactions seen so far.
+    pub fn transactions(&self) -> Vec<SpiTransaction> {
+        self.state.lock().unwrap().transactions.clone()
+    }
+
+    /// Forget all transactions seen so far.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().transactions.clear();
+    }
+}
+
+#[test]
+fn fake_bridge_core_info() {
+    let bridge = FakeHpsBridge::silent()
+        .with_core_type(CoreType::CoreTypeGenericDualSdram)
+        .with_interface_type(CoreInterfaceType::SpiBus16Bit)
+        .with_io_version(2);
+    let mut fpga = bridge.fpga();
+
+    assert!(fpga.is_ready());
+    assert_eq!(fpga.core_type(), Some(CoreType::CoreTypeGenericDualSdram));
+    assert_eq!(
+        fpga.core_interface_type(),
+        Some(CoreInterfaceType::SpiBus16Bit)
+    );
+    assert_eq!(fpga.core_io_version(), Some(2));
+}
+
+#[test]
+fn fake_bridge_transactions() {
+    use crate::fpga::{IntoLowLevelSpiCommand, SpiCommandExt};
+
+    struct RawCommand(SpiFeatureSet, u16);
+    impl IntoLowLevelSpiCommand for RawCommand {
+        fn into_ll_spi_command(self) -> (SpiFeatureSet, u16) {
+            (self.0, self.1)
+        }
+    }
+
+    let bridge = FakeHpsBridge::new(|t: &SpiTransaction| t.words.len() as u16 * 0x11);
+    let mut fpga = bridge.fpga();
+
+    let mut result = [0u16; 2];
+    fpga.spi_mut()
+        .command(RawCommand(SpiFeatureSet::OSD, 0x41))
+        .write(0x1234)
+        .write_read(0x5678, &mut result[0]);
+    fpga.spi_mut()
+        .command(RawCommand(SpiFeatureSet::IO, 0x1E))
+        .write_read(0xABCD, &mut result[1]);
+
+    assert_eq!(result, [0x33, 0x22]);
+    let transactions = bridge.transactions();
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[0].features, SpiFeatureSet::OSD);
+    assert_eq!(transactions[0].command(), Some(0x41));
+    assert_eq!(transactions[0].data(), &[0x1234, 0x5678]);
+    assert_eq!(transactions[1].features, SpiFeatureSet::IO);
+    assert_eq!(transactions[1].data(), &[0xABCD]);
+}
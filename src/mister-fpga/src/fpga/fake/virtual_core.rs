@@ -0,0 +1,550 @@
+//! A virtual core, answering the `hps_io` protocol the way a MiSTer core does.
+//!
+//! The core side can be scripted (changing status bits, requesting SD sectors)
+//! and inspected (files received, images mounted, inputs), so the whole
+//! [`crate::core::MisterFpgaCore`] can be exercised without a board.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use cyclone_v::memory::BufferMemoryMapper;
+
+use crate::fpga::fake::{FakeHpsBridge, HpsResponder, SpiTransaction};
+use crate::fpga::{CoreInterfaceType, CoreType, MisterFpga};
+use crate::types::StatusBitMap;
+
+/// The size of a sector requested by the virtual core.
+pub const VIRTUAL_SD_SECTOR_SIZE: usize = 512;
+
+/// The video timings reported by a [`VirtualCore`]. Times are in 10ns units,
+/// like the `hps_io` video analyzer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VirtualVideo {
+    pub width: u32,
+    pub height: u32,
+    pub htime: u32,
+    pub vtime: u32,
+    pub ptime: u32,
+    pub vtimeh: u32,
+    pub ctime: u32,
+    pub interlaced: bool,
+    pub rotated: bool,
+    pub pixel_repetition: u16,
+    pub de_h: u16,
+    pub de_v: u16,
+}
+
+impl Default for VirtualVideo {
+    fn default() -> Self {
+        // A 256x240 NTSC picture.
+        Self {
+            width: 256,
+            height: 240,
+            htime: 6_355,
+            vtime: 1_663_920,
+            ptime: 18,
+            vtimeh: 0,
+            ctime: 0,
+            interlaced: false,
+            rotated: false,
+            pixel_repetition: 0,
+            de_h: 256,
+            de_v: 240,
+        }
+    }
+}
+
+/// The framebuffer parameters reported by a [`VirtualCore`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VirtualFramebufferParams {
+    pub aspect_ratio: (u16, u16),
+    pub format: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Default for VirtualFramebufferParams {
+    fn default() -> Self {
+        Self {
+            aspect_ratio: (4, 3),
+            format: 0,
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+/// An image mounted on the virtual core, as announced by the HPS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VirtualSdImage {
+    pub size: u64,
+    pub writable: bool,
+}
+
+/// A sector sent by the HPS to the virtual core.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualSdSector {
+    pub disk: u8,
+    pub lba: u64,
+    pub data: Vec<u8>,
+}
+
+/// A file transferred (or being transferred) to the virtual core.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VirtualFile {
+    pub index: u8,
+
+    /// The extension, without the dot.
+    pub extension: String,
+
+    /// The size announced when the transfer started.
+    pub size: Option<u32>,
+
+    pub data: Vec<u8>,
+
+    /// Whether the HPS ended the transfer.
+    pub complete: bool,
+}
+
+#[derive(Debug)]
+enum SdRequest {
+    Read { disk: u8, lba: u64, data: Vec<u8> },
+    Write { disk: u8, lba: u64, data: Vec<u8> },
+}
+
+impl SdRequest {
+    fn status(&self) -> u16 {
+        let (disk, op) = match self {
+            SdRequest::Read { disk, .. } => (*disk, 1),
+            SdRequest::Write { disk, .. } => (*disk, 2),
+        };
+
+        // One block of 512 bytes.
+        0x8000 | (2 << 6) | ((disk as u16 & 0xF) << 2) | op
+    }
+
+    fn lba(&self) -> u64 {
+        match self {
+            SdRequest::Read { lba, .. } | SdRequest::Write { lba, .. } => *lba,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct VirtualCoreState {
+    config_string: Vec<u8>,
+    wide: bool,
+    io_version: u8,
+
+    status: StatusBitMap,
+    status_counter: u8,
+
+    joysticks: [u32; 6],
+    keyboard: Vec<u8>,
+
+    mounted: [Option<VirtualSdImage>; 16],
+    mounting_size: u64,
+    sd_requests: VecDeque<SdRequest>,
+    sd_active: Option<SdRequest>,
+    sectors_read: Vec<VirtualSdSector>,
+
+    file_index: u8,
+    file_extension: [u8; 4],
+    files: Vec<VirtualFile>,
+
+    video: VirtualVideo,
+    video_counter: u8,
+    filter_version: u16,
+    framebuffer: VirtualFramebufferParams,
+}
+
+impl VirtualCoreState {
+    fn new(config_string: &str) -> Self {
+        Self {
+            config_string: config_string.as_bytes().to_vec(),
+            wide: false,
+            io_version: 1,
+            status: StatusBitMap::new(),
+            status_counter: 0,
+            joysticks: [0; 6],
+            keyboard: vec![],
+            mounted: [None; 16],
+            mounting_size: 0,
+            sd_requests: VecDeque::new(),
+            sd_active: None,
+            sectors_read: vec![],
+            file_index: 0,
+            file_extension: [0; 4],
+            files: vec![],
+            video: VirtualVideo::default(),
+            video_counter: 0,
+            filter_version: 0,
+            framebuffer: VirtualFramebufferParams::default(),
+        }
+    }
+
+    /// Split a word received on the bus in bytes, depending on the bus width.
+    fn bytes_of(&self, word: u16) -> Vec<u8> {
+        if self.wide {
+            word.to_le_bytes().to_vec()
+        } else {
+            vec![word as u8]
+        }
+    }
+
+    /// Respond to the `n`th word of an IO command (0 being the command itself).
+    fn respond_io(&mut self, command: u16, n: usize, word: u16) -> u16 {
+        match command & 0xFF {
+            0x02 | 0x03 | 0x10..=0x13 => {
+                let index = match command & 0xFF {
+                    0x02 => 0,
+                    0x03 => 1,
+                    c => (c - 0x10 + 2) as usize,
+                };
+                match n {
+                    1 => self.joysticks[index] = word as u32,
+                    2 => self.joysticks[index] |= (word as u32) << 16,
+                    _ => {}
+                }
+                0
+            }
+            0x05 if n > 0 => {
+                self.keyboard.push(word as u8);
+                0
+            }
+            0x14 if n > 0 => self.config_string.get(n - 1).copied().unwrap_or(0) as u16,
+            0x16 => self.respond_sd_stat(n),
+            0x17 if n > 0 => {
+                let bytes = self.bytes_of(word);
+                if let Some(SdRequest::Read { data, .. }) = &mut self.sd_active {
+                    data.extend_from_slice(&bytes);
+                    if data.len() >= VIRTUAL_SD_SECTOR_SIZE {
+                        if let Some(SdRequest::Read {
+                            disk,
+                            lba,
+                            mut data,
+                        }) = self.sd_active.take()
+                        {
+                            data.truncate(VIRTUAL_SD_SECTOR_SIZE);
+                            self.sectors_read.push(VirtualSdSector { disk, lba, data });
+                        }
+                    }
+                }
+                0
+            }
+            0x18 if n > 0 => {
+                let step = if self.wide { 2 } else { 1 };
+                let offset = (n - 1) * step;
+                let Some(SdRequest::Write { data, .. }) = &self.sd_active else {
+                    return 0;
+                };
+
+                let low = data.get(offset).copied().unwrap_or(0);
+                let high = data.get(offset + 1).copied().unwrap_or(0);
+                if offset + step >= data.len() {
+                    self.sd_active = None;
+                }
+                if self.wide {
+                    u16::from_le_bytes([low, high])
+                } else {
+                    low as u16
+                }
+            }
+            0x1C if n == 1 => {
+                let index = (word as u8 & 0x7F).trailing_zeros() as usize;
+                if let Some(image) = self.mounted.get_mut(index) {
+                    *image = Some(VirtualSdImage {
+                        size: self.mounting_size,
+                        writable: word & 0x80 == 0,
+                    });
+                }
+                0
+            }
+            0x1D if n > 0 => {
+                if n == 1 {
+                    self.mounting_size = 0;
+                }
+                let (bits, max) = if self.io_version != 0 {
+                    (16, 4)
+                } else {
+                    (8, 8)
+                };
+                if n <= max {
+                    self.mounting_size |= (word as u64 & ((1 << bits) - 1)) << ((n - 1) * bits);
+                }
+                0
+            }
+            0x1E if n > 0 => {
+                if let Some(w) = self.status.as_mut_raw_slice().get_mut(n - 1) {
+                    *w = word;
+                }
+                0
+            }
+            0x23 => self.respond_video(n),
+            0x2B if n == 0 => self.filter_version,
+            0x29 => match n {
+                0 => 0xA0 | self.status_counter as u16,
+                n => self.status.as_raw_slice().get(n - 1).copied().unwrap_or(0),
+            },
+            0x40 => {
+                let fb = &self.framebuffer;
+                match n {
+                    1 => fb.aspect_ratio.0,
+                    2 => fb.aspect_ratio.1,
+                    3 => fb.format,
+                    4 => fb.width,
+                    5 => fb.height,
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    fn respond_sd_stat(&mut self, n: usize) -> u16 {
+        if n == 0 {
+            // Only one request is served at a time.
+            if self.sd_active.is_none() {
+                self.sd_active = self.sd_requests.pop_front();
+            }
+            return self.sd_active.as_ref().map_or(0, SdRequest::status);
+        }
+
+        let lba = self.sd_active.as_ref().map_or(0, SdRequest::lba);
+        match n {
+            2 => lba as u16,
+            3 => (lba >> 16) as u16,
+            _ => 0,
+        }
+    }
+
+    fn respond_video(&mut self, n: usize) -> u16 {
+        let v = &self.video;
+        let flags =
+            (v.interlaced as u16) << 8 | (v.rotated as u16) << 9 | self.video_counter as u16;
+        let longs = [
+            v.width, v.height, v.htime, v.vtime, v.ptime, v.vtimeh, v.ctime,
+        ];
+
+        match n {
+            1 => flags,
+            2..=15 => {
+                let long = longs[(n - 2) / 2];
+                if n % 2 == 0 {
+                    long as u16
+                } else {
+                    (long >> 16) as u16
+                }
+            }
+            16 => v.pixel_repetition,
+            17 => v.de_h,
+            18 => v.de_v,
+            _ => 0,
+        }
+    }
+
+    /// Respond to the `n`th word of a file transfer command.
+    fn respond_file(&mut self, command: u16, n: usize, word: u16) -> u16 {
+        match (command, n) {
+            // FileIndex.
+            (0x55, 1) => self.file_index = word as u8,
+            // FileInfo.
+            (0x56, 1 | 2) => {
+                let i = (n - 1) * 2;
+                self.file_extension[i] = (word >> 8) as u8;
+                self.file_extension[i + 1] = word as u8;
+            }
+            // FileTx.
+            (0x53, 1) if word as u8 == 0xFF => {
+                let extension = self
+                    .file_extension
+                    .iter()
+                    .filter(|c| **c != 0 && **c != b'.')
+                    .map(|c| *c as char)
+                    .collect();
+                self.files.push(VirtualFile {
+                    index: self.file_index,
+                    extension,
+                    ..Default::default()
+                });
+            }
+            (0x53, 1) => {
+                if let Some(file) = self.files.last_mut() {
+                    file.complete = true;
+                }
+            }
+            (0x53, 2 | 3) => {
+                if let Some(file) = self.files.last_mut() {
+                    let shift = (n - 2) * 16;
+                    file.size = Some(file.size.unwrap_or(0) | (word as u32) << shift);
+                }
+            }
+            // FileTxDat.
+            (0x54, 1..) => {
+                let bytes = self.bytes_of(word);
+                if let Some(file) = self.files.last_mut() {
+                    file.data.extend_from_slice(&bytes);
+                }
+            }
+            _ => {}
+        }
+        0
+    }
+}
+
+struct VirtualCoreResponder(Arc<Mutex<VirtualCoreState>>);
+
+impl HpsResponder for VirtualCoreResponder {
+    fn respond(&mut self, transaction: &SpiTransaction) -> u16 {
+        let Some(command) = transaction.command() else {
+            return 0;
+        };
+        let n = transaction.words.len() - 1;
+        let word = transaction.words[n];
+
+        let mut state = self.0.lock().unwrap();
+        if transaction.features.io() {
+            state.respond_io(command, n, word)
+        } else if transaction.features.fpga() {
+            state.respond_file(command, n, word)
+        } else {
+            0
+        }
+    }
+}
+
+/// A scriptable core behind a [`FakeHpsBridge`]. Clones share the same state.
+#[derive(Clone)]
+pub struct VirtualCore {
+    state: Arc<Mutex<VirtualCoreState>>,
+    bridge: FakeHpsBridge,
+}
+
+impl VirtualCore {
+    /// Create a virtual core with a config string, on an 8-bit bus.
+    pub fn new(config_string: &str) -> Self {
+        let state = Arc::new(Mutex::new(VirtualCoreState::new(config_string)));
+        let bridge = FakeHpsBridge::new(VirtualCoreResponder(state.clone()))
+            .with_interface_type(CoreInterfaceType::SpiBus8Bit)
+            .with_io_version(1);
+
+        Self { state, bridge }
+    }
+
+    fn state(&self) -> MutexGuard<'_, VirtualCoreState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn with_core_type(mut self, core_type: CoreType) -> Self {
+        self.bridge = self.bridge.with_core_type(core_type);
+        self
+    }
+
+    pub fn with_interface_type(mut self, interface_type: CoreInterfaceType) -> Self {
+        self.state().wide = interface_type.is_wide();
+        self.bridge = self.bridge.with_interface_type(interface_type);
+        self
+    }
+
+    pub fn with_io_version(mut self, io_version: u8) -> Self {
+        self.state().io_version = io_version;
+        self.bridge = self.bridge.with_io_version(io_version);
+        self
+    }
+
+    pub fn with_video(self, video: VirtualVideo) -> Self {
+        self.state().video = video;
+        self
+    }
+
+    /// The format of scaler coefficients the core supports (0 for none).
+    pub fn with_filter_version(self, version: u16) -> Self {
+        self.state().filter_version = version;
+        self
+    }
+
+    pub fn with_framebuffer_params(self, params: VirtualFramebufferParams) -> Self {
+        self.state().framebuffer = params;
+        self
+    }
+
+    /// The bridge this core answers on, e.g. to inspect raw transactions.
+    pub fn bridge(&self) -> &FakeHpsBridge {
+        &self.bridge
+    }
+
+    /// Create an FPGA running this core.
+    pub fn fpga(&self) -> MisterFpga<BufferMemoryMapper> {
+        self.bridge.fpga()
+    }
+
+    /// Change the status bits from the core side. The HPS will see the change
+    /// the next time it reads them.
+    pub fn set_status_bits(&self, bits: StatusBitMap) {
+        let mut state = self.state();
+        state.status = bits;
+        state.status_counter = (state.status_counter + 1) & 0x0F;
+    }
+
+    /// Change the video timings from the core side, e.g. when it switches
+    /// between PAL and NTSC.
+    pub fn set_video(&self, video: VirtualVideo) {
+        let mut state = self.state();
+        state.video = video;
+        state.video_counter = state.video_counter.wrapping_add(1);
+    }
+
+    /// The status bits, as last sent by the HPS (or set by the core).
+    pub fn status_bits(&self) -> StatusBitMap {
+        self.state().status
+    }
+
+    /// Request a sector from an image mounted on `disk`.
+    pub fn request_sd_read(&self, disk: u8, lba: u64) {
+        self.state().sd_requests.push_back(SdRequest::Read {
+            disk,
+            lba,
+            data: vec![],
+        });
+    }
+
+    /// Request writing a sector to an image mounted on `disk`. The data is
+    /// padded or truncated to a full sector.
+    pub fn request_sd_write(&self, disk: u8, lba: u64, data: &[u8]) {
+        let mut data = data.to_vec();
+        data.resize(VIRTUAL_SD_SECTOR_SIZE, 0);
+        self.state()
+            .sd_requests
+            .push_back(SdRequest::Write { disk, lba, data });
+    }
+
+    /// Whether some SD requests were not served by the HPS yet.
+    pub fn has_pending_sd_requests(&self) -> bool {
+        let state = self.state();
+        !state.sd_requests.is_empty() || state.sd_active.is_some()
+    }
+
+    /// The sectors sent by the HPS for read requests.
+    pub fn sectors_read(&self) -> Vec<VirtualSdSector> {
+        self.state().sectors_read.clone()
+    }
+
+    /// The image mounted at an index, if any.
+    pub fn mounted(&self, index: u8) -> Option<VirtualSdImage> {
+        self.state().mounted.get(index as usize).copied().flatten()
+    }
+
+    /// All the files sent to the core.
+    pub fn files(&self) -> Vec<VirtualFile> {
+        self.state().files.clone()
+    }
+
+    /// The last value sent for a joystick.
+    pub fn joystick(&self, index: u8) -> u32 {
+        self.state().joysticks[index as usize]
+    }
+
+    /// All the keyboard bytes sent to the core (PS/2 scancodes).
+    pub fn keyboard(&self) -> Vec<u8> {
+        self.state().keyboard.clone()
+    }
+}
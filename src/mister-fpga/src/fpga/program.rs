@@ -1,11 +1,13 @@
+use cyclone_v::memory::MemoryMapper;
+
 use crate::fpga::{FpgaError, MisterFpga};
 
 pub trait Program {
-    fn load(&self, fpga: &mut MisterFpga) -> Result<(), FpgaError>;
+    fn load<M: MemoryMapper>(&self, fpga: &mut MisterFpga<M>) -> Result<(), FpgaError>;
 }
 
 impl Program for &[u8] {
-    fn load(&self, fpga: &mut MisterFpga) -> Result<(), FpgaError> {
+    fn load<M: MemoryMapper>(&self, fpga: &mut MisterFpga<M>) -> Result<(), FpgaError> {
         fpga.load_rbf_bytes(self)
     }
 }
@@ -8,12 +8,15 @@ use tracing::trace;
 
 /// SPI is a 16-bit data bus where the lowest 16 bits are the data and the highest 16-bits
 /// are the control bits.
-const SSPI_DATA_MASK: u32 = 0x0000_FFFF;
+pub(crate) const SSPI_DATA_MASK: u32 = 0x0000_FFFF;
 
 /// This signal is sent to indicate new data.
-const SSPI_STROBE: u32 = 1 << 17;
+pub(crate) const SSPI_STROBE: u32 = 1 << 17;
 /// This signal is received to indicate that the data was read.
-const SSPI_ACK: u32 = 1 << 17;
+pub(crate) const SSPI_ACK: u32 = 1 << 17;
+
+/// When this bit is unset in GPO, the core answers with its type instead of SPI data.
+pub(crate) const GPO_SPI_ENABLE: u32 = 0x8000_0000;
 
 pub mod feature;
 pub mod file_io;
@@ -238,6 +241,27 @@ impl<M: MemoryMapper> Spi<M> {
         command.execute(self)
     }
 
+    #[inline]
+    fn gpo(&mut self) -> u32 {
+        self.soc_mut().regs().gpo()
+    }
+
+    /// Set the GPO register, letting the memory mapper react to it.
+    #[inline]
+    fn set_gpo(&mut self, value: u32) {
+        let soc = self.soc_mut();
+        soc.regs_mut().set_gpo(value);
+        soc.memory.sync();
+    }
+
+    /// Read the GPI register, after letting the memory mapper update it.
+    #[inline]
+    fn gpi(&mut self) -> u32 {
+        let soc = self.soc_mut();
+        soc.memory.sync();
+        soc.regs().gpi()
+    }
+
     #[inline]
     pub(super) fn enable_u32(&mut self, mask: u32) {
         let mut new_mask = 0;
@@ -252,9 +276,8 @@ impl<M: MemoryMapper> Spi<M> {
             return;
         }
 
-        let regs = self.soc_mut().regs_mut();
-        let gpo = (regs.gpo() & SpiFeatureSet::ALL.as_u32()) | 0x8000_0000;
-        regs.set_gpo(gpo | new_mask);
+        let gpo = (self.gpo() & SpiFeatureSet::ALL.as_u32()) | GPO_SPI_ENABLE;
+        self.set_gpo(gpo | new_mask);
     }
 
     #[inline]
@@ -275,9 +298,8 @@ impl<M: MemoryMapper> Spi<M> {
             return;
         }
 
-        let regs = self.soc_mut().regs_mut();
-        let gpo: u32 = (regs.gpo() & SpiFeatureSet::ALL.as_u32()) | 0x8000_0000;
-        regs.set_gpo(gpo & !new_mask);
+        let gpo: u32 = (self.gpo() & SpiFeatureSet::ALL.as_u32()) | GPO_SPI_ENABLE;
+        self.set_gpo(gpo & !new_mask);
     }
 
     #[inline]
@@ -335,26 +357,24 @@ impl<M: MemoryMapper> Spi<M> {
     /// Send a 16-bit word to the core. Returns the 16-bit word received from the core.
     #[inline]
     pub fn write(&mut self, word: u16) -> u16 {
-        let regs = self.soc_mut().regs_mut();
-
         // Remove the strobe bit and set the data bits.
-        let gpo = (regs.gpo() & !(SSPI_DATA_MASK | SSPI_STROBE)) | (word as u32);
+        let gpo = (self.gpo() & !(SSPI_DATA_MASK | SSPI_STROBE)) | (word as u32);
 
-        regs.set_gpo(gpo);
-        regs.set_gpo(gpo | SSPI_STROBE);
+        self.set_gpo(gpo);
+        self.set_gpo(gpo | SSPI_STROBE);
 
         // Wait for the ACK bit to be unset to give time to the core to get some work.
         loop {
-            let gpi = regs.gpi();
+            let gpi = self.gpi();
             if gpi & SSPI_ACK != 0 {
                 break;
             }
         }
 
         // Send the actual data without the strobe, then wait for the core to get done.
-        regs.set_gpo(gpo);
+        self.set_gpo(gpo);
         loop {
-            let gpi = regs.gpi();
+            let gpi = self.gpi();
             if gpi & SSPI_ACK == 0 {
                 break gpi as u16;
             }
@@ -367,16 +387,15 @@ impl<M: MemoryMapper> Spi<M> {
             return Ok(0);
         }
 
-        let regs = self.soc_mut().regs_mut();
-        let gpo_h = (regs.gpo() & !(SSPI_DATA_MASK | SSPI_STROBE)) | 0x8000_0000;
+        let gpo_h = (self.gpo() & !(SSPI_DATA_MASK | SSPI_STROBE)) | GPO_SPI_ENABLE;
         let mut gpo = gpo_h;
 
         buffer.iter().for_each(|b| {
             gpo = gpo_h | (*b as u32);
-            regs.set_gpo(gpo);
-            regs.set_gpo(gpo | SSPI_STROBE);
+            self.set_gpo(gpo);
+            self.set_gpo(gpo | SSPI_STROBE);
         });
-        regs.set_gpo(gpo);
+        self.set_gpo(gpo);
 
         Ok(buffer.len())
     }
@@ -392,16 +411,15 @@ impl<M: MemoryMapper> Spi<M> {
             return;
         }
 
-        let regs = self.soc_mut().regs_mut();
-        let gpo_h = regs.gpo() & !(SSPI_DATA_MASK | SSPI_STROBE);
+        let gpo_h = self.gpo() & !(SSPI_DATA_MASK | SSPI_STROBE);
         let mut gpo = gpo_h;
 
         buffer.iter().for_each(|b| {
             gpo = gpo_h | (*b as u32);
-            regs.set_gpo(gpo);
-            regs.set_gpo(gpo | SSPI_STROBE);
+            self.set_gpo(gpo);
+            self.set_gpo(gpo | SSPI_STROBE);
         });
-        regs.set_gpo(gpo);
+        self.set_gpo(gpo);
     }
 }
 
@@ -1,13 +1,15 @@
 use crate::config::edid::CustomVideoMode;
 use crate::core::buttons::ButtonMap;
 use crate::core::file::SdCard;
+use crate::filters::{AudioFilter, PolyphaseFilter};
 use crate::fpga::feature::SpiFeatureSet;
 use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt};
 use crate::keyboard::Ps2Scancode;
+use crate::shadow_mask::{ShadowMask, ShadowMaskMode};
 use crate::types::StatusBitMap;
 use bitfield::bitfield;
 use chrono::{DateTime, Datelike, NaiveDateTime, Timelike};
-use cyclone_v::memory::{DevMemMemoryMapper, MemoryMapper};
+use cyclone_v::memory::MemoryMapper;
 use std::mem::transmute;
 use std::ops::BitOrAssign;
 use std::time::SystemTime;
@@ -36,7 +38,7 @@ pub(crate) enum UserIoCommands {
     UserIoButtonSwitch = 0x01,
     UserIoJoystick0 = 0x02,
     UserIoJoystick1 = 0x03,
-    // UserIoMouse = 0x04,
+    UserIoMouse = 0x04,
     UserIoKeyboard = 0x05,
     // UserIoKeyboardOsd = 0x06,
     UserIoJoystick2 = 0x10,
@@ -55,6 +57,9 @@ pub(crate) enum UserIoCommands {
 
     UserIoSetSdConf = 0x19,
 
+    /// Left analog stick, paddles and spinners.
+    UserIoAnalogStick = 0x1A,
+
     /// Set sd card status
     UserIoSetSdStat = 0x1C,
 
@@ -76,6 +81,12 @@ pub(crate) enum UserIoCommands {
 
     UserIoGetStatusBits = 0x29,
 
+    /// Set the scaler polyphase filter coefficients.
+    UserIoSetFilterCoefficients = 0x2A,
+
+    /// Enable the scaler polyphase filter.
+    UserIoSetFilter = 0x2B,
+
     /// Set frame buffer for HPS output
     UserIoSetFramebuffer = 0x2F,
 
@@ -87,9 +98,18 @@ pub(crate) enum UserIoCommands {
     /// Get the info line from the core to show.
     // UserIoGetInfo = 0x36,
 
+    /// Set the audio IIR filter.
+    UserIoSetAudioFilter = 0x39,
+
     // Set a custom aspect ratio.
     UserIoSetArCust = 0x3A,
 
+    /// Right analog stick.
+    UserIoAnalogStick2 = 0x3D,
+
+    /// Set the scaler shadow mask.
+    UserIoShadowMask = 0x3E,
+
     UserIoGetFbParams = 0x40,
 }
 
@@ -204,6 +224,109 @@ impl UserIoJoystick {
     }
 }
 
+/// An analog input of a joystick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalogInput {
+    /// The left stick, with X and Y between -128 and 127.
+    LeftStick(i8, i8),
+
+    /// The right stick, with X and Y between -128 and 127.
+    RightStick(i8, i8),
+
+    /// A paddle, with a position between 0 and 255.
+    Paddle(u8),
+}
+
+/// Send the position of an analog input of a joystick to the core.
+pub struct UserIoAnalogJoystick {
+    index: u8,
+    input: AnalogInput,
+    io_version: u8,
+}
+
+impl UserIoAnalogJoystick {
+    /// Create the command for a joystick. Cores support up to 6 joysticks.
+    #[inline]
+    pub fn new(index: u8, input: AnalogInput, io_version: u8) -> Result<Self, String> {
+        if index > 5 {
+            return Err(format!("Invalid joystick index {index}"));
+        }
+
+        Ok(Self {
+            index,
+            input,
+            io_version,
+        })
+    }
+}
+
+impl SpiCommand for UserIoAnalogJoystick {
+    #[inline]
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+        // The first byte is `{pdsp_idx[7:4], stick_idx[3:0]}`. A stick index
+        // of 15 selects the paddle or spinner of `pdsp_idx`.
+        let (command, x, y) = match self.input {
+            AnalogInput::LeftStick(x, y) => (UserIoCommands::UserIoAnalogStick, x, y),
+            AnalogInput::RightStick(x, y) => (UserIoCommands::UserIoAnalogStick2, x, y),
+            AnalogInput::Paddle(value) => {
+                spi.command(UserIoCommands::UserIoAnalogStick)
+                    .write_b((self.index << 4) | 0x0F)
+                    .write(value as u16);
+                return Ok(());
+            }
+        };
+
+        let mut command = spi.command(command);
+        command.write_b(self.index);
+        if self.io_version != 0 {
+            command.write(((y as u8 as u16) << 8) | x as u8 as u16);
+        } else {
+            command.write_b(x as u8).write_b(y as u8);
+        }
+
+        Ok(())
+    }
+}
+
+/// Send a mouse movement and the state of its buttons to the core, as a PS/2
+/// mouse packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserIoMouse {
+    /// Left, right and middle buttons, in bits 0 to 2.
+    pub buttons: u8,
+
+    /// Movement to the right.
+    pub dx: i8,
+
+    /// Movement to the top (PS/2 Y axis goes up).
+    pub dy: i8,
+}
+
+impl UserIoMouse {
+    /// The 3 bytes PS/2 packet for this movement.
+    pub fn packet(&self) -> [u8; 3] {
+        // Bits are: Y overflow, X overflow, Y sign, X sign, 1, middle, right, left.
+        let mut status = (self.buttons & 0x07) | 0x08;
+        if self.dx < 0 {
+            status |= 0x10;
+        }
+        if self.dy < 0 {
+            status |= 0x20;
+        }
+
+        [status, self.dx as u8, self.dy as u8]
+    }
+}
+
+impl SpiCommand for UserIoMouse {
+    #[inline]
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+        spi.command(UserIoCommands::UserIoMouse)
+            .write_buffer_b(&self.packet());
+        Ok(())
+    }
+}
+
 pub struct UserIoKeyboardKeyDown(u32);
 
 impl From<Ps2Scancode> for UserIoKeyboardKeyDown {
@@ -684,7 +807,11 @@ impl SpiCommand for SdRead<'_> {
         let mut command = spi.command(UserIoSectorRead::Read(self.ack));
 
         if self.wide {
-            command.write_buffer_w(unsafe { transmute::<_, &[u16]>(self.data) });
+            // Transmuting the slice would keep its length in bytes, not words.
+            let words = unsafe {
+                std::slice::from_raw_parts(self.data.as_ptr() as *const u16, self.data.len() / 2)
+            };
+            command.write_buffer_w(words);
         } else {
             command.write_buffer_b(self.data);
         }
@@ -709,7 +836,13 @@ impl SpiCommand for SdWrite<'_> {
         let mut command = spi.command(UserIoSectorRead::Write(self.ack));
 
         if self.wide {
-            command.read_buffer_w(unsafe { transmute::<_, &mut [u16]>(self.data.as_mut_slice()) });
+            let words = unsafe {
+                std::slice::from_raw_parts_mut(
+                    self.data.as_mut_ptr() as *mut u16,
+                    self.data.len() / 2,
+                )
+            };
+            command.read_buffer_w(words);
         } else {
             command.read_buffer_b(self.data.as_mut_slice());
         }
@@ -738,8 +871,9 @@ impl SetMemorySize {
         Self(size)
     }
 
-    pub fn from_fpga() -> Result<Self, &'static str> {
-        Self::from_memory(DevMemMemoryMapper::create(0x1FFFF000, 0x1000)?)
+    /// Read the SDRAM configuration from the FPGA memory, mapped with `M`.
+    pub fn from_fpga<M: MemoryMapper>() -> Result<Self, &'static str> {
+        Self::from_memory(M::create(0x1FFFF000, 0x1000)?)
     }
 
     pub fn from_memory<M: MemoryMapper>(mut mapper: M) -> Result<Self, &'static str> {
@@ -899,10 +1033,12 @@ impl SpiCommand for SetVideoMode<'_> {
         command.write(((!!p.vpol as u16) << 15) | (p.vs as u16));
         command.write(p.vbp as u16);
 
-        // PLL
+        // PLL. The first word also selects the single buffer low latency mode.
         for (i, p) in p.pll.iter().copied().enumerate() {
             if i % 2 != 0 {
                 command.write(0x4000 | (p as u16));
+            } else if i == 0 && m.low_latency {
+                command.write(0x8000 | (p as u16)).write((p >> 16) as u16);
             } else {
                 command.write(p as u16).write((p >> 16) as u16);
             }
@@ -912,6 +1048,115 @@ impl SpiCommand for SetVideoMode<'_> {
     }
 }
 
+/// Enable or disable the scaler polyphase filter. The core answers with the
+/// format of coefficients it supports (see [`SetScalerCoefficients`]), or 0 if
+/// it has no polyphase filter.
+pub struct SetScalerFilter<'a> {
+    pub enabled: bool,
+    pub version: &'a mut u16,
+}
+
+impl SpiCommand for SetScalerFilter<'_> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+        let mut version = 0;
+        spi.command_read(UserIoCommands::UserIoSetFilter, &mut version)
+            .write_b(self.enabled as u8);
+        *self.version = version;
+        Ok(())
+    }
+}
+
+/// Upload the scaler polyphase coefficients, as an address followed by a 10
+/// bits coefficient. The first 2 bits of the version are the number of filters
+/// supported: 1 for horizontal and vertical, 2 to add scanlines. If bit 2 is
+/// set, the 64 phases are sent at full precision, otherwise every 4th phase
+/// with its taps halved.
+pub struct SetScalerCoefficients<'a> {
+    pub version: u16,
+    pub horizontal: &'a PolyphaseFilter,
+    pub vertical: &'a PolyphaseFilter,
+    pub scanlines: &'a PolyphaseFilter,
+}
+
+impl SpiCommand for SetScalerCoefficients<'_> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+        let (step, shift) = if self.version & 0b100 != 0 {
+            (1, 0)
+        } else {
+            (4, 1)
+        };
+        let filters = [self.horizontal, self.vertical, self.scanlines];
+        let count = if self.version & 0b11 >= 2 { 3 } else { 2 };
+
+        let mut command = spi.command(UserIoCommands::UserIoSetFilterCoefficients);
+        let mut address = 0u16;
+        for filter in &filters[..count] {
+            for phase in filter.phases().iter().step_by(step) {
+                for tap in phase {
+                    command.write(address).write((tap >> shift) as u16 & 0x3FF);
+                    address += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Set the audio IIR filter: the 40 bits gain, the `x` coefficients and the 24
+/// bits `y` coefficients, least significant word first.
+pub struct SetAudioFilter<'a>(pub &'a AudioFilter);
+
+impl SpiCommand for SetAudioFilter<'_> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+        let gain = self.0.gain();
+        let mut command = spi.command(UserIoCommands::UserIoSetAudioFilter);
+        command
+            .write(gain as u16)
+            .write((gain >> 16) as u16)
+            .write((gain >> 32) as u16 & 0xFF);
+        for x in self.0.x() {
+            command.write(x as u16);
+        }
+        for y in self.0.y() {
+            command.write(y as u16).write((y >> 16) as u16 & 0xFF);
+        }
+
+        Ok(())
+    }
+}
+
+/// Set the scaler shadow mask. Each word has a 3 bits type and a 13 bits value:
+/// 0 for the mode (bit 0 enables the mask, bit 1 doubles its size), 1 and 2 for
+/// the height and width minus one, and 3 for each pixel, row by row.
+pub struct SetShadowMask<'a> {
+    pub mode: ShadowMaskMode,
+    pub mask: Option<&'a ShadowMask>,
+}
+
+impl SpiCommand for SetShadowMask<'_> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+        let mode = match (self.mask, self.mode) {
+            (None, _) | (_, ShadowMaskMode::Off) => 0,
+            (Some(_), ShadowMaskMode::On) => 0b01,
+            (Some(_), ShadowMaskMode::Double) => 0b11,
+        };
+
+        let mut command = spi.command(UserIoCommands::UserIoShadowMask);
+        command.write(mode);
+        if let Some(mask) = self.mask {
+            command
+                .write(1 << 13 | (mask.height() as u16 - 1))
+                .write(2 << 13 | (mask.width() as u16 - 1));
+            for pixel in mask.pixels() {
+                command.write(3 << 13 | (pixel & 0x1FFF));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Set the audio volume as the number of bits to shift to the right.
 pub struct SetAudioVolume(pub u8);
 
@@ -944,3 +1189,271 @@ pub fn sd_status_1() {
     assert_eq!(status.block_size(), 512);
     assert_eq!(status.disk(), 0);
 }
+
+#[test]
+pub fn get_string() {
+    use crate::fpga::fake::{FakeHpsBridge, SpiTransaction};
+
+    let config = b"NES;;O1,Aspect ratio,4:3,16:9;";
+    let bridge = FakeHpsBridge::new(move |t: &SpiTransaction| -> u16 {
+        // The first word is the command, then one character per word.
+        config
+            .get(t.words.len().wrapping_sub(2))
+            .copied()
+            .unwrap_or(0) as u16
+    });
+    let mut fpga = bridge.fpga();
+
+    assert_eq!(
+        fpga.spi_mut().config_string(),
+        "NES;;O1,Aspect ratio,4:3,16:9;"
+    );
+}
+
+#[test]
+pub fn keyboard_keys() {
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+
+    fpga.spi_mut()
+        .execute(UserIoKeyboardKeyDown::from(0x1Cu32))
+        .unwrap();
+    fpga.spi_mut()
+        .execute(UserIoKeyboardKeyDown::from(0x080075u32))
+        .unwrap();
+    fpga.spi_mut()
+        .execute(UserIoKeyboardKeyUp::from(0x1Cu32))
+        .unwrap();
+
+    let words = bridge
+        .transactions()
+        .into_iter()
+        .map(|t| t.words)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        [
+            vec![0x05, 0x1C],
+            vec![0x05, 0xE0, 0x75],
+            vec![0x05, 0xF0, 0x1C]
+        ]
+    );
+}
+
+#[test]
+pub fn joysticks() {
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+
+    fpga.spi_mut().execute(UserIoJoystick(0, 0x0030)).unwrap();
+    fpga.spi_mut()
+        .execute(UserIoJoystick(4, 0x0001_8000))
+        .unwrap();
+
+    let transactions = bridge.transactions();
+    assert_eq!(transactions.len(), 2);
+    assert!(transactions.iter().all(|t| t.features == SpiFeatureSet::IO));
+    assert_eq!(transactions[0].words, [0x02, 0x0030]);
+    assert_eq!(transactions[1].words, [0x12, 0x8000, 0x0001]);
+}
+
+#[test]
+pub fn analog_joysticks() {
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+
+    fpga.spi_mut()
+        .execute(UserIoAnalogJoystick::new(1, AnalogInput::LeftStick(-1, 127), 1).unwrap())
+        .unwrap();
+    fpga.spi_mut()
+        .execute(UserIoAnalogJoystick::new(0, AnalogInput::RightStick(5, -128), 0).unwrap())
+        .unwrap();
+    fpga.spi_mut()
+        .execute(UserIoAnalogJoystick::new(2, AnalogInput::Paddle(200), 1).unwrap())
+        .unwrap();
+    assert!(UserIoAnalogJoystick::new(6, AnalogInput::Paddle(0), 1).is_err());
+
+    let words = bridge
+        .transactions()
+        .into_iter()
+        .map(|t| t.words)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        [
+            vec![0x1A, 0x01, 0x7FFF],
+            vec![0x3D, 0x00, 0x05, 0x80],
+            vec![0x1A, 0x2F, 200],
+        ]
+    );
+}
+
+#[test]
+pub fn mouse() {
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+
+    fpga.spi_mut()
+        .execute(UserIoMouse {
+            buttons: 0b101,
+            dx: -2,
+            dy: 16,
+        })
+        .unwrap();
+
+    let transactions = bridge.transactions();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].words, [0x04, 0x1D, 0xFE, 0x10]);
+}
+
+#[test]
+pub fn rtc() {
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+    let time = chrono::NaiveDate::from_ymd_opt(2024, 3, 15)
+        .unwrap()
+        .and_hms_opt(12, 34, 56)
+        .unwrap();
+
+    fpga.spi_mut().execute(UserIoRtc(time)).unwrap();
+
+    let transactions = bridge.transactions();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].command(), Some(0x22));
+    assert_eq!(
+        transactions[0].data(),
+        [0x56, 0x34, 0x12, 0x15, 0x03, 0x24, 5, 0x40]
+    );
+}
+
+#[test]
+pub fn set_status_bits() {
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+    let mut bits = StatusBitMap::new();
+    bits.set(1, true);
+    bits.set(17, true);
+
+    fpga.spi_mut().execute(SetStatusBits(&bits)).unwrap();
+    bits.set(70, true);
+    fpga.spi_mut().execute(SetStatusBits(&bits)).unwrap();
+
+    let transactions = bridge.transactions();
+    assert_eq!(transactions[0].words, [0x1E, 0x0002, 0x0002, 0, 0]);
+    assert_eq!(
+        transactions[1].words,
+        [0x1E, 0x0002, 0x0002, 0, 0, 0x0040, 0, 0, 0]
+    );
+}
+
+#[test]
+pub fn set_video_mode() {
+    use crate::config::edid::DefaultVideoMode;
+    use crate::fpga::fake::FakeHpsBridge;
+
+    let bridge = FakeHpsBridge::silent();
+    let mut fpga = bridge.fpga();
+    let mut mode: CustomVideoMode = DefaultVideoMode::V1920x1080r60.into();
+    fpga.spi_mut().execute(SetVideoMode(&mode)).unwrap();
+    mode.low_latency = true;
+    fpga.spi_mut().execute(SetVideoMode(&mode)).unwrap();
+
+    let transactions = bridge.transactions();
+    assert_eq!(
+        transactions[0].words[..10],
+        [0x20, 1920, 88, 44, 148, 1080, 4, 5, 36, 4]
+    );
+    // The low latency flag is set on the first PLL word only.
+    assert_eq!(transactions[1].words[9], 0x8004);
+    assert_eq!(transactions[0].words[10..], transactions[1].words[10..]);
+}
+
+#[test]
+pub fn get_status_bits() {
+    use crate::fpga::fake::{FakeHpsBridge, SpiTransaction};
+
+    let bridge = FakeHpsBridge::new(|t: &SpiTransaction| -> u16 {
+        match (t.command(), t.words.len()) {
+            // Status changed, with a counter of 3.
+            (Some(0x29), 1) => 0xA3,
+            (Some(0x29), 2) => 0x0005,
+            (Some(0x29), 3) => 0x0001,
+            _ => 0,
+        }
+    });
+    let mut fpga = bridge.fpga();
+
+    let mut bits = StatusBitMap::new();
+    let mut counter = 0;
+    fpga.spi_mut()
+        .execute(GetStatusBits(&mut bits, &mut counter))
+        .unwrap();
+
+    assert_eq!(counter, 3);
+    // Bit 0 is the reset bit, and is cleared by the HPS.
+    assert!(!bits.get(0));
+    assert!(bits.get(2));
+    assert!(bits.get(16));
+
+    // The HPS sends back the status bits it read.
+    let transactions = bridge.transactions();
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[1].words, [0x1E, 0x0004, 0x0001, 0, 0]);
+
+    // Same counter, nothing to read.
+    bridge.clear();
+    fpga.spi_mut()
+        .execute(GetStatusBits(&mut bits, &mut counter))
+        .unwrap();
+    assert_eq!(bridge.transactions().len(), 1);
+    assert_eq!(bridge.transactions()[0].words, [0x29]);
+}
+
+#[test]
+pub fn get_sd_stat() {
+    use crate::fpga::fake::{FakeHpsBridge, SpiTransaction};
+
+    let bridge = FakeHpsBridge::new(|t: &SpiTransaction| -> u16 {
+        match t.words.len() {
+            1 => 0x8081,
+            3 => 0x1234,
+            4 => 0x0001,
+            _ => 0,
+        }
+    });
+    let mut fpga = bridge.fpga();
+
+    let mut output = SdStatOutput::default();
+    fpga.spi_mut().execute(GetSdStat(&mut output)).unwrap();
+
+    assert_eq!(output.disk, 0);
+    assert_eq!(output.lba, 0x0001_1234);
+    assert_eq!(output.block_count, 1);
+    assert_eq!(output.size, 512);
+}
+
+#[test]
+pub fn memory_size() {
+    use cyclone_v::memory::BufferMemoryMapper;
+
+    let memory = BufferMemoryMapper::create(0x1FFFF000, 0x1000).unwrap();
+    assert_eq!(SetMemorySize::from_memory(memory).unwrap().0, 0);
+
+    let mut memory = BufferMemoryMapper::create(0x1FFFF000, 0x1000).unwrap();
+    memory
+        .as_mut_range(0xF00..0xF04)
+        .copy_from_slice(&[0x12, 0x57, 0x03, 0x04]);
+    assert_eq!(SetMemorySize::from_memory(memory).unwrap().0, 0x8304);
+}
@@ -1,15 +1,25 @@
 use bitfield::bitfield;
+use image::imageops::FilterType;
 use image::{DynamicImage, RgbImage};
 use simple_endian::BigEndian;
+use std::time::{Duration, Instant};
 use tracing::debug;
 
-use cyclone_v::memory::{DevMemMemoryMapper, MemoryMapper};
+use cyclone_v::memory::MemoryMapper;
 
 pub const FB_BASE_ADDRESS: usize = 0x2000_0000;
 pub const BUFFER_SIZE: usize = 2048 * 1024 * 3 * 4;
 
 pub const SCALER_FB_TYPE: u8 = 0x01;
 
+/// Flag for 16-bit formats with 5 bits per channel, same as `FB_FMT_1555`.
+const FORMAT_1555: u8 = 0b01000;
+
+/// Flag for formats with the blue channel first, same as `FB_FMT_RXB`.
+const FORMAT_BGR: u8 = 0b10000;
+
+/// The pixel format of the scaler output. Pixels are stored in big endian,
+/// like the header.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ScalerPixelFormat {
@@ -23,6 +33,22 @@ pub enum ScalerPixelFormat {
     /// The alpha channel is ignored.
     RGBA32 = 2,
 
+    /// 16-bit RGB1555. The top bit is ignored.
+    RGB1555 = FORMAT_1555,
+
+    /// 16-bit BGR565.
+    BGR16 = FORMAT_BGR,
+
+    /// 24-bit BGR888.
+    BGR24 = FORMAT_BGR | 1,
+
+    /// 32-bit BGRA8888.
+    /// The alpha channel is ignored.
+    BGRA32 = FORMAT_BGR | 2,
+
+    /// 16-bit BGR1555. The top bit is ignored.
+    BGR1555 = FORMAT_BGR | FORMAT_1555,
+
     INVALID = 0xFF,
 }
 
@@ -32,11 +58,62 @@ impl From<u8> for ScalerPixelFormat {
             0 => ScalerPixelFormat::RGB16,
             1 => ScalerPixelFormat::RGB24,
             2 => ScalerPixelFormat::RGBA32,
+            FORMAT_1555 => ScalerPixelFormat::RGB1555,
+            FORMAT_BGR => ScalerPixelFormat::BGR16,
+            0x11 => ScalerPixelFormat::BGR24,
+            0x12 => ScalerPixelFormat::BGRA32,
+            0x18 => ScalerPixelFormat::BGR1555,
             _ => ScalerPixelFormat::INVALID,
         }
     }
 }
 
+impl ScalerPixelFormat {
+    /// The number of bytes of a single pixel, or `None` if the format is invalid.
+    pub fn bytes_per_pixel(&self) -> Option<usize> {
+        match self {
+            ScalerPixelFormat::RGB16
+            | ScalerPixelFormat::RGB1555
+            | ScalerPixelFormat::BGR16
+            | ScalerPixelFormat::BGR1555 => Some(2),
+            ScalerPixelFormat::RGB24 | ScalerPixelFormat::BGR24 => Some(3),
+            ScalerPixelFormat::RGBA32 | ScalerPixelFormat::BGRA32 => Some(4),
+            ScalerPixelFormat::INVALID => None,
+        }
+    }
+
+    /// Decode a pixel of this format into RGB888. `pixel` must be at least
+    /// [`Self::bytes_per_pixel`] long.
+    pub fn to_rgb(&self, pixel: &[u8]) -> [u8; 3] {
+        let word = || u16::from_be_bytes([pixel[0], pixel[1]]);
+        // Expand a 5 or 6-bit channel to 8 bits, so the maximum stays the maximum.
+        let c5 = |v: u16| ((v & 0x1F) << 3 | (v & 0x1F) >> 2) as u8;
+        let c6 = |v: u16| ((v & 0x3F) << 2 | (v & 0x3F) >> 4) as u8;
+
+        let [r, g, b] = match self {
+            ScalerPixelFormat::RGB16 | ScalerPixelFormat::BGR16 => {
+                let w = word();
+                [c5(w >> 11), c6(w >> 5), c5(w)]
+            }
+            ScalerPixelFormat::RGB1555 | ScalerPixelFormat::BGR1555 => {
+                let w = word();
+                [c5(w >> 10), c5(w >> 5), c5(w)]
+            }
+            ScalerPixelFormat::RGB24
+            | ScalerPixelFormat::BGR24
+            | ScalerPixelFormat::RGBA32
+            | ScalerPixelFormat::BGRA32 => [pixel[0], pixel[1], pixel[2]],
+            ScalerPixelFormat::INVALID => [0, 0, 0],
+        };
+
+        if (*self as u8) & FORMAT_BGR != 0 {
+            [b, g, r]
+        } else {
+            [r, g, b]
+        }
+    }
+}
+
 bitfield! {
     #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct ScalerAttributes(u16);
@@ -51,10 +128,10 @@ bitfield! {
     /// True if triple buffered.
     pub triple_buffered, _: 4;
 
-    /// A Frame counter in the scaler image header. Although this is
-    /// named "counter", it is more like a checksum and its value will
-    /// not necessarily increment with each frame.
-    pub frame_counter, _: 5, 3;
+    /// A 3 bits frame counter in the scaler image header. The scaler
+    /// increments it (modulo 8) every time it starts writing a new frame,
+    /// and writes it in the header of the buffer receiving that frame.
+    pub frame_counter, _: 7, 5;
 }
 
 impl From<u16> for ScalerAttributes {
@@ -89,7 +166,6 @@ impl FbHeader {
         }
     }
 
-    #[allow(unused)]
     pub fn frame_checksum(&self) -> u8 {
         self.attributes().frame_counter()
     }
@@ -157,9 +233,19 @@ impl FramebufferType {
     }
 }
 
-/// An iterator that waits a frame.
+/// How long [`FrameIter`] waits for a new frame by default, before giving up.
+pub const DEFAULT_FRAME_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long [`FrameIter`] sleeps between two polls of the frame counters.
+const FRAME_POLL_INTERVAL: Duration = Duration::from_micros(500);
+
+/// An iterator that waits a frame, and yields the number of frames completed
+/// since the last call. Iteration stops if no frame is completed before the
+/// timeout.
 pub struct FrameIter {
     frame_counters: [*const u8; 3],
+    last: u8,
+    timeout: Duration,
 }
 
 impl FrameIter {
@@ -174,28 +260,148 @@ impl FrameIter {
             let ptr2 = framebuffer.memory.as_ptr::<u8>().add(header2).add(5);
 
             let frame_counters = [ptr0, ptr1, ptr2];
+            let mut this = Self {
+                frame_counters,
+                last: 0,
+                timeout: DEFAULT_FRAME_TIMEOUT,
+            };
+            this.last = this.current();
+            this
+        }
+    }
+
+    /// Wait at most `timeout` for a new frame.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
 
-            Self { frame_counters }
+    /// The sum of the frame counters of all buffers, modulo 8. Every new
+    /// frame increments the counter of one buffer (without triple buffering,
+    /// the same buffer is read three times), so this sum advances by 3 with
+    /// each frame.
+    #[inline]
+    fn current(&self) -> u8 {
+        unsafe {
+            self.frame_counters
+                .iter()
+                .fold(0u8, |acc, f| acc.wrapping_add(f.read_volatile() >> 5))
+                & 7
+        }
+    }
+
+    /// Returns the number of frames completed since the last call, without
+    /// waiting. Since frame counters are 3 bits, this wraps after 7 frames.
+    pub fn poll(&mut self) -> u8 {
+        let current = self.current();
+        let delta = current.wrapping_sub(self.last);
+        self.last = current;
+
+        // 3 is its own inverse modulo 8.
+        delta.wrapping_mul(3) & 7
+    }
+
+    /// Wait for a new frame, returning the number of frames completed since
+    /// the last call, or an error after the timeout.
+    pub fn wait(&mut self) -> Result<u8, String> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let frames = self.poll();
+            if frames != 0 {
+                return Ok(frames);
+            }
+            if Instant::now() >= deadline {
+                return Err("Timed out waiting for a frame.".to_string());
+            }
+            std::thread::sleep(FRAME_POLL_INTERVAL);
         }
     }
 }
 
 impl Iterator for FrameIter {
-    type Item = ();
+    type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            let last: u8 = self.frame_counters.iter().map(|f| f.read_volatile()).sum();
+        self.wait().ok()
+    }
+}
+
+/// Captures successive frames of the scaler, as they are completed. Frames can
+/// be downscaled to fit a maximum size.
+///
+/// Iterating waits for each new frame, and yields the frame along with the
+/// number of frames elapsed since the previous one. Use [`FrameCapture::poll`]
+/// to capture frames without blocking.
+pub struct FrameCapture<'a, M: MemoryMapper> {
+    framebuffer: &'a FpgaFramebuffer<M>,
+    frames: FrameIter,
+    interval: usize,
+    max_size: Option<(u32, u32)>,
+}
+
+impl<'a, M: MemoryMapper> FrameCapture<'a, M> {
+    pub fn new(framebuffer: &'a FpgaFramebuffer<M>) -> Self {
+        Self {
+            framebuffer,
+            frames: FrameIter::new(framebuffer),
+            interval: 1,
+            max_size: None,
+        }
+    }
+
+    /// Only capture one frame out of `interval` when iterating.
+    pub fn with_interval(mut self, interval: usize) -> Self {
+        self.interval = interval.max(1);
+        self
+    }
+
+    /// Downscale frames larger than `width` x `height`, keeping their aspect
+    /// ratio. Smaller frames are left as is.
+    pub fn with_max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    /// Wait at most `timeout` for each new frame when iterating.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.frames = self.frames.with_timeout(timeout);
+        self
+    }
+
+    /// Capture the last completed frame, without waiting for a new one.
+    pub fn capture(&self) -> Result<DynamicImage, String> {
+        let image = self.framebuffer.take_screenshot()?;
 
-            loop {
-                let current: u8 = self.frame_counters.iter().map(|f| f.read_volatile()).sum();
-                if current != last {
-                    break;
-                }
+        match self.max_size {
+            Some((width, height)) if image.width() > width || image.height() > height => {
+                Ok(image.resize(width, height, FilterType::Triangle))
             }
+            _ => Ok(image),
+        }
+    }
 
-            Some(())
+    /// Capture a frame if a new one was completed since the last call.
+    pub fn poll(&mut self) -> Option<Result<DynamicImage, String>> {
+        if self.frames.poll() != 0 {
+            Some(self.capture())
+        } else {
+            None
+        }
+    }
+}
+
+impl<M: MemoryMapper> Iterator for FrameCapture<'_, M> {
+    type Item = Result<(DynamicImage, usize), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut frames = 0;
+        while frames < self.interval {
+            match self.frames.wait() {
+                Ok(n) => frames += n as usize,
+                Err(e) => return Some(Err(e)),
+            }
         }
+        Some(self.capture().map(|image| (image, frames)))
     }
 }
 
@@ -206,15 +412,14 @@ pub struct FpgaFramebuffer<M: MemoryMapper> {
     ty_: Option<FramebufferType>,
 }
 
-impl Default for FpgaFramebuffer<DevMemMemoryMapper> {
+impl<M: MemoryMapper> Default for FpgaFramebuffer<M> {
     fn default() -> Self {
         // In MiSTer there is an alignment of the address to the page size.
         // We know the page size in advance, so we don't need to calculate
         // it.
         let address = FB_BASE_ADDRESS;
         let size = BUFFER_SIZE;
-        let mapper =
-            DevMemMemoryMapper::create(address, size).expect("Could not mmap framebuffer.");
+        let mapper = M::create(address, size).expect("Could not mmap framebuffer.");
 
         Self::new(mapper).unwrap()
     }
@@ -257,7 +462,6 @@ impl<M: MemoryMapper> FpgaFramebuffer<M> {
         self.ty_.and_then(|ty| ty.offset_of(index))
     }
 
-    #[allow(unused)]
     pub(crate) fn header(&self, index: u8) -> Option<FbHeader> {
         self.offset_of(index)
             .and_then(|offset| unsafe { self.header_offset(offset) })
@@ -267,6 +471,46 @@ impl<M: MemoryMapper> FpgaFramebuffer<M> {
         unsafe { self.header_offset(0).unwrap() }
     }
 
+    /// The index of the buffer holding the last completed frame. With triple
+    /// buffering, the scaler writes buffers in order (0, 1, 2, 0, ...) and
+    /// writes the incremented frame counter in a buffer when it starts writing
+    /// it. Consecutive buffers therefore hold consecutive counters, and the
+    /// last completed frame is in the buffer before the one with the newest
+    /// counter.
+    ///
+    /// If the counters are not consecutive (e.g. the scaler was just reset and
+    /// not all buffers were written yet), this falls back to the first buffer.
+    pub(crate) fn last_frame_index(&self) -> u8 {
+        let counters = [0, 1, 2].map(|i| self.header(i).map(|h| h.frame_checksum()));
+        let [Some(c0), Some(c1), Some(c2)] = counters else {
+            return 0;
+        };
+
+        let counters = [c0, c1, c2];
+        (0..3)
+            .find(|&i| counters[i].wrapping_sub(counters[(i + 1) % 3]) & 7 == 2)
+            .map(|newest| ((newest + 2) % 3) as u8)
+            .unwrap_or(0)
+    }
+
+    /// The header and offset of the last completed frame.
+    fn last_frame(&self) -> (FbHeader, usize) {
+        let index = self.last_frame_index();
+        self.offset_of(index)
+            .and_then(|offset| unsafe { self.header_offset(offset) }.map(|h| (h, offset)))
+            .unwrap_or_else(|| (self.first_header(), 0))
+    }
+
+    /// The pixels of a frame, with padding at the end of lines.
+    fn frame_data(&self, header: &FbHeader, offset: usize) -> Result<&[u8], String> {
+        let start = offset + header.header_len() as usize;
+        let len = header.line() as usize * header.height() as usize;
+        self.memory
+            .as_range(start..)
+            .get(..len)
+            .ok_or_else(|| "Framebuffer header is out of memory bounds.".to_string())
+    }
+
     pub fn write(&mut self, data: &[u8]) -> Result<(), String> {
         let header_len = self.first_header().header_len() as usize;
         self.memory
@@ -276,41 +520,217 @@ impl<M: MemoryMapper> FpgaFramebuffer<M> {
         Ok(())
     }
 
-    pub fn take_screenshot(&self) -> Result<DynamicImage, String> {
-        // Bytes are in big endian, but ARM is in little endian.
-        let header = self.first_header();
+    /// A CRC32 of the visible pixels of the current frame. Padding at the end
+    /// of lines is ignored, so this only changes when the picture does.
+    pub fn frame_crc32(&self) -> Result<u32, String> {
+        let (header, offset) = self.last_frame();
+        let bytes_per_pixel = header
+            .scaler_pixel_format()
+            .bytes_per_pixel()
+            .ok_or("Invalid Scaler PixelFormat.")?;
+
+        let height = header.height() as usize;
+        let width = header.width() as usize * bytes_per_pixel;
+        let line = header.line() as usize;
+        if width > line {
+            return Err("Invalid framebuffer line length.".to_string());
+        }
+        let fb = self.frame_data(&header, offset)?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        for y in 0..height {
+            hasher.update(&fb[y * line..y * line + width]);
+        }
+        Ok(hasher.finalize())
+    }
 
+    /// Take a screenshot of the last completed frame.
+    pub fn take_screenshot(&self) -> Result<DynamicImage, String> {
+        let (header, offset) = self.last_frame();
         debug!("Header data: {:?}", header);
 
+        let format = header.scaler_pixel_format();
+        let bytes_per_pixel = format
+            .bytes_per_pixel()
+            .ok_or("Invalid Scaler PixelFormat.")?;
+
         let height = header.height() as usize;
         let width = header.width() as usize;
         let line = header.line() as usize;
-        let start = self.memory.as_ptr::<u8>();
-        let fb = unsafe {
-            std::slice::from_raw_parts(start.add(header.header_len() as usize), line * height * 3)
-        };
+        if width * bytes_per_pixel > line {
+            return Err("Invalid framebuffer line length.".to_string());
+        }
+        let fb = self.frame_data(&header, offset)?;
 
-        // TODO: add support for RGBA and RGB565.
-        let mut img = match header.scaler_pixel_format() {
-            ScalerPixelFormat::RGB16 => {
-                return Err("RGB565 not supported.".to_string());
+        let mut img = RgbImage::new(width as u32, height as u32);
+        for (y, row) in img.rows_mut().enumerate() {
+            let pixels = fb[y * line..].chunks_exact(bytes_per_pixel);
+            for (pixel, data) in row.zip(pixels) {
+                pixel.0 = format.to_rgb(data);
             }
-            ScalerPixelFormat::RGB24 => RgbImage::new(width as u32, height as u32),
-            ScalerPixelFormat::RGBA32 => {
-                return Err("RGBA32 not supported.".to_string());
-            }
-            ScalerPixelFormat::INVALID => {
-                return Err("Invalid Scaler PixelFormat.".to_string());
-            }
-        };
-
-        for y in 0..height {
-            let line = &fb[y * line..y * line + width * 3];
-            img.get_mut(y * width * 3..y * width * 3 + width * 3)
-                .unwrap()
-                .copy_from_slice(line);
         }
 
         Ok(DynamicImage::ImageRgb8(img))
     }
 }
+
+#[test]
+fn frame_crc32_ignores_line_padding() {
+    use cyclone_v::memory::BufferMemoryMapper;
+
+    let mut memory = BufferMemoryMapper::create(FB_BASE_ADDRESS, 0x1000).unwrap();
+    // RGB24, 2x2 pixels, lines of 8 bytes starting at 0x20.
+    memory
+        .as_mut_range(0..14)
+        .copy_from_slice(&[1, 1, 0, 0x20, 0, 0, 0, 2, 0, 2, 0, 8, 0, 2]);
+    memory
+        .as_mut_range(0x20..0x30)
+        .copy_from_slice(&[1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0]);
+    let mut fb = FpgaFramebuffer::new(memory).unwrap();
+    let crc = fb.frame_crc32().unwrap();
+    assert_eq!(
+        crc,
+        crc32fast::hash(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12])
+    );
+
+    fb.memory
+        .as_mut_range(0x26..0x28)
+        .copy_from_slice(&[0xFF, 0xFF]);
+    assert_eq!(fb.frame_crc32().unwrap(), crc);
+
+    fb.memory.as_mut_range(0x20..0x21).copy_from_slice(&[0xFF]);
+    assert_ne!(fb.frame_crc32().unwrap(), crc);
+
+    // Lines shorter than the width.
+    fb.memory.as_mut_range(10..12).copy_from_slice(&[0, 4]);
+    assert!(fb.frame_crc32().is_err());
+
+    // A frame larger than the memory.
+    fb.memory
+        .as_mut_range(8..12)
+        .copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+    assert!(fb.frame_crc32().is_err());
+    assert!(fb.take_screenshot().is_err());
+}
+
+#[cfg(test)]
+fn test_framebuffer(
+    format: u8,
+    pixels: &[u8],
+) -> FpgaFramebuffer<cyclone_v::memory::BufferMemoryMapper> {
+    use cyclone_v::memory::BufferMemoryMapper;
+
+    // 2x1 pixels, lines of 8 bytes starting at 0x20.
+    let mut memory = BufferMemoryMapper::create(FB_BASE_ADDRESS, 0x1000).unwrap();
+    memory
+        .as_mut_range(0..14)
+        .copy_from_slice(&[1, format, 0, 0x20, 0, 0, 0, 2, 0, 1, 0, 8, 0, 2]);
+    memory
+        .as_mut_range(0x20..0x20 + pixels.len())
+        .copy_from_slice(pixels);
+    FpgaFramebuffer::new(memory).unwrap()
+}
+
+#[test]
+fn screenshot_formats() {
+    let expected = [0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF];
+    for (format, pixels) in [
+        (0x00, vec![0xF8, 0x00, 0x00, 0x1F]),
+        (0x01, vec![0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF]),
+        (0x02, vec![0xFF, 0x00, 0x00, 0x80, 0x00, 0x00, 0xFF, 0x80]),
+        (0x08, vec![0x7C, 0x00, 0x00, 0x1F]),
+        (0x10, vec![0x00, 0x1F, 0xF8, 0x00]),
+        (0x11, vec![0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00]),
+        (0x12, vec![0x00, 0x00, 0xFF, 0x80, 0xFF, 0x00, 0x00, 0x80]),
+        (0x18, vec![0x00, 0x1F, 0x7C, 0x00]),
+    ] {
+        let image = test_framebuffer(format, &pixels).take_screenshot().unwrap();
+        assert_eq!(image.as_bytes(), expected, "format {format:#x}");
+    }
+
+    assert!(test_framebuffer(0x03, &[]).take_screenshot().is_err());
+}
+
+#[test]
+fn screenshot_last_triple_buffer() {
+    use cyclone_v::memory::BufferMemoryMapper;
+
+    let mut memory = BufferMemoryMapper::create(FB_BASE_ADDRESS, 0x0040_1000).unwrap();
+    // RGB24, 1x1 pixel. Buffer 1 is being written, so buffer 0 is the last frame.
+    for (index, counter) in [(0u8, 3u8), (1, 4), (2, 2)] {
+        let offset = FramebufferType::TripleSmall.offset_of(index).unwrap();
+        memory.as_mut_range(offset..offset + 14).copy_from_slice(&[
+            1,
+            1,
+            0,
+            0x20,
+            0,
+            0x10 | counter << 5,
+            0,
+            1,
+            0,
+            1,
+            0,
+            3,
+            0,
+            1,
+        ]);
+        memory
+            .as_mut_range(offset + 0x20..offset + 0x23)
+            .copy_from_slice(&[index; 3]);
+    }
+    let mut fb = FpgaFramebuffer::new(memory).unwrap();
+    fb.ty_ = Some(FramebufferType::TripleSmall);
+
+    assert_eq!(fb.last_frame_index(), 0);
+    assert_eq!(fb.take_screenshot().unwrap().as_bytes(), [0, 0, 0]);
+
+    // Buffer 2 is being written, so buffer 1 is the last frame.
+    let offset = FramebufferType::TripleSmall.offset_of(2).unwrap();
+    fb.memory.as_mut_range(offset + 5..offset + 6)[0] = 0x10 | 5 << 5;
+    assert_eq!(fb.last_frame_index(), 1);
+    assert_eq!(fb.take_screenshot().unwrap().as_bytes(), [1, 1, 1]);
+
+    // Counters that are not consecutive fall back to the first buffer.
+    fb.memory.as_mut_range(offset + 5..offset + 6)[0] = 0x10 | 7 << 5;
+    assert_eq!(fb.last_frame_index(), 0);
+}
+
+#[test]
+fn capture_downscales() {
+    let mut fb = test_framebuffer(0x01, &[0xFF, 0, 0, 0xFF, 0, 0]);
+    fb.ty_ = Some(FramebufferType::Single);
+    let capture = FrameCapture::new(&fb);
+    assert_eq!(capture.capture().unwrap().width(), 2);
+
+    let capture = capture.with_max_size(1, 1);
+    let image = capture.capture().unwrap();
+    assert_eq!((image.width(), image.height()), (1, 1));
+    assert_eq!(image.as_bytes(), [0xFF, 0, 0]);
+}
+
+#[test]
+fn frame_iter_counts_frames() {
+    let mut fb = test_framebuffer(0x01, &[0xFF, 0, 0, 0xFF, 0, 0]);
+    fb.ty_ = Some(FramebufferType::Single);
+    let mut frames = FrameIter::new(&fb).with_timeout(Duration::from_millis(10));
+    assert!(frames.wait().is_err());
+
+    fb.memory.as_mut_range(5..6)[0] = 1 << 5;
+    assert_eq!(frames.wait(), Ok(1));
+
+    // Counters wrap around.
+    fb.memory.as_mut_range(5..6)[0] = 0;
+    assert_eq!(frames.wait(), Ok(7));
+    assert_eq!(frames.poll(), 0);
+
+    // The scaler writes behind the back of the capture.
+    let counter = fb.memory.as_mut_range(5..6).as_mut_ptr();
+    let mut capture = FrameCapture::new(&fb)
+        .with_interval(2)
+        .with_timeout(Duration::from_millis(10));
+    unsafe { counter.write_volatile(3 << 5) };
+    let (image, frames) = capture.next().unwrap().unwrap();
+    assert_eq!((image.width(), frames), (2, 3));
+    assert!(capture.next().unwrap().is_err());
+}
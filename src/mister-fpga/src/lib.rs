@@ -1,10 +1,15 @@
+pub mod cheats;
 pub mod config;
 pub mod config_string;
 pub mod core;
 pub mod core_info;
+pub mod dip;
+pub mod filters;
 pub mod fpga;
 pub mod framebuffer;
 pub mod keyboard;
+pub mod mra;
 pub mod osd;
 pub mod savestate;
+pub mod shadow_mask;
 pub mod types;
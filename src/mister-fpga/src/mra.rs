@@ -0,0 +1,553 @@
+//! MRA files (MiSTer ROM Archive), which describe arcade games.
+//!
+//! An MRA names the core (RBF) to load, and how to assemble the ROMs of the game
+//! from files in MAME zip archives:
+//!
+//! ```xml
+//! <misterromdescription>
+//!   <name>Pac-Man</name>
+//!   <setname>pacman</setname>
+//!   <rbf>pacman</rbf>
+//!   <rom index="0" zip="pacman.zip|puckman.zip">
+//!     <part name="pacman.6e" crc="c1e6ab10"/>
+//!     <part repeat="0x10">FF</part>
+//!     <interleave output="16">
+//!       <part name="hi.bin" crc="12345678" map="10"/>
+//!       <part name="lo.bin" crc="9abcdef0" map="01"/>
+//!     </interleave>
+//!   </rom>
+//!   <switches default="FF">...</switches>
+//! </misterromdescription>
+//! ```
+//!
+//! Parts are concatenated in order. A file part can be sliced with `offset` and
+//! `length`, and any part can be repeated with `repeat`. Interleaved parts are
+//! read word by word; the `map` of a part has one digit per output byte (most
+//! significant byte first), which is the byte of the part (1-based) written
+//! there, or 0 if the byte comes from another part.
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use one_fpga::archive::ArchivePath;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::dip::DipSwitches;
+
+/// Directories where MAME zip files are searched, after the directory of the MRA
+/// file and its `mame` sub-directory.
+pub const ROM_DIRECTORIES: &[&str] = &["/media/fat/games/mame", "/media/fat/games/hbmame"];
+
+/// A part of a ROM that could not be assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartError {
+    /// The file was not found in any of the zip files.
+    Missing { name: String },
+
+    /// The file was found but its CRC32 does not match.
+    BadCrc {
+        name: String,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl Display for PartError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartError::Missing { name } => write!(f, "{name} (missing)"),
+            PartError::BadCrc {
+                name,
+                expected,
+                actual,
+            } => write!(f, "{name} (CRC {actual:08x}, expected {expected:08x})"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MraError {
+    #[error("Invalid MRA file: {0}")]
+    Invalid(String),
+
+    #[error("Could not read MRA file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not find core {0:?}")]
+    CoreNotFound(String),
+
+    #[error("ROM {index} has invalid parts: {}", display_parts(.parts))]
+    Parts { index: u8, parts: Vec<PartError> },
+}
+
+fn display_parts(parts: &[PartError]) -> String {
+    parts
+        .iter()
+        .map(PartError::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A file inside a zip, used by a ROM part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MraFile {
+    /// The name of the file in the zip.
+    pub name: String,
+
+    /// The zip files to look into, instead of the ones of the ROM.
+    pub zips: Vec<String>,
+
+    /// The expected CRC32 of the (whole) file.
+    pub crc: Option<u32>,
+}
+
+/// A part of a ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MraPart {
+    /// A file from a zip, optionally sliced.
+    File {
+        file: MraFile,
+        offset: usize,
+        length: Option<usize>,
+        repeat: usize,
+    },
+
+    /// Inline data.
+    Data { data: Vec<u8>, repeat: usize },
+
+    /// Files interleaved into words of `output` bytes.
+    Interleave {
+        output: usize,
+        parts: Vec<(MraFile, Vec<u8>)>,
+    },
+}
+
+/// A ROM to send to the core, on a file index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MraRom {
+    pub index: u8,
+    pub zips: Vec<String>,
+    pub parts: Vec<MraPart>,
+}
+
+/// A parsed MRA file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mra {
+    pub name: String,
+    pub setname: Option<String>,
+    pub rbf: Option<String>,
+    pub roms: Vec<MraRom>,
+    pub switches: Option<DipSwitches>,
+}
+
+fn parse_number(value: &str) -> Result<usize, MraError> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|e| MraError::Invalid(format!("Invalid number {value:?}: {e}")))
+}
+
+fn parse_crc(value: &str) -> Result<u32, MraError> {
+    u32::from_str_radix(value.trim(), 16)
+        .map_err(|e| MraError::Invalid(format!("Invalid CRC {value:?}: {e}")))
+}
+
+fn parse_hex_data(text: &str) -> Result<Vec<u8>, MraError> {
+    text.split_whitespace()
+        .map(|b| {
+            u8::from_str_radix(b, 16)
+                .map_err(|e| MraError::Invalid(format!("Invalid data {b:?}: {e}")))
+        })
+        .collect()
+}
+
+fn split_zips(value: Option<&str>) -> Vec<String> {
+    value
+        .map(|z| z.split('|').map(|z| z.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn text(node: roxmltree::Node, tag: &str) -> Option<String> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+}
+
+impl MraFile {
+    fn from_node(node: roxmltree::Node) -> Result<Self, MraError> {
+        Ok(Self {
+            name: node.attribute("name").unwrap_or_default().to_string(),
+            zips: split_zips(node.attribute("zip")),
+            crc: node.attribute("crc").map(parse_crc).transpose()?,
+        })
+    }
+}
+
+impl MraPart {
+    fn from_node(node: roxmltree::Node) -> Result<Self, MraError> {
+        let repeat = node
+            .attribute("repeat")
+            .map(parse_number)
+            .transpose()?
+            .unwrap_or(1);
+
+        if node.has_tag_name("interleave") {
+            let output = node
+                .attribute("output")
+                .map(parse_number)
+                .transpose()?
+                .unwrap_or(8)
+                / 8;
+            let children: Vec<_> = node.children().filter(|n| n.has_tag_name("part")).collect();
+            if output == 0 || children.is_empty() {
+                return Err(MraError::Invalid("Invalid interleave".to_string()));
+            }
+
+            let parts = children
+                .iter()
+                .enumerate()
+                .map(|(i, part)| {
+                    let map = match part.attribute("map") {
+                        Some(map) => map
+                            .chars()
+                            .map(|c| c.to_digit(10).map(|d| d as u8))
+                            .collect::<Option<Vec<u8>>>()
+                            .filter(|m| m.len() == output)
+                            .ok_or_else(|| MraError::Invalid(format!("Invalid map {map:?}")))?,
+                        None => {
+                            // Split the output equally between parts, the first
+                            // part being the least significant.
+                            let width = output / children.len();
+                            (0..output)
+                                .rev()
+                                .map(|byte| match byte.checked_sub(i * width) {
+                                    Some(b) if b < width => b as u8 + 1,
+                                    _ => 0,
+                                })
+                                .collect()
+                        }
+                    };
+                    Ok((MraFile::from_node(*part)?, map))
+                })
+                .collect::<Result<_, MraError>>()?;
+
+            return Ok(MraPart::Interleave { output, parts });
+        }
+
+        match node.text().map(str::trim).filter(|t| !t.is_empty()) {
+            Some(text) => Ok(MraPart::Data {
+                data: parse_hex_data(text)?,
+                repeat,
+            }),
+            None => Ok(MraPart::File {
+                file: MraFile::from_node(node)?,
+                offset: node
+                    .attribute("offset")
+                    .map(parse_number)
+                    .transpose()?
+                    .unwrap_or(0),
+                length: node.attribute("length").map(parse_number).transpose()?,
+                repeat,
+            }),
+        }
+    }
+}
+
+impl Mra {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, MraError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(xml: &str) -> Result<Self, MraError> {
+        let doc = roxmltree::Document::parse(xml).map_err(|e| MraError::Invalid(e.to_string()))?;
+        let root = doc.root_element();
+        if !root.has_tag_name("misterromdescription") {
+            return Err(MraError::Invalid("Not an MRA file".to_string()));
+        }
+
+        let roms = root
+            .children()
+            .filter(|n| n.has_tag_name("rom"))
+            .map(|rom| {
+                let index = rom
+                    .attribute("index")
+                    .map(parse_number)
+                    .transpose()?
+                    .unwrap_or(0);
+                let parts = rom
+                    .children()
+                    .filter(|n| n.has_tag_name("part") || n.has_tag_name("interleave"))
+                    .map(MraPart::from_node)
+                    .collect::<Result<_, _>>()?;
+
+                Ok(MraRom {
+                    index: u8::try_from(index)
+                        .map_err(|_| MraError::Invalid(format!("Invalid ROM index {index}")))?,
+                    zips: split_zips(rom.attribute("zip")),
+                    parts,
+                })
+            })
+            .collect::<Result<_, MraError>>()?;
+
+        let switches = root
+            .children()
+            .find(|n| n.has_tag_name("switches"))
+            .map(DipSwitches::from_node)
+            .transpose()
+            .map_err(MraError::Invalid)?;
+
+        Ok(Self {
+            name: text(root, "name").unwrap_or_default(),
+            setname: text(root, "setname"),
+            rbf: text(root, "rbf"),
+            roms,
+            switches,
+        })
+    }
+
+    /// The name to use for files saved for this game (e.g. DIP switches).
+    pub fn game_name(&self) -> &str {
+        self.setname.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Find the core of this MRA, in a `cores` directory next to the MRA or in one
+    /// of its parents. Cores are named `{rbf}_{date}.rbf` or `{rbf}.rbf`; the most
+    /// recent is used.
+    pub fn find_rbf(&self, mra_path: &Path) -> Result<PathBuf, MraError> {
+        let rbf = self
+            .rbf
+            .as_ref()
+            .ok_or_else(|| MraError::Invalid("No core in MRA file".to_string()))?;
+        let rbf_lower = rbf.to_lowercase();
+
+        let mut candidates: Vec<PathBuf> = mra_path
+            .ancestors()
+            .skip(1)
+            .take(3)
+            .filter_map(|dir| std::fs::read_dir(dir.join("cores")).ok())
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                name.ends_with(".rbf")
+                    && (name == format!("{rbf_lower}.rbf")
+                        || name.starts_with(&format!("{rbf_lower}_")))
+            })
+            .collect();
+
+        candidates.sort();
+        candidates
+            .pop()
+            .ok_or_else(|| MraError::CoreNotFound(rbf.clone()))
+    }
+}
+
+/// Reads files from zips for an MRA.
+struct ZipFinder<'a> {
+    dirs: &'a [PathBuf],
+}
+
+impl ZipFinder<'_> {
+    fn read(&self, file: &MraFile, rom_zips: &[String]) -> Option<Vec<u8>> {
+        let zips = if file.zips.is_empty() {
+            rom_zips
+        } else {
+            &file.zips
+        };
+
+        zips.iter()
+            .flat_map(|zip| self.dirs.iter().map(move |dir| dir.join(zip)))
+            .filter(|path| path.is_file())
+            .find_map(|path| {
+                let archive = ArchivePath::list(&path).ok()?.into_iter().find(|member| {
+                    let name = member.member();
+                    let name = name.rsplit('/').next().unwrap_or(name);
+                    name.eq_ignore_ascii_case(&file.name)
+                })?;
+                debug!(?archive, "Reading MRA part");
+                archive.read().ok()
+            })
+    }
+
+    /// Read a file and verify its CRC. Errors are collected and an empty file is
+    /// returned, so all errors can be reported at once.
+    fn read_checked(
+        &self,
+        file: &MraFile,
+        rom_zips: &[String],
+        errors: &mut Vec<PartError>,
+    ) -> Vec<u8> {
+        let Some(data) = self.read(file, rom_zips) else {
+            errors.push(PartError::Missing {
+                name: file.name.clone(),
+            });
+            return Vec::new();
+        };
+
+        if let Some(expected) = file.crc {
+            let actual = crc32fast::hash(&data);
+            if actual != expected {
+                errors.push(PartError::BadCrc {
+                    name: file.name.clone(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+        data
+    }
+}
+
+impl MraRom {
+    /// Assemble the ROM from its parts. Zip files are searched in `dirs`, in order.
+    /// All missing parts and bad CRCs are reported together.
+    pub fn assemble(&self, dirs: &[PathBuf]) -> Result<Vec<u8>, MraError> {
+        let finder = ZipFinder { dirs };
+        let mut errors = Vec::new();
+        let mut rom = Vec::new();
+
+        for part in &self.parts {
+            match part {
+                MraPart::Data { data, repeat } => {
+                    for _ in 0..*repeat {
+                        rom.extend_from_slice(data);
+                    }
+                }
+                MraPart::File {
+                    file,
+                    offset,
+                    length,
+                    repeat,
+                } => {
+                    let data = finder.read_checked(file, &self.zips, &mut errors);
+                    let start = (*offset).min(data.len());
+                    let end = length.map_or(data.len(), |l| (start + l).min(data.len()));
+                    for _ in 0..*repeat {
+                        rom.extend_from_slice(&data[start..end]);
+                    }
+                }
+                MraPart::Interleave { output, parts } => {
+                    let inputs: Vec<_> = parts
+                        .iter()
+                        .map(|(file, map)| {
+                            let data = finder.read_checked(file, &self.zips, &mut errors);
+                            (data, map)
+                        })
+                        .collect();
+                    interleave(*output, &inputs, &mut rom);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(rom)
+        } else {
+            Err(MraError::Parts {
+                index: self.index,
+                parts: errors,
+            })
+        }
+    }
+}
+
+/// Interleave inputs into words of `output` bytes, using their maps.
+fn interleave(output: usize, inputs: &[(Vec<u8>, &Vec<u8>)], rom: &mut Vec<u8>) {
+    let widths: Vec<usize> = inputs
+        .iter()
+        .map(|(_, map)| map.iter().filter(|b| **b != 0).count().max(1))
+        .collect();
+    let words = inputs
+        .iter()
+        .zip(&widths)
+        .map(|((data, _), width)| data.len() / width)
+        .max()
+        .unwrap_or(0);
+
+    for word in 0..words {
+        let mut out = vec![0u8; output];
+        for ((data, map), width) in inputs.iter().zip(&widths) {
+            for (position, byte) in map.iter().enumerate() {
+                if *byte != 0 {
+                    let src = word * width + (*byte as usize - 1);
+                    out[output - 1 - position] = data.get(src).copied().unwrap_or(0);
+                }
+            }
+        }
+        rom.extend_from_slice(&out);
+    }
+}
+
+#[test]
+fn parse() {
+    let mra = Mra::parse(
+        r#"<misterromdescription>
+            <name>Test Game</name>
+            <setname>test</setname>
+            <rbf>testcore</rbf>
+            <rom index="1" zip="test.zip|parent.zip">
+                <part name="a.bin" crc="0000ABCD" offset="0x10" length="16"/>
+                <part repeat="2">01 02</part>
+                <interleave output="16">
+                    <part name="hi.bin"/>
+                    <part name="lo.bin"/>
+                </interleave>
+            </rom>
+            <switches default="01"><dip bits="0" name="Test" ids="Off,On"/></switches>
+        </misterromdescription>"#,
+    )
+    .unwrap();
+
+    assert_eq!(mra.name, "Test Game");
+    assert_eq!(mra.game_name(), "test");
+    assert_eq!(mra.rbf.as_deref(), Some("testcore"));
+    assert!(mra.switches.is_some());
+
+    let rom = &mra.roms[0];
+    assert_eq!(rom.index, 1);
+    assert_eq!(rom.zips, ["test.zip", "parent.zip"]);
+    assert_eq!(
+        rom.parts[0],
+        MraPart::File {
+            file: MraFile {
+                name: "a.bin".to_string(),
+                zips: vec![],
+                crc: Some(0xABCD),
+            },
+            offset: 0x10,
+            length: Some(16),
+            repeat: 1,
+        }
+    );
+    assert_eq!(
+        rom.parts[1],
+        MraPart::Data {
+            data: vec![1, 2],
+            repeat: 2,
+        }
+    );
+    let MraPart::Interleave { output, parts } = &rom.parts[2] else {
+        panic!("Expected interleave");
+    };
+    assert_eq!(*output, 2);
+    assert_eq!(parts[0].1, [0, 1]);
+    assert_eq!(parts[1].1, [1, 0]);
+}
+
+#[test]
+fn interleave_words() {
+    let hi = vec![0xA1, 0xA2];
+    let lo = vec![0xB1, 0xB2];
+    let mut rom = Vec::new();
+    interleave(2, &[(hi, &vec![1, 0]), (lo, &vec![0, 1])], &mut rom);
+    assert_eq!(rom, [0xB1, 0xA1, 0xB2, 0xA2]);
+}
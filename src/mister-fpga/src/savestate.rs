@@ -1,5 +1,5 @@
 use crate::config_string::Config;
-use cyclone_v::memory::{DevMemMemoryMapper, MemoryMapper};
+use cyclone_v::memory::MemoryMapper;
 use one_fpga::core::Error;
 use std::io::{Read, Write};
 use std::ptr::NonNull;
@@ -19,7 +19,7 @@ pub struct SaveStateManager<M: MemoryMapper> {
     slots: Vec<SaveState>,
 }
 
-impl SaveStateManager<DevMemMemoryMapper> {
+impl<M: MemoryMapper> SaveStateManager<M> {
     pub fn from_config_string(config: &Config) -> Option<Self> {
         let (base, size) = config.settings().save_state?;
         let nb_slots = DEFAULT_MISTER_SAVESTATE_SLOTS;
@@ -29,8 +29,7 @@ impl SaveStateManager<DevMemMemoryMapper> {
         //   0x04: u32 size                 Size of the savestate, in 32-bits words.
         //   0x08..0x08 + (size * 4)        The savestate data.
 
-        let mut memory =
-            DevMemMemoryMapper::create(base.as_usize(), size * (nb_slots as usize)).unwrap();
+        let mut memory = M::create(base.as_usize(), size * (nb_slots as usize)).unwrap();
 
         let slots = (0..nb_slots)
             .map(|i| {
@@ -45,9 +44,7 @@ impl SaveStateManager<DevMemMemoryMapper> {
             slots,
         })
     }
-}
 
-impl<M: MemoryMapper> SaveStateManager<M> {
     #[inline]
     pub fn slots(&self) -> &[SaveState] {
         &self.slots[..(self.nb_slots as usize)]
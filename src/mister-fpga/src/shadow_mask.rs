@@ -0,0 +1,151 @@
+//! Shadow masks of the HDMI scaler, to look like a CRT.
+//!
+//! Masks are MiSTer definition files, in `shadow_masks/`. `#` starts a comment.
+//! The first line is the size of the mask, `width,height` (up to 16x16), and is
+//! followed by a line of `width` comma separated hexadecimal values per row.
+//!
+//! Version 1 masks have 3 bits values, one per color (red, green and blue) to
+//! enable. Files starting with a `v2` line have 12 bits values, 4 bits per
+//! color (red first), where `8` keeps the brightness of a color and higher
+//! values brighten it.
+use std::path::Path;
+
+/// The root directory of the shadow masks.
+pub const SHADOW_MASKS_ROOT: &str = "/media/fat/shadow_masks";
+
+/// The maximum width and height of a shadow mask.
+pub const MAX_SHADOW_MASK_SIZE: u8 = 16;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::FromRepr, strum::EnumString)]
+#[repr(u8)]
+pub enum ShadowMaskMode {
+    #[default]
+    #[strum(serialize = "off")]
+    Off = 0,
+
+    #[strum(serialize = "on")]
+    On = 1,
+
+    /// Each pixel of the mask covers 2x2 pixels, for high resolutions.
+    #[strum(serialize = "2x")]
+    Double = 2,
+}
+
+impl ShadowMaskMode {
+    pub const ALL: [ShadowMaskMode; 3] = [
+        ShadowMaskMode::Off,
+        ShadowMaskMode::On,
+        ShadowMaskMode::Double,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShadowMaskMode::Off => "Off",
+            ShadowMaskMode::On => "On",
+            ShadowMaskMode::Double => "2x",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowMask {
+    width: u8,
+    height: u8,
+    pixels: Vec<u16>,
+}
+
+impl ShadowMask {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut lines = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .peekable();
+
+        let v2 = lines
+            .next_if(|line| line.eq_ignore_ascii_case("v2"))
+            .is_some();
+        let size = lines.next().ok_or("Empty shadow mask")?;
+        let (width, height) = size
+            .split_once(',')
+            .and_then(|(w, h)| Some((w.trim().parse::<u8>().ok()?, h.trim().parse::<u8>().ok()?)))
+            .filter(|(w, h)| {
+                (1..=MAX_SHADOW_MASK_SIZE).contains(w) && (1..=MAX_SHADOW_MASK_SIZE).contains(h)
+            })
+            .ok_or_else(|| format!("Invalid shadow mask size {size:?}"))?;
+
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for line in lines {
+            let row = line
+                .split(',')
+                .map(|v| {
+                    let v = v.trim();
+                    let hex = v
+                        .strip_prefix("0x")
+                        .or_else(|| v.strip_prefix("0X"))
+                        .unwrap_or(v);
+                    u16::from_str_radix(hex, 16)
+                        .map_err(|e| format!("Invalid shadow mask value {v:?}: {e}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if row.len() != width as usize {
+                return Err(format!(
+                    "Shadow mask row {line:?} does not have {width} values"
+                ));
+            }
+
+            for value in row {
+                pixels.push(if v2 {
+                    value & 0xFFF
+                } else {
+                    // Convert to version 2, keeping the brightness of enabled colors.
+                    (value & 0b100) << 9 | (value & 0b010) << 6 | (value & 0b001) << 3
+                });
+            }
+        }
+
+        if pixels.len() != width as usize * height as usize {
+            return Err(format!("Shadow mask does not have {height} rows"));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::parse(&std::fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// The version 2 value of each pixel, row by row.
+    pub fn pixels(&self) -> &[u16] {
+        &self.pixels
+    }
+}
+
+#[test]
+fn shadow_mask_v1() {
+    let mask = ShadowMask::parse("# Aperture grille\n3,2\n4,2,1\n4,2,1\n").unwrap();
+    assert_eq!((mask.width(), mask.height()), (3, 2));
+    assert_eq!(mask.pixels(), [0x800, 0x080, 0x008, 0x800, 0x080, 0x008]);
+}
+
+#[test]
+fn shadow_mask_v2() {
+    let mask = ShadowMask::parse("v2\n2,1 # Size\n0x0C8C, 0x8C8\n").unwrap();
+    assert_eq!(mask.pixels(), [0xC8C, 0x8C8]);
+
+    assert!(ShadowMask::parse("2,2\n1,2\n").is_err());
+    assert!(ShadowMask::parse("2,1\n1,2,3\n").is_err());
+    assert!(ShadowMask::parse("17,1\n").is_err());
+    assert!(ShadowMask::parse("v2\n").is_err());
+}
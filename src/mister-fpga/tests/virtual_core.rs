@@ -0,0 +1,742 @@
+use cyclone_v::memory::BufferMemoryMapper;
+use mister_fpga::cheats::Cheats;
+use mister_fpga::config::edid::{DefaultVideoMode, VideoModeDef};
+use mister_fpga::config::VsyncAdjustConfig;
+use mister_fpga::core::file::SdCard;
+use mister_fpga::core::MisterFpgaCore;
+use mister_fpga::dip::DipSwitches;
+use mister_fpga::filters::{FilterKind, Filters};
+use mister_fpga::fpga::fake::{
+    VirtualCore, VirtualFile, VirtualSdImage, VirtualSdSector, VirtualVideo,
+};
+use mister_fpga::fpga::CoreInterfaceType;
+use mister_fpga::mra::{Mra, MraError};
+use mister_fpga::types::StatusBitMap;
+use one_fpga::core::{Bios, CoreSettingItem, Rom};
+use one_fpga::inputs::gamepad::ButtonSet;
+use one_fpga::inputs::keyboard::ScancodeSet;
+use one_fpga::inputs::{mouse, Axis, Button, Scancode};
+use one_fpga::Core;
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+const CONFIG_STRING: &str =
+    "TEST;;F1,BIN,Load Game;S0,DSK,Mount Disk;O1,Option,Off,On;R0,Reset;V,v1";
+
+fn virtual_core(interface_type: CoreInterfaceType) -> VirtualCore {
+    VirtualCore::new(CONFIG_STRING).with_interface_type(interface_type)
+}
+
+/// Create a `MisterFpgaCore` talking to a virtual core.
+fn connect(vcore: VirtualCore) -> (VirtualCore, MisterFpgaCore<BufferMemoryMapper>) {
+    let core = MisterFpgaCore::new(vcore.fpga()).unwrap();
+    (vcore, core)
+}
+
+#[rstest]
+fn new_reads_core_info(
+    #[values(CoreInterfaceType::SpiBus8Bit, CoreInterfaceType::SpiBus16Bit)]
+    interface_type: CoreInterfaceType,
+) {
+    let wide = interface_type.is_wide();
+    let (_, core) = connect(virtual_core(interface_type).with_io_version(2));
+
+    assert_eq!(core.name(), "TEST");
+    assert_eq!(core.spi_type.is_wide(), wide);
+    assert_eq!(core.io_version, 2);
+}
+
+#[rstest]
+fn load_file(
+    #[values(CoreInterfaceType::SpiBus8Bit, CoreInterfaceType::SpiBus16Bit)]
+    interface_type: CoreInterfaceType,
+) {
+    let (vcore, mut core) = connect(virtual_core(interface_type));
+
+    let dir = tempdir::TempDir::new("virtual_core").unwrap();
+    let path = dir.path().join("game.bin");
+    let data = (0..10_000u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+    std::fs::write(&path, &data).unwrap();
+
+    core.load_file(&path, None).unwrap();
+    assert_eq!(
+        vcore.files(),
+        [VirtualFile {
+            index: 1,
+            extension: "BIN".to_string(),
+            size: Some(10_000),
+            data: data.clone(),
+            complete: false,
+        }]
+    );
+
+    core.end_send_file().unwrap();
+    assert!(vcore.files()[0].complete);
+}
+
+#[rstest]
+fn send_rom_from_memory(
+    #[values(CoreInterfaceType::SpiBus8Bit, CoreInterfaceType::SpiBus16Bit)]
+    interface_type: CoreInterfaceType,
+    #[values(None, Some("game.bin"))] path: Option<&str>,
+) {
+    let (vcore, mut core) = connect(virtual_core(interface_type));
+
+    let data = (0..3000u32).map(|i| (i * 13) as u8).collect::<Vec<_>>();
+    core.send_rom(Rom::Memory(
+        path.map(PathBuf::from),
+        Cursor::new(data.clone()),
+    ))
+    .unwrap();
+
+    assert_eq!(
+        vcore.files(),
+        [VirtualFile {
+            index: 1,
+            extension: "BIN".to_string(),
+            size: Some(3000),
+            data,
+            complete: false,
+        }]
+    );
+}
+
+#[test]
+fn send_rom_from_zip() {
+    let dir = tempdir::TempDir::new("send_rom_from_zip").unwrap();
+    let data = (0..5000u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+
+    let archive = std::fs::File::create(dir.path().join("roms.zip")).unwrap();
+    let mut zip = zip::ZipWriter::new(archive);
+    zip.start_file("readme.txt", Default::default()).unwrap();
+    zip.write_all(b"Not a ROM").unwrap();
+    zip.start_file("game.bin", Default::default()).unwrap();
+    zip.write_all(&data).unwrap();
+    zip.finish().unwrap();
+
+    let rom = Rom::from_path(dir.path().join("roms.zip/game.bin"));
+    assert!(matches!(rom, Rom::Archive(_)));
+
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    core.send_rom(rom).unwrap();
+
+    assert_eq!(
+        vcore.files(),
+        [VirtualFile {
+            index: 1,
+            extension: "BIN".to_string(),
+            size: Some(5000),
+            data,
+            complete: false,
+        }]
+    );
+}
+
+#[test]
+fn send_rom_from_zip_to_sdram() {
+    let dir = tempdir::TempDir::new("send_rom_from_zip_to_sdram").unwrap();
+    let data = (0..200_000u32)
+        .map(|i| (i * 7 / 3) as u8)
+        .collect::<Vec<_>>();
+
+    let archive = std::fs::File::create(dir.path().join("roms.zip")).unwrap();
+    let mut zip = zip::ZipWriter::new(archive);
+    zip.start_file("game.rom", Default::default()).unwrap();
+    zip.write_all(&data).unwrap();
+    zip.finish().unwrap();
+
+    let (vcore, mut core) = connect(
+        VirtualCore::new("TEST;;F2,ROM,Load ROM,30000000;V,v1")
+            .with_interface_type(CoreInterfaceType::SpiBus16Bit),
+    );
+    core.send_rom(Rom::from_path(dir.path().join("roms.zip/game.rom")))
+        .unwrap();
+
+    // The data goes to memory, the core only sees the transfer.
+    assert_eq!(
+        vcore.files(),
+        [VirtualFile {
+            index: 2,
+            extension: "ROM".to_string(),
+            size: Some(200_000),
+            data: Vec::new(),
+            complete: false,
+        }]
+    );
+}
+
+#[test]
+fn send_bios_before_rom() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+
+    let bios = vec![0xB1; 1024];
+    core.send_bios(Bios::Memory(None, Cursor::new(bios.clone())).with_index(0))
+        .unwrap();
+    core.send_rom(Rom::Memory(
+        Some(PathBuf::from("game.bin")),
+        Cursor::new(vec![0x42; 16]),
+    ))
+    .unwrap();
+
+    assert_eq!(
+        vcore.files(),
+        [
+            VirtualFile {
+                index: 0,
+                extension: "".to_string(),
+                size: Some(1024),
+                data: bios,
+                complete: true,
+            },
+            VirtualFile {
+                index: 1,
+                extension: "BIN".to_string(),
+                size: Some(16),
+                data: vec![0x42; 16],
+                complete: false,
+            }
+        ]
+    );
+}
+
+#[test]
+fn send_bios_file_twice() {
+    let dir = tempdir::TempDir::new("bios").unwrap();
+    let path = dir.path().join("boot.rom");
+    let data = (0..1500u32).map(|i| i as u8).collect::<Vec<_>>();
+    std::fs::write(&path, &data).unwrap();
+
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    let bios = Bios::from_path(&path).unwrap().with_index(0);
+    core.send_bios(bios.clone()).unwrap();
+    core.send_bios(bios).unwrap();
+
+    let file = VirtualFile {
+        index: 0,
+        extension: "ROM".to_string(),
+        size: Some(1500),
+        data,
+        complete: true,
+    };
+    assert_eq!(vcore.files(), [file.clone(), file]);
+}
+
+#[rstest]
+fn mount_and_poll(
+    #[values(CoreInterfaceType::SpiBus8Bit, CoreInterfaceType::SpiBus16Bit)]
+    interface_type: CoreInterfaceType,
+) {
+    let (vcore, mut core) = connect(virtual_core(interface_type));
+
+    let image = (0..2048u32).map(|i| (i / 3) as u8).collect::<Vec<_>>();
+    core.mount(SdCard::from_memory(image.clone()), 0).unwrap();
+    assert_eq!(
+        vcore.mounted(0),
+        Some(VirtualSdImage {
+            size: 2048,
+            writable: true
+        })
+    );
+
+    // Nothing requested.
+    assert!(!core.poll_mounts().unwrap());
+
+    vcore.request_sd_read(0, 2);
+    assert!(core.poll_mounts().unwrap());
+    assert!(!vcore.has_pending_sd_requests());
+    assert_eq!(
+        vcore.sectors_read(),
+        [VirtualSdSector {
+            disk: 0,
+            lba: 2,
+            data: image[1024..1536].to_vec(),
+        }]
+    );
+
+    vcore.request_sd_write(0, 1, &[0xA5; 512]);
+    assert!(core.poll_mounts().unwrap());
+    assert!(!vcore.has_pending_sd_requests());
+
+    let mut written = vec![0; 2048];
+    let file = core.mounted_file_mut(0).unwrap().unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_exact(&mut written).unwrap();
+    assert_eq!(&written[..512], &image[..512]);
+    assert_eq!(&written[512..1024], &[0xA5; 512]);
+    assert_eq!(&written[1024..], &image[1024..]);
+}
+
+#[test]
+fn status_bits() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus8Bit));
+
+    let mut bits = StatusBitMap::new();
+    bits.set(1, true);
+    core.send_status_bits(bits);
+    assert_eq!(vcore.status_bits(), bits);
+
+    // The core changes its own status.
+    bits.set(40, true);
+    vcore.set_status_bits(bits);
+    assert!(core.read_status_bits().get(40));
+    assert!(core.read_status_bits().get(1));
+}
+
+#[test]
+fn video_info() {
+    let (_, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus8Bit));
+
+    let info = core.video_info().unwrap();
+    assert_eq!(info.resolution().width, 256);
+    assert_eq!(info.resolution().height, 240);
+    assert_eq!(info.vtime().as_micros(), 16_639);
+}
+
+#[test]
+fn pal_ntsc_switch() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus8Bit));
+
+    // Without PAL and NTSC modes, nothing is switched.
+    assert!(!core.poll_video().unwrap());
+
+    let modes = VideoModeDef {
+        vmode_def: None,
+        vmode_pal: Some(DefaultVideoMode::V1920x1080r50.into()),
+        vmode_ntsc: Some(DefaultVideoMode::V1920x1080r60.into()),
+    };
+    // Use direct video so no HDMI transmitter is needed.
+    core.set_video_modes(Some(modes), true);
+    assert!(core.poll_video().unwrap());
+    assert!(!core.poll_video().unwrap());
+
+    // The core switches to 50Hz.
+    vcore.set_video(VirtualVideo {
+        vtime: 2_000_000,
+        ..VirtualVideo::default()
+    });
+    assert!(core.poll_video().unwrap());
+    assert!(!core.poll_video().unwrap());
+    assert_eq!(core.video_info().unwrap().vtime().as_millis(), 20);
+}
+
+#[test]
+fn vsync_adjust() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus8Bit));
+
+    let modes = VideoModeDef {
+        vmode_def: Some(DefaultVideoMode::V1920x1080r60.into()),
+        vmode_pal: None,
+        vmode_ntsc: None,
+    };
+    core.set_video_modes(Some(modes), true);
+    assert!(!core.poll_video().unwrap());
+
+    // The output refresh rate matches the core (~60.1Hz).
+    core.set_vsync_adjust(VsyncAdjustConfig::Automatic, 0.0..=f32::MAX);
+    assert!(core.poll_video().unwrap());
+    let rate = core.video_mode().unwrap().frame_rate();
+    assert!((rate - 100_000_000. / 1_663_920.).abs() < 0.001, "{rate}");
+
+    // The same timings don't change the mode.
+    vcore.set_video(VirtualVideo::default());
+    assert!(!core.poll_video().unwrap());
+
+    assert!(!core.video_mode().unwrap().low_latency);
+
+    // The low latency mode uses a single buffer, sent on the first PLL word.
+    core.set_vsync_adjust(VsyncAdjustConfig::LowLatency, 55.0..=65.0);
+    vcore.bridge().clear();
+    assert!(core.poll_video().unwrap());
+    assert!(core.video_mode().unwrap().low_latency);
+    let first_pll_words = |vcore: &VirtualCore| {
+        vcore
+            .bridge()
+            .transactions()
+            .into_iter()
+            .filter(|t| t.words[0] == 0x20)
+            .map(|t| t.words[9])
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(first_pll_words(&vcore), [0x8004]);
+
+    // Refresh rates out of range keep the original mode, with double buffering.
+    vcore.bridge().clear();
+    vcore.set_video(VirtualVideo {
+        vtime: 2_000_000,
+        ..VirtualVideo::default()
+    });
+    assert!(core.poll_video().unwrap());
+    let rate = core.video_mode().unwrap().frame_rate();
+    assert!((rate - 60.).abs() < 0.001, "{rate}");
+    assert!(!core.video_mode().unwrap().low_latency);
+    assert_eq!(first_pll_words(&vcore), [4]);
+}
+
+#[rstest]
+fn filters(#[values(1, 2, 5)] version: u16) {
+    let dir = tempdir::TempDir::new("filters").unwrap();
+    let video = dir.path().join("filters");
+    let audio = dir.path().join("filters_audio");
+    std::fs::create_dir_all(&video).unwrap();
+    std::fs::create_dir_all(&audio).unwrap();
+    std::fs::write(video.join("Sharp.txt"), "-2, 132, -2, 0\n".repeat(16)).unwrap();
+    std::fs::write(audio.join("Flat.txt"), "0.5\n1\n0\n0\n0\n0\n0\n").unwrap();
+
+    let (vcore, mut core) =
+        connect(virtual_core(CoreInterfaceType::SpiBus16Bit).with_filter_version(version));
+    let path = dir.path().join("TEST.txt");
+    core.load_filters(Filters::scan(&video, &audio), Some(path.clone()))
+        .unwrap();
+    assert_eq!(core.filters().selected(FilterKind::Horizontal), None);
+
+    // Select the filters from the settings.
+    vcore.bridge().clear();
+    let id = FilterKind::Horizontal.setting_id();
+    assert_eq!(core.int_option(id, 1).unwrap(), 1);
+    let id = FilterKind::Audio.setting_id();
+    assert_eq!(core.int_option(id, 1).unwrap(), 1);
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("horizontal=Sharp"));
+
+    let transactions = vcore.bridge().transactions();
+    let words = |command: u16| {
+        transactions
+            .iter()
+            .rfind(|t| t.words[0] == command)
+            .map(|t| t.words[1..].to_vec())
+            .unwrap_or_default()
+    };
+
+    // Vertical and scanline filters are the same as horizontal.
+    let (filters, phases) = match version {
+        1 => (2, 16),
+        2 => (3, 16),
+        _ => (2, 64),
+    };
+    let coefficients = words(0x2A);
+    assert_eq!(coefficients.len(), filters * phases * 4 * 2);
+    let taps = if version & 4 != 0 {
+        [0x3FE, 132]
+    } else {
+        [0x3FF, 66]
+    };
+    assert_eq!(coefficients[..4], [0, taps[0], 1, taps[1]]);
+
+    assert_eq!(words(0x39), [0, 0, 0x80, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn filters_unsupported() {
+    let dir = tempdir::TempDir::new("filters").unwrap();
+    let video = dir.path().join("filters");
+    std::fs::create_dir_all(&video).unwrap();
+    std::fs::write(video.join("Sharp.txt"), "-2, 132, -2, 0\n".repeat(16)).unwrap();
+
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    core.load_filters(
+        Filters::scan(&video, dir.path().join("filters_audio")),
+        None,
+    )
+    .unwrap();
+
+    // Without an audio filter, a flat one is sent.
+    let audio = vcore
+        .bridge()
+        .transactions()
+        .into_iter()
+        .find(|t| t.words[0] == 0x39)
+        .map(|t| t.words[1..].to_vec());
+    assert_eq!(
+        audio,
+        Some(vec![0xFFFF, 0xFFFF, 0xFF, 1, 0, 0, 0, 0, 0, 0, 0, 0])
+    );
+
+    // The core has no polyphase filter.
+    let id = FilterKind::Horizontal.setting_id();
+    assert!(core.int_option(id, 1).is_err());
+}
+
+#[test]
+fn shadow_mask() {
+    let dir = tempdir::TempDir::new("shadow_masks").unwrap();
+    let masks = dir.path().join("shadow_masks");
+    std::fs::create_dir_all(&masks).unwrap();
+    std::fs::write(masks.join("Grille.txt"), "2,1\n4,1\n").unwrap();
+
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    let path = dir.path().join("TEST.txt");
+    let filters = Filters::scan(dir.path().join("filters"), dir.path().join("filters_audio"))
+        .with_shadow_masks(&masks);
+    core.load_filters(filters, Some(path.clone())).unwrap();
+
+    let mask_words = |vcore: &VirtualCore| {
+        vcore
+            .bridge()
+            .transactions()
+            .into_iter()
+            .filter(|t| t.words[0] == 0x3E)
+            .map(|t| t.words[1..].to_vec())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(mask_words(&vcore), [vec![0]]);
+
+    vcore.bridge().clear();
+    assert!(core
+        .update_filters(|f| f.select(FilterKind::ShadowMask, Some("Grille")))
+        .unwrap());
+    assert_eq!(
+        mask_words(&vcore),
+        [vec![0, 0x2000, 0x4001, 0x6800, 0x6008]]
+    );
+
+    vcore.bridge().clear();
+    let id = Filters::shadow_mask_mode_id();
+    assert_eq!(core.int_option(id, 2).unwrap(), 2);
+    assert_eq!(
+        mask_words(&vcore),
+        [vec![3, 0x2000, 0x4001, 0x6800, 0x6008]]
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "horizontal=\nvertical=\nscanlines=\naudio=\nshadow_mask=Grille\nshadow_mask_mode=2\n"
+    );
+}
+
+#[test]
+fn mouse_throttle() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    core.set_mouse_throttle(2);
+    vcore.bridge().clear();
+
+    core.mouse_move(3, -5);
+    core.mouse_move(1, 0);
+    core.mouse_button(mouse::Button::Left, true);
+
+    let words = vcore
+        .bridge()
+        .transactions()
+        .into_iter()
+        .map(|t| t.words)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        [
+            vec![0x04, 0x08, 0x01, 0x02],
+            vec![0x04, 0x08, 0x01, 0x00],
+            vec![0x04, 0x09, 0x00, 0x00],
+        ]
+    );
+}
+
+#[test]
+fn keys_set() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    let a: Scancode = "A".parse().unwrap();
+    let b: Scancode = "B".parse().unwrap();
+    vcore.bridge().clear();
+
+    let mut keys = ScancodeSet::new();
+    keys.insert(a);
+    Core::key_down(&mut core, b).unwrap();
+    Core::keys_set(&mut core, keys.clone()).unwrap();
+    assert_eq!(Core::keys(&core).unwrap(), keys);
+
+    // Setting the same keys again sends nothing.
+    Core::keys_set(&mut core, keys).unwrap();
+
+    let words = vcore
+        .bridge()
+        .transactions()
+        .into_iter()
+        .map(|t| t.words)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        [vec![0x05, 0x32], vec![0x05, 0xF0, 0x32], vec![0x05, 0x1C],]
+    );
+}
+
+#[test]
+fn gamepad_buttons_set() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    vcore.bridge().clear();
+
+    let mut buttons = ButtonSet::new();
+    buttons.insert(Button::A);
+    buttons.insert(Button::Start);
+    core.gamepad_buttons_set(1, buttons).unwrap();
+    assert_eq!(core.gamepad_buttons(1).unwrap(), Some(buttons));
+    assert_eq!(core.gamepad_buttons(0).unwrap(), Some(ButtonSet::new()));
+    assert_eq!(core.gamepad_buttons(6).unwrap(), None);
+    assert!(core.gamepad_buttons_set(6, buttons).is_err());
+
+    // Setting the same buttons again sends nothing.
+    core.gamepad_buttons_set(1, buttons).unwrap();
+    core.gamepad_buttons_set(1, ButtonSet::new()).unwrap();
+
+    let transactions = vcore.bridge().transactions();
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[0].command(), Some(0x03));
+    assert_ne!(transactions[0].data(), [0x0000]);
+    assert_eq!(transactions[1].words, [0x03, 0x0000]);
+}
+
+#[test]
+fn axis_motion_invalid_index() {
+    let (vcore, mut core) = connect(virtual_core(CoreInterfaceType::SpiBus16Bit));
+    vcore.bridge().clear();
+
+    assert!(core.axis_motion(6, Axis::LEFT_X, i16::MAX).is_err());
+    // Large indices are not truncated to another gamepad.
+    assert!(core.axis_motion(256, Axis::LEFT_X, i16::MAX).is_err());
+    assert!(vcore.bridge().transactions().is_empty());
+
+    core.axis_motion(0, Axis::LEFT_X, i16::MAX).unwrap();
+    assert_eq!(vcore.bridge().transactions().len(), 1);
+}
+
+#[test]
+fn cheats() {
+    let dir = tempdir::TempDir::new("cheats").unwrap();
+    let path = dir.path().join("game.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    zip.start_file("Infinite Lives.gg", Default::default())
+        .unwrap();
+    zip.write_all(&[0x11; 16]).unwrap();
+    zip.start_file("Invalid.gg", Default::default()).unwrap();
+    zip.write_all(&[0x22; 3]).unwrap();
+    zip.finish().unwrap();
+
+    let (vcore, mut core) = connect(VirtualCore::new("TEST;;F1,BIN,Load Game;C,Cheats;V,v1"));
+    assert!(core.supports_cheats());
+
+    core.load_cheats(Cheats::from_zip(&path).unwrap()).unwrap();
+    let cheat = core.cheats().iter().next().unwrap().clone();
+    assert_eq!(core.cheats().iter().count(), 1);
+    assert_eq!(cheat.name(), "Infinite Lives");
+
+    let settings = core.settings().unwrap();
+    let page = settings
+        .items()
+        .iter()
+        .find(|item| matches!(item, CoreSettingItem::Page { label, .. } if label == "Cheats"))
+        .unwrap();
+    assert_eq!(page.items().map(Vec::len), Some(1));
+
+    assert!(core.bool_option(cheat.setting_id(), true).unwrap());
+    let files = vcore.files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].data, vec![0; 2]);
+    assert_eq!(
+        files[1],
+        VirtualFile {
+            index: 255,
+            extension: "".to_string(),
+            size: Some(16),
+            data: vec![0x11; 16],
+            complete: true,
+        }
+    );
+}
+
+#[test]
+fn dip_switches() {
+    let dir = tempdir::TempDir::new("dip_switches").unwrap();
+    let path = dir.path().join("dips/game.dip");
+    let dips = DipSwitches::from_mra(
+        r#"<misterromdescription>
+            <switches default="01">
+                <dip bits="0,1" name="Lives" ids="1,2,3,4"/>
+            </switches>
+        </misterromdescription>"#,
+    )
+    .unwrap()
+    .unwrap();
+    let lives = dips.switches()[0].setting_id();
+
+    let (vcore, mut core) = connect(VirtualCore::new("TEST;;DIP;V,v1"));
+    core.load_dip_switches(dips.clone(), Some(path.clone()))
+        .unwrap();
+    assert_eq!(core.int_option(lives, 3).unwrap(), 3);
+
+    let files = vcore.files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].data, [1, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        files[1],
+        VirtualFile {
+            index: 254,
+            extension: "".to_string(),
+            size: Some(8),
+            data: vec![3, 0, 0, 0, 0, 0, 0, 0],
+            complete: true,
+        }
+    );
+
+    // The value is saved for the next launch.
+    core.load_dip_switches(dips, Some(path)).unwrap();
+    assert_eq!(core.dip_switches().unwrap().value(), 3);
+}
+
+#[test]
+fn load_mra() {
+    let dir = tempdir::TempDir::new("load_mra").unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("_Arcade/cores")).unwrap();
+    std::fs::write(root.join("_Arcade/cores/testcore_20240101.rbf"), b"").unwrap();
+    std::fs::write(root.join("_Arcade/cores/testcore_20240301.rbf"), b"").unwrap();
+
+    let archive = std::fs::File::create(root.join("_Arcade/test.zip")).unwrap();
+    let mut zip = zip::ZipWriter::new(archive);
+    zip.start_file("a.bin", Default::default()).unwrap();
+    zip.write_all(&[1, 2, 3, 4]).unwrap();
+    zip.finish().unwrap();
+
+    let mra_path = root.join("_Arcade/Test Game.mra");
+    std::fs::write(
+        &mra_path,
+        r#"<misterromdescription>
+            <name>Test Game</name>
+            <setname>test</setname>
+            <rbf>testcore</rbf>
+            <rom index="0" zip="test.zip">
+                <part name="a.bin" offset="1" length="2"/>
+                <part>FF</part>
+            </rom>
+            <rom index="1" zip="test.zip">
+                <part name="missing.bin"/>
+            </rom>
+        </misterromdescription>"#,
+    )
+    .unwrap();
+
+    let mut mra = Mra::from_path(&mra_path).unwrap();
+    assert_eq!(
+        mra.find_rbf(&mra_path).unwrap(),
+        root.join("_Arcade/cores/testcore_20240301.rbf")
+    );
+    assert!(matches!(
+        mra.roms[1].assemble(&[root.join("_Arcade")]),
+        Err(MraError::Parts { index: 1, .. })
+    ));
+
+    let (_, mut core) = connect(VirtualCore::new("TEST;;V,v1"));
+    assert!(core.load_mra(&mra, &mra_path).is_err());
+
+    mra.roms.pop();
+    let (vcore, mut core) = connect(VirtualCore::new("TEST;;V,v1"));
+    core.load_mra(&mra, &mra_path).unwrap();
+    assert_eq!(
+        vcore.files(),
+        [VirtualFile {
+            index: 0,
+            extension: "".to_string(),
+            size: Some(3),
+            data: vec![2, 3, 0xFF],
+            complete: true,
+        }]
+    );
+}
@@ -0,0 +1,103 @@
+//! Files inside archives. A member of an archive is addressed by a path that
+//! goes through the archive file, e.g. `/media/fat/games/NES/roms.zip/Game.nes`.
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Extensions of archives that can be read.
+pub const ARCHIVE_EXTENSIONS: &[&str] = &["zip"];
+
+fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ARCHIVE_EXTENSIONS.iter().any(|a| a.eq_ignore_ascii_case(e)))
+}
+
+/// A path to a file inside an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchivePath {
+    archive: PathBuf,
+    member: String,
+}
+
+impl ArchivePath {
+    pub fn new(archive: impl Into<PathBuf>, member: impl Into<String>) -> Self {
+        Self {
+            archive: archive.into(),
+            member: member.into(),
+        }
+    }
+
+    /// Split a path going through an archive file into the archive and the
+    /// member. Returns `None` if the path does not go through an archive that
+    /// exists on the file system.
+    pub fn parse(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let archive = path
+            .ancestors()
+            .skip(1)
+            .find(|p| is_archive(p) && p.is_file())?;
+        let member = path.strip_prefix(archive).ok()?;
+
+        // Members always use forward slashes.
+        let member = member
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some(Self::new(archive, member))
+    }
+
+    pub fn archive(&self) -> &Path {
+        &self.archive
+    }
+
+    pub fn member(&self) -> &str {
+        &self.member
+    }
+
+    /// The extension of the member (not of the archive).
+    pub fn extension(&self) -> Option<&str> {
+        Path::new(&self.member).extension().and_then(|e| e.to_str())
+    }
+
+    fn open(&self) -> io::Result<zip::ZipArchive<File>> {
+        let file = File::open(&self.archive)?;
+        zip::ZipArchive::new(file).map_err(io::Error::other)
+    }
+
+    /// The uncompressed size of the member.
+    pub fn size(&self) -> io::Result<u64> {
+        let mut archive = self.open()?;
+        let file = archive.by_name(&self.member).map_err(io::Error::other)?;
+        Ok(file.size())
+    }
+
+    /// Open the member, and call `f` with its uncompressed size and a reader
+    /// of its content. The content is decompressed as it is read.
+    pub fn with_reader<T>(&self, f: impl FnOnce(u64, &mut dyn Read) -> T) -> io::Result<T> {
+        let mut archive = self.open()?;
+        let mut file = archive.by_name(&self.member).map_err(io::Error::other)?;
+        Ok(f(file.size(), &mut file))
+    }
+
+    /// Read the whole member in memory.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        self.with_reader(|size, reader| {
+            let mut data = Vec::with_capacity(size as usize);
+            reader.read_to_end(&mut data).map(|_| data)
+        })?
+    }
+
+    /// List the files in an archive.
+    pub fn list(archive: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        let archive = archive.as_ref();
+        let zip = Self::new(archive, "").open()?;
+        Ok(zip
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(|name| Self::new(archive, name))
+            .collect())
+    }
+}
@@ -10,7 +10,7 @@ pub use null::NullCore;
 pub use rom::Rom;
 use serde::Serialize;
 
-use crate::inputs::{gamepad, keyboard};
+use crate::inputs::{gamepad, keyboard, mouse};
 
 pub mod bios;
 pub mod null;
@@ -402,8 +402,8 @@ pub trait Core {
     fn send_rom(&mut self, rom: Rom) -> Result<(), Error>;
 
     /// Load a BIOS into the core. For cores that support multiple BIOS, the BIOS should be
-    /// selected based on the core's configuration and the slot information should be part
-    /// of [`&dyn Bios`].
+    /// selected based on the core's configuration, using the extension of the BIOS path or
+    /// its explicit index (see [`Bios::with_index`]).
     fn send_bios(&mut self, bios: Bios) -> Result<(), Error>;
 
     /// Send a key up event to the core.
@@ -434,6 +434,18 @@ pub trait Core {
     /// return `None`.
     fn gamepad_buttons(&self, index: usize) -> Result<Option<gamepad::ButtonSet>, Error>;
 
+    /// Send the position of a gamepad axis to the core. Sticks go from -32768 to
+    /// 32767, triggers from 0 to 32767 (same as SDL). Cores that don't support
+    /// analog inputs should ignore this.
+    fn axis_motion(&mut self, index: usize, axis: gamepad::Axis, value: i16) -> Result<(), Error>;
+
+    /// Send a relative mouse movement to the core. Positive values go right and
+    /// down (same as SDL).
+    fn mouse_move(&mut self, dx: i32, dy: i32) -> Result<(), Error>;
+
+    /// Send a mouse button event to the core.
+    fn mouse_button(&mut self, button: mouse::Button, pressed: bool) -> Result<(), Error>;
+
     /// Returns the menu items that the core supports. This would correspond to the
     /// top level page of config items. If the core does not support a menu, this
     /// should return an empty vector.
@@ -581,6 +593,18 @@ impl Core for OneFpgaCore {
         unsafe { &mut *self.inner.get() }.gamepad_buttons(index)
     }
 
+    fn axis_motion(&mut self, index: usize, axis: gamepad::Axis, value: i16) -> Result<(), Error> {
+        unsafe { &mut *self.inner.get() }.axis_motion(index, axis, value)
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32) -> Result<(), Error> {
+        unsafe { &mut *self.inner.get() }.mouse_move(dx, dy)
+    }
+
+    fn mouse_button(&mut self, button: mouse::Button, pressed: bool) -> Result<(), Error> {
+        unsafe { &mut *self.inner.get() }.mouse_button(button, pressed)
+    }
+
     fn settings(&self) -> Result<CoreSettings, Error> {
         unsafe { &mut *self.inner.get() }.settings()
     }
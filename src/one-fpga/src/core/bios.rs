@@ -1,5 +1,5 @@
 use std::io::{Cursor, Read, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 /// A BIOS, including any information the core needs to know about the BIOS.
@@ -10,6 +10,53 @@ pub enum Bios {
 
     /// A BIOS that is stored in a file.
     File(PathBuf, Arc<std::fs::File>),
+
+    /// A BIOS that is sent to an explicit file index of the core, instead of
+    /// the one matching its extension.
+    Indexed(u8, Box<Bios>),
+}
+
+impl Bios {
+    /// Open a BIOS file.
+    pub fn from_path(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let file = std::fs::File::open(&path)?;
+        Ok(Self::File(path, Arc::new(file)))
+    }
+
+    /// Send this BIOS to an explicit file index.
+    pub fn with_index(self, index: u8) -> Self {
+        match self {
+            Self::Indexed(_, bios) => Self::Indexed(index, bios),
+            bios => Self::Indexed(index, Box::new(bios)),
+        }
+    }
+
+    /// The path of the BIOS, if any. Only its extension is used by cores.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Memory(path, _) => path.as_deref(),
+            Self::File(path, _) => Some(path),
+            Self::Indexed(_, bios) => bios.path(),
+        }
+    }
+
+    /// The explicit file index of the BIOS, if any.
+    pub fn index(&self) -> Option<u8> {
+        match self {
+            Self::Indexed(index, _) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// The size of the BIOS, in bytes.
+    pub fn size(&self) -> std::io::Result<u64> {
+        match self {
+            Self::Memory(_, data) => Ok(data.get_ref().len() as u64),
+            Self::File(_, file) => Ok(file.metadata()?.len()),
+            Self::Indexed(_, bios) => bios.size(),
+        }
+    }
 }
 
 impl Read for Bios {
@@ -17,6 +64,7 @@ impl Read for Bios {
         match self {
             Self::Memory(_, data) => data.read(buf),
             Self::File(_, file) => file.read(buf),
+            Self::Indexed(_, bios) => bios.read(buf),
         }
     }
 }
@@ -26,6 +74,7 @@ impl Seek for Bios {
         match self {
             Self::Memory(_, data) => data.seek(pos),
             Self::File(_, file) => file.seek(pos),
+            Self::Indexed(_, bios) => bios.seek(pos),
         }
     }
 }
@@ -6,7 +6,7 @@ use image::{ColorType, DynamicImage};
 use crate::core::{Bios, CoreSettings, Error, MountedFile, Rom, SaveState, SettingId};
 use crate::inputs::gamepad::ButtonSet;
 use crate::inputs::keyboard::ScancodeSet;
-use crate::inputs::{Button, Scancode};
+use crate::inputs::{mouse, Axis, Button, Scancode};
 use crate::Core;
 
 /// A Core that does nothing.
@@ -93,6 +93,18 @@ impl Core for NullCore {
         Ok(None)
     }
 
+    fn axis_motion(&mut self, _index: usize, _axis: Axis, _value: i16) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn mouse_move(&mut self, _dx: i32, _dy: i32) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn mouse_button(&mut self, _button: mouse::Button, _pressed: bool) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn settings(&self) -> Result<CoreSettings, Error> {
         // TODO: add some basic items.
         Ok(CoreSettings::new("null".to_string(), vec![]))
@@ -1,12 +1,45 @@
 use std::io::Cursor;
 use std::path::PathBuf;
 
+use crate::archive::ArchivePath;
+
 /// A ROM, including any information the core needs to know about the ROM.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum Rom {
     /// A ROM that is stored in memory.
     Memory(Option<PathBuf>, Cursor<Vec<u8>>),
 
     /// A ROM that is stored in a file on the file system.
     File(PathBuf),
+
+    /// A ROM that is a file inside an archive. The extension of the member
+    /// is used by the core, not the extension of the archive.
+    Archive(ArchivePath),
+}
+
+impl Rom {
+    /// Create a ROM from a path on the file system. Paths that go through an
+    /// archive (e.g. `roms.zip/game.nes`) are ROMs inside that archive.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        match ArchivePath::parse(&path) {
+            Some(archive) => Self::Archive(archive),
+            None => Self::File(path),
+        }
+    }
+}
+
+impl std::fmt::Debug for Rom {
+    // Do not print the content of memory ROMs, which can be megabytes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rom::Memory(path, data) => f
+                .debug_struct("Memory")
+                .field("path", path)
+                .field("size", &data.get_ref().len())
+                .finish(),
+            Rom::File(path) => f.debug_tuple("File").field(path).finish(),
+            Rom::Archive(path) => f.debug_tuple("Archive").field(path).finish(),
+        }
+    }
 }
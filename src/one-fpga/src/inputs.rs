@@ -1,5 +1,6 @@
 pub mod gamepad;
 pub mod keyboard;
+pub mod mouse;
 
 pub use gamepad::{Axis, Button};
 pub use keyboard::Scancode;
@@ -137,6 +137,11 @@ impl ButtonSet {
     pub fn remove(&mut self, button: Button) {
         self.0 &= !(1 << button.as_repr());
     }
+
+    /// Iterate over the buttons in the set.
+    pub fn iter(&self) -> impl Iterator<Item = Button> + '_ {
+        Button::iter().filter(|b| self.contains(*b))
+    }
 }
 
 impl std::fmt::Debug for ButtonSet {
@@ -159,12 +164,35 @@ impl std::fmt::Debug for ButtonSet {
 pub struct Axis(sdl3::gamepad::Axis);
 
 impl Axis {
+    pub const LEFT_X: Axis = Axis(sdl3::gamepad::Axis::LeftX);
+    pub const LEFT_Y: Axis = Axis(sdl3::gamepad::Axis::LeftY);
+    pub const RIGHT_X: Axis = Axis(sdl3::gamepad::Axis::RightX);
+    pub const RIGHT_Y: Axis = Axis(sdl3::gamepad::Axis::RightY);
+    pub const TRIGGER_LEFT: Axis = Axis(sdl3::gamepad::Axis::TriggerLeft);
+    pub const TRIGGER_RIGHT: Axis = Axis(sdl3::gamepad::Axis::TriggerRight);
+
     /// Get a string representation of the axis.
     pub fn name(&self) -> String {
         self.0.string()
     }
 }
 
+impl std::fmt::Display for Axis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+impl std::str::FromStr for Axis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        sdl3::gamepad::Axis::from_string(s)
+            .map(Self)
+            .ok_or_else(|| format!("Unknown axis: {s}"))
+    }
+}
+
 impl From<sdl3::gamepad::Axis> for Axis {
     fn from(axis: sdl3::gamepad::Axis) -> Self {
         Self(axis)
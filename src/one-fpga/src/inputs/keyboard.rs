@@ -75,4 +75,9 @@ impl ScancodeSet {
     pub fn remove(&mut self, scancode: Scancode) {
         self.set.remove(&scancode);
     }
+
+    /// Iterate over the scancodes in the set, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = Scancode> + '_ {
+        self.set.iter().copied()
+    }
 }
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+/// Mouse buttons.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize, EnumString, Display)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+impl Button {
+    /// Convert an SDL mouse button. Returns `None` for unknown buttons.
+    pub fn from_sdl(button: sdl3::mouse::MouseButton) -> Option<Self> {
+        match button {
+            sdl3::mouse::MouseButton::Left => Some(Button::Left),
+            sdl3::mouse::MouseButton::Right => Some(Button::Right),
+            sdl3::mouse::MouseButton::Middle => Some(Button::Middle),
+            sdl3::mouse::MouseButton::X1 => Some(Button::X1),
+            sdl3::mouse::MouseButton::X2 => Some(Button::X2),
+            _ => None,
+        }
+    }
+}
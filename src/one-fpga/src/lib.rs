@@ -1,6 +1,7 @@
 pub use core::Core;
 pub use core::OneFpgaCore;
 
+pub mod archive;
 pub mod core;
 pub mod runner;
 
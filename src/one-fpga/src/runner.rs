@@ -14,6 +14,10 @@ pub enum CoreType {
 
     /// Launch the menu core.
     Menu,
+
+    /// Launch an arcade game from an MRA file, which names the core and
+    /// describes its ROMs.
+    Mra(PathBuf),
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +53,10 @@ impl CoreLaunchInfo<()> {
         Self::new(CoreType::RbfFile(rbf_path))
     }
 
+    pub fn mra(mra_path: PathBuf) -> Self {
+        Self::new(CoreType::Mra(mra_path))
+    }
+
     pub fn menu() -> Self {
         Self::new(CoreType::Menu)
     }
@@ -64,6 +72,12 @@ impl<T> CoreLaunchInfo<T> {
         self
     }
 
+    /// Add a BIOS, sent to the core before the ROM.
+    pub fn with_bios(mut self, bios: Bios) -> Self {
+        self.bios.push(bios);
+        self
+    }
+
     pub fn with_file(mut self, slot: usize, content: Slot) -> Self {
         self.files.insert(slot, content);
         self
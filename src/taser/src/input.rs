@@ -0,0 +1,174 @@
+//! Mapping of movie inputs to MiSTer joysticks.
+//!
+//! Each system has its own controller schema (NES pads, SNES pads with X/Y/L/R,
+//! Genesis 6-button pads...). An [`InputMapping`] translates the names used by
+//! a movie into [`MisterFpgaButtons`], which the core's `jn` list then maps to
+//! its own bits.
+use fce_movie_format::FceInputButton;
+use mister_fpga::core::buttons::{ButtonMap, MisterFpgaButtons};
+use tracing::warn;
+
+/// The number of joysticks a core can receive (see `UserIoJoystick`).
+pub const MAX_JOYSTICKS: usize = 6;
+
+/// The state of all joysticks for a single frame. `None` means the joystick
+/// isn't used by the movie and should be left alone.
+pub type FrameInputs = [Option<ButtonMap>; MAX_JOYSTICKS];
+
+/// A mapping of a system's controller buttons to MiSTer buttons.
+pub trait InputMapping {
+    /// Map a button name (without the player prefix) to a MiSTer button.
+    /// Returns `None` if the button is unknown or has no equivalent.
+    fn button(&self, name: &str) -> Option<MisterFpgaButtons>;
+}
+
+fn dpad(name: &str) -> Option<MisterFpgaButtons> {
+    match name {
+        "Up" => Some(MisterFpgaButtons::DpadUp),
+        "Down" => Some(MisterFpgaButtons::DpadDown),
+        "Left" => Some(MisterFpgaButtons::DpadLeft),
+        "Right" => Some(MisterFpgaButtons::DpadRight),
+        _ => None,
+    }
+}
+
+/// NES and Famicom controllers.
+pub struct NesMapping;
+
+impl InputMapping for NesMapping {
+    fn button(&self, name: &str) -> Option<MisterFpgaButtons> {
+        match name {
+            "A" => Some(MisterFpgaButtons::A),
+            "B" => Some(MisterFpgaButtons::B),
+            "Select" => Some(MisterFpgaButtons::Back),
+            "Start" => Some(MisterFpgaButtons::Start),
+            _ => dpad(name),
+        }
+    }
+}
+
+/// SNES controllers. The MiSTer buttons are named after these, so this is
+/// mostly an identity mapping.
+pub struct SnesMapping;
+
+impl InputMapping for SnesMapping {
+    fn button(&self, name: &str) -> Option<MisterFpgaButtons> {
+        match name {
+            "A" => Some(MisterFpgaButtons::A),
+            "B" => Some(MisterFpgaButtons::B),
+            "X" => Some(MisterFpgaButtons::X),
+            "Y" => Some(MisterFpgaButtons::Y),
+            "L" => Some(MisterFpgaButtons::LeftShoulder),
+            "R" => Some(MisterFpgaButtons::RightShoulder),
+            "Select" => Some(MisterFpgaButtons::Back),
+            "Start" => Some(MisterFpgaButtons::Start),
+            _ => dpad(name),
+        }
+    }
+}
+
+/// Genesis 3 and 6-button controllers. This follows the positions used by
+/// the Genesis core's `jn` list (`Y,B,A,Start,Select,L,X,R`), so that the
+/// bottom row (A, B, C) and top row (X, Y, Z) match the physical layout.
+pub struct GenesisMapping;
+
+impl InputMapping for GenesisMapping {
+    fn button(&self, name: &str) -> Option<MisterFpgaButtons> {
+        match name {
+            "A" => Some(MisterFpgaButtons::Y),
+            "B" => Some(MisterFpgaButtons::B),
+            "C" => Some(MisterFpgaButtons::A),
+            "X" => Some(MisterFpgaButtons::LeftShoulder),
+            "Y" => Some(MisterFpgaButtons::X),
+            "Z" => Some(MisterFpgaButtons::RightShoulder),
+            "Mode" => Some(MisterFpgaButtons::Back),
+            "Start" => Some(MisterFpgaButtons::Start),
+            _ => dpad(name),
+        }
+    }
+}
+
+/// Find the mapping for a BizHawk platform ID (e.g. `NES`, `SNES`, `GEN`).
+pub fn mapping_for_platform(platform: &str) -> Option<&'static dyn InputMapping> {
+    match platform.to_ascii_uppercase().as_str() {
+        "NES" | "FDS" => Some(&NesMapping),
+        "SNES" | "SGB" => Some(&SnesMapping),
+        "GEN" | "GENESIS" | "MD" => Some(&GenesisMapping),
+        _ => None,
+    }
+}
+
+pub fn fce_button_name(button: FceInputButton) -> &'static str {
+    match button {
+        FceInputButton::A => "A",
+        FceInputButton::B => "B",
+        FceInputButton::Select => "Select",
+        FceInputButton::Start => "Start",
+        FceInputButton::Up => "Up",
+        FceInputButton::Down => "Down",
+        FceInputButton::Left => "Left",
+        FceInputButton::Right => "Right",
+    }
+}
+
+/// Builds [`ButtonMap`]s for a frame, from the names of the buttons pressed.
+/// Buttons that the mapping or the core doesn't know about are ignored, with
+/// a warning the first time they are seen.
+pub struct ButtonMapper<'a> {
+    mapping: &'a dyn InputMapping,
+    base_map: ButtonMap,
+    warned: Vec<String>,
+}
+
+impl<'a> ButtonMapper<'a> {
+    pub fn new(mapping: &'a dyn InputMapping, base_map: ButtonMap) -> Self {
+        Self {
+            mapping,
+            base_map,
+            warned: Vec::new(),
+        }
+    }
+
+    pub fn map<'b>(&mut self, pressed: impl IntoIterator<Item = &'b str>) -> ButtonMap {
+        let mut map = self.base_map;
+        map.clear();
+
+        for name in pressed {
+            match self.mapping.button(name) {
+                Some(button) if map.has_button(button) => map.press(button),
+                mapped => {
+                    if !self.warned.iter().any(|w| w == name) {
+                        warn!(
+                            ?name,
+                            ?mapped,
+                            "Button cannot be sent to the core, ignoring."
+                        );
+                        self.warned.push(name.to_string());
+                    }
+                }
+            }
+        }
+
+        map
+    }
+}
+
+#[test]
+fn map_genesis_six_buttons() {
+    let base_map =
+        ButtonMap::map_from_snes_list(&["Y", "B", "A", "Start", "Select", "L", "X", "R"]);
+    let mut mapper = ButtonMapper::new(mapping_for_platform("GEN").unwrap(), base_map);
+
+    // Bits 0-3 are the D-Pad, then A, B, C, Start, Mode, X, Y, Z.
+    assert_eq!(mapper.map(["A", "C"]).value(), 0b0000_0101_0000);
+    assert_eq!(mapper.map(["Up", "Z", "Mode"]).value(), 0b1001_0000_1000);
+}
+
+#[test]
+fn map_snes_ignores_unknown_buttons() {
+    let base_map =
+        ButtonMap::map_from_snes_list(&["A", "B", "X", "Y", "L", "R", "Select", "Start"]);
+    let mut mapper = ButtonMapper::new(&SnesMapping, base_map);
+
+    assert_eq!(mapper.map(["Right", "R", "Power"]).value(), 0b10_0000_0001);
+}
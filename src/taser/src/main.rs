@@ -1,18 +1,22 @@
 use clap::Parser;
 use clap_verbosity_flag::Level as VerbosityLevel;
 use clap_verbosity_flag::Verbosity;
-use fce_movie_format::{FceInputButton, FceInputGamepad};
+use fce_movie_format::FceInputGamepad;
+use input::{ButtonMapper, FrameInputs, InputMapping, MAX_JOYSTICKS};
 use mister_fpga::config::Config;
-use mister_fpga::core::buttons::{ButtonMap, MisterFpgaButtons};
+use mister_fpga::core::buttons::ButtonMap;
 use mister_fpga::core::MisterFpgaCore;
 use mister_fpga::fpga::user_io::UserIoButtonSwitch;
 use one_fpga::Core;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
-use tracing::{debug, error, info, trace, Level};
+use tracing::{debug, error, info, trace, warn, Level};
 use tracing_subscriber::fmt::Subscriber;
 
+mod input;
+mod verify;
+
 /// `taser` is a simple command-line interface to the 1FPGA Mister core
 /// library. It is intended to be used as a standalone application, or as a
 /// testbed for cores.
@@ -46,6 +50,26 @@ struct Flags {
     #[clap(long)]
     wait_inner_frame: Option<humantime::Duration>,
 
+    /// Golden file of framebuffer checksums. The run is verified against it
+    /// and stops at the first frame that differs.
+    #[clap(long, requires = "tas")]
+    golden: Option<PathBuf>,
+
+    /// Record the golden file instead of verifying against it.
+    #[clap(long, requires = "golden")]
+    record_golden: bool,
+
+    /// Only check every N frames. When recording, the checksum of every N
+    /// frames is saved. When verifying, only the frames of the golden file
+    /// that are a multiple of N are checked.
+    #[clap(long, default_value = "1")]
+    checksum_every: usize,
+
+    /// Where to save a screenshot of the first frame that differs from the
+    /// golden file.
+    #[clap(long, requires = "golden")]
+    divergence_screenshot: Option<PathBuf>,
+
     #[command(flatten)]
     pub verbose: Verbosity<clap_verbosity_flag::InfoLevel>,
 }
@@ -113,6 +137,15 @@ fn main() {
         let port0 = *core.gamepad(0).unwrap();
         let frames = read_frames(&tas, port0).expect("Could not read TAS file.");
 
+        let mut verifier = opts.golden.as_ref().map(|golden| {
+            if opts.record_golden {
+                verify::Verifier::record(golden, opts.checksum_every)
+            } else {
+                let golden = verify::GoldenFile::load(golden).expect("Could not read golden file.");
+                verify::Verifier::verify(golden, opts.checksum_every)
+            }
+        });
+
         let trace_is_enabled = tracing::enabled!(Level::TRACE);
 
         const TRACE_EVERY_N_FRAMES: usize = 600;
@@ -146,9 +179,36 @@ fn main() {
         let start = std::time::Instant::now();
         let mut last = start;
 
-        for (frame, (p0, p1)) in frames.into_iter().enumerate() {
+        for (frame, inputs) in frames.into_iter().enumerate() {
             let _ = frame_it.next();
 
+            if let Some(verifier) = verifier.as_mut().filter(|v| v.should_check(frame)) {
+                let checksum = core
+                    .framebuffer()
+                    .frame_crc32()
+                    .expect("Could not read the framebuffer.");
+
+                if let Some(divergence) = verifier.check(frame, checksum) {
+                    error!(
+                        frame = divergence.frame,
+                        expected = %format!("{:08x}", divergence.expected),
+                        actual = %format!("{:08x}", divergence.actual),
+                        "Run diverged from the golden file."
+                    );
+                    if let Some(path) = &opts.divergence_screenshot {
+                        match core.take_screenshot() {
+                            Ok(img) => {
+                                if let Err(e) = img.save(path) {
+                                    error!(?e, "Could not save screenshot.");
+                                }
+                            }
+                            Err(e) => error!(?e, "Could not take screenshot."),
+                        }
+                    }
+                    std::process::exit(1);
+                }
+            }
+
             let wait_to_inner_frame = std::time::Instant::now() + wait_inner_frame;
             while std::time::Instant::now() < wait_to_inner_frame {}
 
@@ -171,11 +231,21 @@ fn main() {
                 last = std::time::Instant::now();
             }
 
-            if let Some(p0) = p0 {
-                core.send_gamepad(0, p0);
+            for (idx, map) in inputs.into_iter().enumerate() {
+                if let Some(map) = map {
+                    core.send_gamepad(idx as u8, map);
+                }
             }
-            if let Some(p1) = p1 {
-                core.send_gamepad(1, p1);
+        }
+
+        if let Some(verifier) = verifier {
+            match verifier.finish() {
+                Ok(n) if opts.record_golden => info!(checksums = n, "Golden file recorded."),
+                Ok(n) => info!(checksums = n, "Run matches the golden file."),
+                Err(e) => {
+                    error!(?e, "Verification failed.");
+                    std::process::exit(1);
+                }
             }
         }
     } else {
@@ -184,32 +254,14 @@ fn main() {
     }
 }
 
-fn fce_button_to_mister(button: FceInputButton) -> MisterFpgaButtons {
-    match button {
-        FceInputButton::A => MisterFpgaButtons::A,
-        FceInputButton::B => MisterFpgaButtons::B,
-        FceInputButton::Select => MisterFpgaButtons::Back,
-        FceInputButton::Start => MisterFpgaButtons::Start,
-        FceInputButton::Up => MisterFpgaButtons::DpadUp,
-        FceInputButton::Down => MisterFpgaButtons::DpadDown,
-        FceInputButton::Left => MisterFpgaButtons::DpadLeft,
-        FceInputButton::Right => MisterFpgaButtons::DpadRight,
-    }
-}
-
-fn fce_gamepad_to_button_map(gamepad: FceInputGamepad, mut map: ButtonMap) -> ButtonMap {
-    map.clear();
-    for button in gamepad.buttons() {
-        map.press(fce_button_to_mister(button));
-    }
-    map
+fn fce_gamepad_to_button_map(gamepad: FceInputGamepad, mapper: &mut ButtonMapper) -> ButtonMap {
+    mapper.map(gamepad.buttons().into_iter().map(input::fce_button_name))
 }
 
 fn read_frames(
     tas_file: impl AsRef<Path>,
     base_map: ButtonMap,
-) -> Result<Vec<(Option<ButtonMap>, Option<ButtonMap>)>, &'static str> {
-    // let base_map = ButtonMap::new();
+) -> Result<Vec<FrameInputs>, &'static str> {
     let tas = tas_file.as_ref();
     match tas.extension().map(|e| e.to_str().unwrap_or_default()) {
         Some("fm2") => {
@@ -217,19 +269,51 @@ fn read_frames(
             // Read the file and decode it.
             let file = std::fs::File::open(tas).expect("Could not open TAS file");
             let fm = fce_movie_format::FceFile::load_stream(BufReader::new(file)).unwrap();
+            let mut mapper = ButtonMapper::new(&input::NesMapping, base_map);
 
             let frames = fm.frames().map(|f| {
-                let p0 = f
-                    .port0
-                    .as_ref()
-                    .and_then(|p| p.as_gamepad())
-                    .map(|buttons| fce_gamepad_to_button_map(*buttons, base_map));
-                let p1 = f
-                    .port1
-                    .as_ref()
-                    .and_then(|p| p.as_gamepad())
-                    .map(|buttons| fce_gamepad_to_button_map(*buttons, base_map));
-                (p0, p1)
+                let mut inputs: FrameInputs = Default::default();
+                for (i, port) in [&f.port0, &f.port1].into_iter().enumerate() {
+                    inputs[i] = port
+                        .as_ref()
+                        .and_then(|p| p.as_gamepad())
+                        .map(|buttons| fce_gamepad_to_button_map(*buttons, &mut mapper));
+                }
+                inputs
+            });
+
+            Ok(frames.collect())
+        }
+        Some("bk2") => {
+            info!("Reading BK2 file: {}", tas.display());
+            let file = std::fs::File::open(tas).expect("Could not open TAS file");
+            let bk2 = bk2_format::Bk2File::load(file).map_err(|e| {
+                error!(?e, "Could not read BK2 file.");
+                "Could not read BK2 file."
+            })?;
+
+            let platform = bk2.header().platform();
+            let mapping: &dyn InputMapping =
+                input::mapping_for_platform(platform).ok_or_else(|| {
+                    error!(?platform, "Unsupported BK2 platform.");
+                    "Unsupported BK2 platform."
+                })?;
+            let mut mapper = ButtonMapper::new(mapping, base_map);
+
+            let players = bk2.inputs().key().players() as usize;
+            if players > MAX_JOYSTICKS {
+                warn!(
+                    players,
+                    "Movie has more players than supported joysticks, ignoring extra players."
+                );
+            }
+
+            let frames = bk2.frames().map(|f| {
+                let mut inputs: FrameInputs = Default::default();
+                for (i, input) in inputs.iter_mut().enumerate().take(players) {
+                    *input = Some(mapper.map(f.pressed(Some(i as u8 + 1))));
+                }
+                inputs
             });
 
             Ok(frames.collect())
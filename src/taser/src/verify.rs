@@ -0,0 +1,220 @@
+//! Verification of TAS runs against a golden file of framebuffer checksums.
+//!
+//! The golden file is a text file with one `<frame> <crc32>` line per
+//! checked frame. Lines starting with `#` are comments.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Checksums of frames, by frame number.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GoldenFile {
+    checksums: BTreeMap<usize, u32>,
+}
+
+impl GoldenFile {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        content.parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| e.to_string())
+    }
+
+    pub fn insert(&mut self, frame: usize, checksum: u32) {
+        self.checksums.insert(frame, checksum);
+    }
+
+    pub fn get(&self, frame: usize) -> Option<u32> {
+        self.checksums.get(&frame).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.checksums.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checksums.is_empty()
+    }
+
+    /// The last frame with a checksum.
+    pub fn last_frame(&self) -> Option<usize> {
+        self.checksums.keys().next_back().copied()
+    }
+}
+
+impl std::str::FromStr for GoldenFile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut checksums = BTreeMap::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (frame, checksum) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("Invalid golden file line {}: {:?}", i + 1, line))?;
+            let frame = frame
+                .parse()
+                .map_err(|e| format!("Invalid frame number on line {}: {}", i + 1, e))?;
+            let checksum = u32::from_str_radix(checksum.trim().trim_start_matches("0x"), 16)
+                .map_err(|e| format!("Invalid checksum on line {}: {}", i + 1, e))?;
+            checksums.insert(frame, checksum);
+        }
+
+        Ok(Self { checksums })
+    }
+}
+
+impl std::fmt::Display for GoldenFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::from("# taser golden file: <frame> <crc32>\n");
+        for (frame, checksum) in &self.checksums {
+            writeln!(out, "{frame} {checksum:08x}")?;
+        }
+        f.write_str(&out)
+    }
+}
+
+/// The first frame whose checksum differs from the golden file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    pub frame: usize,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+enum Mode {
+    Record(PathBuf),
+    Verify(GoldenFile),
+}
+
+/// Records or verifies frame checksums during a run.
+pub struct Verifier {
+    mode: Mode,
+    every: usize,
+    checksums: GoldenFile,
+}
+
+impl Verifier {
+    /// Record checksums, to be saved in `path` by [`Verifier::finish`].
+    pub fn record(path: impl Into<PathBuf>, every: usize) -> Self {
+        Self {
+            mode: Mode::Record(path.into()),
+            every: every.max(1),
+            checksums: GoldenFile::default(),
+        }
+    }
+
+    /// Verify checksums against a golden file.
+    pub fn verify(golden: GoldenFile, every: usize) -> Self {
+        Self {
+            mode: Mode::Verify(golden),
+            every: every.max(1),
+            checksums: GoldenFile::default(),
+        }
+    }
+
+    /// Whether the frame should be checked, every `every` frames. When
+    /// verifying, only frames in the golden file are checked.
+    pub fn should_check(&self, frame: usize) -> bool {
+        frame.is_multiple_of(self.every)
+            && match &self.mode {
+                Mode::Record(_) => true,
+                Mode::Verify(golden) => golden.get(frame).is_some(),
+            }
+    }
+
+    /// Check the checksum of a frame. Returns the divergence if the frame
+    /// does not match the golden file.
+    pub fn check(&mut self, frame: usize, checksum: u32) -> Option<Divergence> {
+        self.checksums.insert(frame, checksum);
+        match &self.mode {
+            Mode::Record(_) => None,
+            Mode::Verify(golden) => golden
+                .get(frame)
+                .filter(|expected| *expected != checksum)
+                .map(|expected| Divergence {
+                    frame,
+                    expected,
+                    actual: checksum,
+                }),
+        }
+    }
+
+    /// Finish the run. When recording, this saves the golden file. When
+    /// verifying, this returns the number of frames that matched, or an
+    /// error if the run ended before the last frame of the golden file.
+    pub fn finish(self) -> Result<usize, String> {
+        match self.mode {
+            Mode::Record(path) => {
+                self.checksums.save(path)?;
+                Ok(self.checksums.len())
+            }
+            Mode::Verify(golden) => {
+                let last = golden
+                    .checksums
+                    .keys()
+                    .rfind(|frame| frame.is_multiple_of(self.every))
+                    .copied();
+                if last > self.checksums.last_frame() {
+                    return Err(format!(
+                        "Run ended before frame {} of the golden file.",
+                        last.unwrap_or_default()
+                    ));
+                }
+                Ok(self.checksums.len())
+            }
+        }
+    }
+}
+
+#[test]
+fn golden_file_roundtrip() {
+    let mut golden = GoldenFile::default();
+    golden.insert(0, 0xDEAD_BEEF);
+    golden.insert(60, 0x0000_0001);
+
+    let parsed: GoldenFile = golden.to_string().parse().unwrap();
+    assert_eq!(parsed, golden);
+    assert_eq!(parsed.last_frame(), Some(60));
+}
+
+#[test]
+fn verifier_reports_first_divergence() {
+    let golden: GoldenFile = "0 00000001\n2 00000003\n4 00000005\n".parse().unwrap();
+    let mut verifier = Verifier::verify(golden, 1);
+
+    assert!(verifier.should_check(0));
+    assert!(!verifier.should_check(1));
+    assert_eq!(verifier.check(0, 1), None);
+    assert_eq!(
+        verifier.check(2, 4),
+        Some(Divergence {
+            frame: 2,
+            expected: 3,
+            actual: 4
+        })
+    );
+    assert!(verifier.finish().is_err());
+}
+
+#[test]
+fn verifier_checks_every_n_frames() {
+    let golden: GoldenFile = "0 00000001\n1 00000002\n2 00000003\n3 00000004\n"
+        .parse()
+        .unwrap();
+    let mut verifier = Verifier::verify(golden, 2);
+
+    assert!(verifier.should_check(0));
+    assert!(!verifier.should_check(1));
+    assert!(verifier.should_check(2));
+    assert!(!verifier.should_check(3));
+    assert_eq!(verifier.check(0, 1), None);
+    assert_eq!(verifier.check(2, 3), None);
+    assert_eq!(verifier.finish(), Ok(2));
+}